@@ -0,0 +1,423 @@
+use {
+    super::{
+        AnimationId, AssetKind, BitmapFontId, BitmapId, BlobId, Data, MaterialId, MaterialInfo,
+        MeshId, PAK_HASH_LEN, PakError, SceneId, Stored, StoredId, anim::Animation, bitmap::Bitmap,
+        bitmap_font::BitmapFont, compression::Compression, decode_data, format::Header, mesh::Mesh,
+        range_read_error, scene::Scene,
+    },
+    log::trace,
+    std::{
+        fmt::Debug,
+        future::Future,
+        io::{Error, SeekFrom},
+        panic::resume_unwind,
+        path::Path,
+        sync::Arc,
+    },
+    tokio::{
+        fs::File,
+        io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, BufReader},
+        task::spawn_blocking,
+    },
+};
+
+/// Bytes first read from the start of a pak, which hold every header without a dictionary.
+const HEADER_PREFIX_LEN: u64 = 256;
+
+/// Asynchronous counterpart of `Pak`, for use with readers driven by a tokio runtime.
+pub trait AsyncPak: Send {
+    /// Gets the kind of asset stored under the given key, if one exists.
+    fn asset_kind(&self, key: impl AsRef<str>) -> Option<AssetKind>;
+
+    /// Gets the pak-unique `AnimationId` corresponding to the given key, if one exists.
+    fn animation_id(&self, key: impl AsRef<str>) -> Option<AnimationId>;
+
+    /// Gets the pak-unique `BitmapFontId` corresponding to the given key, if one exists.
+    fn bitmap_font_id(&self, key: impl AsRef<str>) -> Option<BitmapFontId>;
+
+    /// Gets the pak-unique `BitmapId` corresponding to the given key, if one exists.
+    fn bitmap_id(&self, key: impl AsRef<str>) -> Option<BitmapId>;
+
+    /// Gets the pak-unique `BlobId` corresponding to the given key, if one exists.
+    fn blob_id(&self, key: impl AsRef<str>) -> Option<BlobId>;
+
+    /// Gets the pak-unique `MaterialId` corresponding to the given key, if one exists.
+    fn material_id(&self, key: impl AsRef<str>) -> Option<MaterialId>;
+
+    /// Gets the pak-unique `MeshId` corresponding to the given key, if one exists.
+    fn mesh_id(&self, key: impl AsRef<str>) -> Option<MeshId>;
+
+    /// Gets the pak-unique `SceneId` corresponding to the given key, if one exists.
+    fn scene_id(&self, key: impl AsRef<str>) -> Option<SceneId>;
+
+    /// Gets the corresponding animation for the given ID.
+    fn read_animation_id(
+        &mut self,
+        id: impl Into<AnimationId>,
+    ) -> impl Future<Output = Result<Animation, PakError>> + Send;
+
+    /// Reads the corresponding bitmap font for the given ID.
+    fn read_bitmap_font_id(
+        &mut self,
+        id: impl Into<BitmapFontId>,
+    ) -> impl Future<Output = Result<BitmapFont, PakError>> + Send;
+
+    /// Reads the corresponding bitmap for the given ID.
+    fn read_bitmap_id(
+        &mut self,
+        id: impl Into<BitmapId>,
+    ) -> impl Future<Output = Result<Bitmap, PakError>> + Send;
+
+    /// Gets the corresponding blob for the given ID.
+    fn read_blob_id(
+        &mut self,
+        id: impl Into<BlobId>,
+    ) -> impl Future<Output = Result<Vec<u8>, PakError>> + Send;
+
+    /// Gets the material for the given ID.
+    fn read_material_id(&self, id: impl Into<MaterialId>) -> Option<MaterialInfo>;
+
+    /// Gets the corresponding mesh for the given ID.
+    fn read_mesh_id(
+        &mut self,
+        id: impl Into<MeshId>,
+    ) -> impl Future<Output = Result<Mesh, PakError>> + Send;
+
+    /// Gets the corresponding scene for the given ID.
+    fn read_scene_id(
+        &mut self,
+        id: impl Into<SceneId>,
+    ) -> impl Future<Output = Result<Scene, PakError>> + Send;
+
+    /// Gets the material corresponding to the given key, if one exists.
+    fn read_material(&self, key: impl AsRef<str>) -> Option<MaterialInfo> {
+        if let Some(h) = self.material_id(key) {
+            self.read_material_id(h)
+        } else {
+            None
+        }
+    }
+
+    /// Gets the animation corresponding to the given key.
+    fn read_animation(
+        &mut self,
+        key: impl AsRef<str>,
+    ) -> impl Future<Output = Result<Animation, PakError>> + Send {
+        let key = key.as_ref();
+        let id = self
+            .animation_id(key)
+            .ok_or_else(|| PakError::missing_key(key, AssetKind::Animation, self.asset_kind(key)));
+
+        async move { self.read_animation_id(id?).await }
+    }
+
+    /// Gets the bitmap font corresponding to the given key.
+    fn read_bitmap_font(
+        &mut self,
+        key: impl AsRef<str>,
+    ) -> impl Future<Output = Result<BitmapFont, PakError>> + Send {
+        let key = key.as_ref();
+        let id = self
+            .bitmap_font_id(key)
+            .ok_or_else(|| PakError::missing_key(key, AssetKind::BitmapFont, self.asset_kind(key)));
+
+        async move { self.read_bitmap_font_id(id?).await }
+    }
+
+    /// Gets the bitmap corresponding to the given key.
+    fn read_bitmap(
+        &mut self,
+        key: impl AsRef<str>,
+    ) -> impl Future<Output = Result<Bitmap, PakError>> + Send {
+        let key = key.as_ref();
+        let id = self
+            .bitmap_id(key)
+            .ok_or_else(|| PakError::missing_key(key, AssetKind::Bitmap, self.asset_kind(key)));
+
+        async move { self.read_bitmap_id(id?).await }
+    }
+
+    /// Gets the blob corresponding to the given key.
+    fn read_blob(
+        &mut self,
+        key: impl AsRef<str>,
+    ) -> impl Future<Output = Result<Vec<u8>, PakError>> + Send {
+        let key = key.as_ref();
+        let id = self
+            .blob_id(key)
+            .ok_or_else(|| PakError::missing_key(key, AssetKind::Blob, self.asset_kind(key)));
+
+        async move { self.read_blob_id(id?).await }
+    }
+
+    /// Gets the mesh corresponding to the given key.
+    fn read_mesh(
+        &mut self,
+        key: impl AsRef<str>,
+    ) -> impl Future<Output = Result<Mesh, PakError>> + Send {
+        let key = key.as_ref();
+        let id = self
+            .mesh_id(key)
+            .ok_or_else(|| PakError::missing_key(key, AssetKind::Mesh, self.asset_kind(key)));
+
+        async move { self.read_mesh_id(id?).await }
+    }
+
+    /// Gets the scene corresponding to the given key.
+    fn read_scene(
+        &mut self,
+        key: impl AsRef<str>,
+    ) -> impl Future<Output = Result<Scene, PakError>> + Send {
+        let key = key.as_ref();
+        let id = self
+            .scene_id(key)
+            .ok_or_else(|| PakError::missing_key(key, AssetKind::Scene, self.asset_kind(key)));
+
+        async move { self.read_scene_id(id?).await }
+    }
+}
+
+/// Asynchronous counterpart of `PakBuf` which reads from a tokio stream.
+///
+/// Assets are decoded on the blocking thread pool of the runtime, so reads must be awaited within
+/// a tokio runtime.
+#[derive(Debug)]
+pub struct AsyncPakBuf {
+    compression: Option<Compression>,
+    data: Data,
+    dictionary: Option<Arc<[u8]>>,
+    reader: Box<dyn AsyncStream>,
+    verify: bool,
+}
+
+impl AsyncPakBuf {
+    pub fn animation_count(&self) -> usize {
+        self.data.anims.len()
+    }
+
+    pub fn bitmap_count(&self) -> usize {
+        self.data.bitmaps.len()
+    }
+
+    pub fn bitmap_font_count(&self) -> usize {
+        self.data.bitmap_fonts.len()
+    }
+
+    pub fn blob_count(&self) -> usize {
+        self.data.blobs.len()
+    }
+
+    /// Reads and decodes the asset with the given ID.
+    async fn read<I>(&mut self, id: I) -> Result<I::Asset, PakError>
+    where
+        I: StoredId,
+        I::Asset: Send + 'static,
+    {
+        let stored = self.data.stored(id, self.compression)?;
+        let buf = self.read_stored(stored).await?;
+
+        // Decompressing and decoding large assets would stall the runtime worker
+        let dictionary = self.dictionary.clone();
+        let decoded =
+            spawn_blocking(move || decode_data(stored.compression, dictionary.as_deref(), &buf));
+
+        match decoded.await {
+            Ok(res) => res,
+            Err(err) if err.is_panic() => resume_unwind(err.into_panic()),
+            Err(err) => Err(Error::other(err).into()),
+        }
+    }
+
+    /// Reads the stored bytes of one asset, checking them if verification is enabled.
+    async fn read_stored(&mut self, stored: Stored) -> Result<Vec<u8>, PakError> {
+        let Stored { len, pos, .. } = stored;
+
+        trace!("Read data: {len} bytes ({pos}..{})", pos + len as u64);
+
+        // Create a zero-filled buffer
+        let mut buf = vec![0; len];
+
+        // Read the data into our buffer
+        self.reader.seek(SeekFrom::Start(pos)).await?;
+        self.reader
+            .read_exact(&mut buf)
+            .await
+            .map_err(range_read_error)?;
+
+        if self.verify {
+            stored.verify(&buf)?;
+        }
+
+        Ok(buf)
+    }
+
+    pub async fn from_stream(mut stream: impl AsyncStream + 'static) -> Result<Self, PakError> {
+        // Read enough of the start of the stream to decode the magic bytes and compression
+        let stream_end = stream.seek(SeekFrom::End(0)).await?;
+        let prefix_len = stream_end
+            .saturating_sub(PAK_HASH_LEN as u64)
+            .min(HEADER_PREFIX_LEN);
+        let mut prefix = vec![0; prefix_len as usize];
+        stream.seek(SeekFrom::Start(0)).await?;
+        stream.read_exact(&mut prefix).await?;
+
+        // Headers which store a dictionary do not fit within the prefix
+        if let Some(header_len) = Header::encoded_len(&prefix)
+            && header_len > prefix_len
+        {
+            let header_len = header_len.min(stream_end);
+            prefix.resize(
+                usize::try_from(header_len).map_err(|_| PakError::TruncatedRange)?,
+                0,
+            );
+            stream
+                .read_exact(&mut prefix[prefix_len as usize..])
+                .await
+                .map_err(range_read_error)?;
+        }
+
+        let header = Header::read(&mut prefix.as_slice())?;
+
+        // Read the main data, excluding the hash trailer. The trailer is not validated here.
+        let data_len = header.data_len(stream_end)?;
+        let mut buf = vec![0; usize::try_from(data_len).map_err(|_| PakError::TruncatedRange)?];
+        stream.seek(SeekFrom::Start(header.skip)).await?;
+        stream
+            .read_exact(&mut buf)
+            .await
+            .map_err(range_read_error)?;
+
+        let data = header.read_data(buf.as_slice())?;
+
+        trace!(
+            "Read header: v{} {} bytes ({} keys)",
+            header.version,
+            data_len,
+            data.ids.len()
+        );
+
+        Ok(Self {
+            compression: header.compression,
+            data,
+            dictionary: header.dictionary.map(Arc::from),
+            reader: Box::new(stream),
+            verify: false,
+        })
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.data.ids.keys().map(|key| key.as_str())
+    }
+
+    pub fn mesh_count(&self) -> usize {
+        self.data.meshes.len()
+    }
+
+    pub fn material_count(&self) -> usize {
+        self.data.materials.len()
+    }
+
+    /// Opens the given path and decodes a `Pak`.
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self, PakError> {
+        let file = File::open(path).await?;
+
+        Self::from_stream(BufReader::new(file)).await
+    }
+
+    pub fn scene_count(&self) -> usize {
+        self.data.scenes.len()
+    }
+
+    /// Sets whether each asset is checked against its stored checksum when read.
+    ///
+    /// Paks written before checksums were stored are read without verification.
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+}
+
+impl AsyncPak for AsyncPakBuf {
+    fn asset_kind(&self, key: impl AsRef<str>) -> Option<AssetKind> {
+        self.data.id(key.as_ref()).map(|id| id.kind())
+    }
+
+    fn animation_id(&self, key: impl AsRef<str>) -> Option<AnimationId> {
+        self.data.id(key.as_ref())?.as_animation()
+    }
+
+    fn bitmap_font_id(&self, key: impl AsRef<str>) -> Option<BitmapFontId> {
+        self.data.id(key.as_ref())?.as_bitmap_font()
+    }
+
+    fn bitmap_id(&self, key: impl AsRef<str>) -> Option<BitmapId> {
+        self.data.id(key.as_ref())?.as_bitmap()
+    }
+
+    fn blob_id(&self, key: impl AsRef<str>) -> Option<BlobId> {
+        self.data.id(key.as_ref())?.as_blob()
+    }
+
+    fn material_id(&self, key: impl AsRef<str>) -> Option<MaterialId> {
+        self.data.id(key.as_ref())?.as_material()
+    }
+
+    fn mesh_id(&self, key: impl AsRef<str>) -> Option<MeshId> {
+        self.data.id(key.as_ref())?.as_mesh()
+    }
+
+    fn scene_id(&self, key: impl AsRef<str>) -> Option<SceneId> {
+        self.data.id(key.as_ref())?.as_scene()
+    }
+
+    fn read_animation_id(
+        &mut self,
+        id: impl Into<AnimationId>,
+    ) -> impl Future<Output = Result<Animation, PakError>> + Send {
+        self.read(id.into())
+    }
+
+    fn read_bitmap_font_id(
+        &mut self,
+        id: impl Into<BitmapFontId>,
+    ) -> impl Future<Output = Result<BitmapFont, PakError>> + Send {
+        self.read(id.into())
+    }
+
+    fn read_bitmap_id(
+        &mut self,
+        id: impl Into<BitmapId>,
+    ) -> impl Future<Output = Result<Bitmap, PakError>> + Send {
+        self.read(id.into())
+    }
+
+    fn read_blob_id(
+        &mut self,
+        id: impl Into<BlobId>,
+    ) -> impl Future<Output = Result<Vec<u8>, PakError>> + Send {
+        self.read(id.into())
+    }
+
+    fn read_material_id(&self, id: impl Into<MaterialId>) -> Option<MaterialInfo> {
+        self.data.materials.get(id.into().0).copied()
+    }
+
+    fn read_mesh_id(
+        &mut self,
+        id: impl Into<MeshId>,
+    ) -> impl Future<Output = Result<Mesh, PakError>> + Send {
+        self.read(id.into())
+    }
+
+    fn read_scene_id(
+        &mut self,
+        id: impl Into<SceneId>,
+    ) -> impl Future<Output = Result<Scene, PakError>> + Send {
+        self.read(id.into())
+    }
+}
+
+/// A tokio stream which an `AsyncPakBuf` may read from.
+pub trait AsyncStream: AsyncRead + AsyncSeek + Debug + Send + Unpin {}
+
+impl<T> AsyncStream for T where T: AsyncRead + AsyncSeek + Debug + Send + Unpin {}
@@ -0,0 +1,199 @@
+use {
+    anyhow::{Context, bail},
+    clap::{Parser, Subcommand},
+    pak::{AssetKind, Pak, PakBuf, buf::Baker},
+    std::{
+        fs::write,
+        io::{Write, stdout},
+        path::{Path, PathBuf},
+    },
+};
+
+/// Bakes and inspects `.pak` files.
+#[derive(Debug, Parser)]
+#[command(about, version)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Bakes a content file into a `.pak` file.
+    Bake {
+        /// Main `.pak` configuration file.
+        src: PathBuf,
+        /// Path of the `.pak` file to write.
+        dst: PathBuf,
+        /// Asset root; defaults to the directory of the content file.
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Bakes every asset it can and then reports all failures.
+        #[arg(long)]
+        keep_going: bool,
+        /// Number of threads used to bake assets; defaults to the number of CPU cores.
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+
+    /// Writes the blob or bitmap stored under a key; bitmaps are written as PNG images.
+    Extract {
+        pak: PathBuf,
+        key: String,
+        /// Path of the file to write; defaults to standard output.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// Prints the compression and asset counts of a `.pak` file.
+    Info { pak: PathBuf },
+
+    /// Prints the stored range, sizes and details of each asset of a `.pak` file.
+    Inspect {
+        pak: PathBuf,
+        /// Prints the report as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Prints the key, kind and stored size of each asset of a `.pak` file.
+    List { pak: PathBuf },
+
+    /// Prints the source files used to bake a content file.
+    Sources {
+        /// Main `.pak` configuration file.
+        src: PathBuf,
+        /// Asset root; defaults to the directory of the content file.
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+
+    /// Writes the assets of a `.pak` file back to PNG, glTF, TOML and raw files.
+    Unpack {
+        pak: PathBuf,
+        /// Directory to write the files to.
+        dir: PathBuf,
+        /// Unpacks only the asset stored under this key.
+        #[arg(long)]
+        key: Option<String>,
+    },
+
+    /// Checks a `.pak` file against its hash trailer.
+    Verify { pak: PathBuf },
+}
+
+fn main() -> anyhow::Result<()> {
+    match Args::parse().command {
+        Command::Bake {
+            src,
+            dst,
+            dir,
+            keep_going,
+            threads,
+        } => {
+            let mut baker = Baker::new()
+                .with_asset_dir(dir.unwrap_or_else(|| parent(&src)))
+                .with_cargo_watches(false)
+                .with_keep_going(keep_going);
+            if let Some(threads) = threads {
+                baker = baker.with_threads(threads);
+            }
+
+            baker.bake(&src, &dst)?;
+
+            println!("Baked {}", dst.display());
+        }
+        Command::Extract { pak, key, output } => {
+            let data = extract(&mut open(&pak)?, &key)?;
+
+            if let Some(output) = output {
+                write(&output, data).with_context(|| format!("writing {}", output.display()))?;
+            } else {
+                stdout().write_all(&data)?;
+            }
+        }
+        Command::Info { pak } => {
+            let pak = open(&pak)?;
+
+            match pak.compression() {
+                Some(compression) => println!("compression: {compression:?}"),
+                None => println!("compression: none"),
+            }
+
+            println!("keys: {}", pak.keys().count());
+            println!("animations: {}", pak.animation_count());
+            println!("bitmap fonts: {}", pak.bitmap_font_count());
+            println!("bitmaps: {}", pak.bitmap_count());
+            println!("blobs: {}", pak.blob_count());
+            println!("materials: {}", pak.material_count());
+            println!("meshes: {}", pak.mesh_count());
+            println!("scenes: {}", pak.scene_count());
+        }
+        Command::Inspect { pak, json } => {
+            let report = open(&pak)?.report()?;
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{report}");
+            }
+        }
+        Command::List { pak } => {
+            let pak = open(&pak)?;
+
+            for key in pak.keys() {
+                let kind = pak.asset_kind(key).expect("missing key");
+
+                match pak.stored_len(key) {
+                    Some(len) => println!("{key}\t{kind}\t{len}"),
+                    None => println!("{key}\t{kind}\t-"),
+                }
+            }
+        }
+        Command::Sources { src, dir } => {
+            let dir = dir.unwrap_or_else(|| parent(&src));
+
+            for path in PakBuf::source_files_with_dir(&src, dir)? {
+                println!("{}", path.display());
+            }
+        }
+        Command::Unpack { pak, dir, key } => {
+            let mut pak = open(&pak)?;
+            let paths = match key {
+                Some(key) => vec![pak.unpack_key(&key, &dir)?],
+                None => pak.unpack(&dir)?,
+            };
+
+            for path in paths {
+                println!("{}", path.display());
+            }
+        }
+        Command::Verify { pak } => {
+            if !open(&pak)?.validate_hash()? {
+                bail!("{} does not match its hash", pak.display());
+            }
+
+            println!("{} is valid", pak.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the stored blob, or the stored bitmap encoded as a PNG image.
+fn extract(pak: &mut PakBuf, key: &str) -> anyhow::Result<Vec<u8>> {
+    match pak.asset_kind(key) {
+        Some(AssetKind::Blob) => Ok(pak.read_blob(key)?),
+        Some(AssetKind::Bitmap) => pak.read_bitmap(key)?.to_png(),
+        Some(kind) => bail!("`{key}` is a {kind}; only blobs and bitmaps may be extracted"),
+        None => bail!("key `{key}` not found"),
+    }
+}
+
+fn open(path: &Path) -> anyhow::Result<PakBuf> {
+    PakBuf::open(path).with_context(|| format!("opening {}", path.display()))
+}
+
+fn parent(path: &Path) -> PathBuf {
+    path.parent().map(Path::to_path_buf).unwrap_or_default()
+}
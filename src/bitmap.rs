@@ -1,4 +1,7 @@
-use serde::{Deserialize, Deserializer, Serialize, de::Error};
+use {
+    serde::{Deserialize, Deserializer, Serialize, de::Error},
+    std::borrow::Cow,
+};
 
 /// Holds a `Bitmap` in a `.pak` file. For data transport only.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
@@ -31,16 +34,7 @@ impl<'de> Deserialize<'de> for Bitmap {
         }
 
         let data = BitmapData::deserialize(deserializer)?;
-        if data.width == 0 {
-            return Err(D::Error::custom("bitmap width must be greater than zero"));
-        }
-
-        let row_len = data.width as usize * data.fmt.byte_len();
-        if !data.pixels.len().is_multiple_of(row_len) {
-            return Err(D::Error::custom(
-                "bitmap pixel byte length is not a whole number of rows",
-            ));
-        }
+        check_pixels(data.fmt, data.width, data.pixels.len())?;
 
         Ok(Self {
             color: data.color,
@@ -52,6 +46,24 @@ impl<'de> Deserialize<'de> for Bitmap {
     }
 }
 
+/// Checks that deserialized pixels hold a whole number of rows of a non-zero width.
+fn check_pixels<E>(fmt: BitmapFormat, width: u32, len: usize) -> Result<(), E>
+where
+    E: Error,
+{
+    if width == 0 {
+        return Err(E::custom("bitmap width must be greater than zero"));
+    }
+
+    if !len.is_multiple_of(width as usize * fmt.byte_len()) {
+        return Err(E::custom(
+            "bitmap pixel byte length is not a whole number of rows",
+        ));
+    }
+
+    Ok(())
+}
+
 impl Bitmap {
     /// Pixel data must be tightly packed (no additional stride)
     pub fn new(
@@ -138,9 +150,114 @@ impl Bitmap {
     }
 }
 
+/// A `Bitmap` which may borrow its pixels directly from the storage it was read from.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BitmapView<'a> {
+    color: BitmapColor,
+    fmt: BitmapFormat,
+    mip_levels: u32,
+    pixels: Cow<'a, [u8]>,
+    width: u32,
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for BitmapView<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct BitmapData<'a> {
+            color: BitmapColor,
+            fmt: BitmapFormat,
+            mip_levels: u32,
+
+            #[serde(borrow)]
+            pixels: &'a [u8],
+
+            width: u32,
+        }
+
+        let data = BitmapData::deserialize(deserializer)?;
+        check_pixels(data.fmt, data.width, data.pixels.len())?;
+
+        Ok(Self {
+            color: data.color,
+            fmt: data.fmt,
+            mip_levels: data.mip_levels,
+            pixels: Cow::Borrowed(data.pixels),
+            width: data.width,
+        })
+    }
+}
+
+impl BitmapView<'_> {
+    pub fn color(&self) -> BitmapColor {
+        self.color
+    }
+
+    /// Gets the dimensions, in pixels, of this `BitmapView`.
+    pub fn extent(&self) -> (u32, u32) {
+        (self.width(), self.height())
+    }
+
+    /// Gets a description of the number of channels contained in this `BitmapView`.
+    pub fn format(&self) -> BitmapFormat {
+        self.fmt
+    }
+
+    pub fn height(&self) -> u32 {
+        (self.pixels.len() / self.stride()) as u32
+    }
+
+    /// Returns `true` if the pixels are borrowed instead of decoded into a new buffer.
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self.pixels, Cow::Borrowed(_))
+    }
+
+    pub fn mip_levels(&self) -> u32 {
+        self.mip_levels
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Bytes per row of pixels (there is no padding)
+    pub fn stride(&self) -> usize {
+        self.width as usize * self.fmt.byte_len()
+    }
+
+    /// Copies the pixels, if borrowed, into an owned `Bitmap`.
+    pub fn to_bitmap(&self) -> Bitmap {
+        Bitmap {
+            color: self.color,
+            fmt: self.fmt,
+            mip_levels: self.mip_levels,
+            pixels: self.pixels.to_vec(),
+            width: self.width,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+}
+
+impl From<Bitmap> for BitmapView<'static> {
+    fn from(bitmap: Bitmap) -> Self {
+        Self {
+            color: bitmap.color,
+            fmt: bitmap.fmt,
+            mip_levels: bitmap.mip_levels,
+            pixels: Cow::Owned(bitmap.pixels),
+            width: bitmap.width,
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::bitmap::{Bitmap, BitmapColor, BitmapFormat};
+    use crate::bitmap::{Bitmap, BitmapColor, BitmapFormat, BitmapView};
 
     #[test]
     fn pixels_as_format_drops_extra_channels() {
@@ -190,6 +307,24 @@ mod test {
         let _ = Bitmap::new(BitmapColor::Srgb, BitmapFormat::Rgb, 1, 1, [1, 2, 3, 4]);
     }
 
+    #[test]
+    fn bitmap_view_borrows_pixels() {
+        let bitmap = Bitmap::new(BitmapColor::Linear, BitmapFormat::Rg, 2, 1, [1, 2, 3, 4]);
+        let mut encoded = Vec::new();
+        bincode::serde::encode_into_std_write(&bitmap, &mut encoded, bincode::config::legacy())
+            .unwrap();
+
+        let (view, _) = bincode::serde::borrow_decode_from_slice::<BitmapView, _>(
+            &encoded,
+            bincode::config::legacy(),
+        )
+        .unwrap();
+
+        assert!(view.is_borrowed());
+        assert_eq!(view.extent(), (2, 1));
+        assert_eq!(view.to_bitmap(), bitmap);
+    }
+
     #[test]
     fn deserialize_rejects_zero_width() {
         let invalid = Bitmap {
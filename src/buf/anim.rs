@@ -1,8 +1,14 @@
 use {
-    super::{Asset, Canonicalize, Writer, file_key},
+    super::{
+        Asset, Canonicalize, Writer,
+        cache::{BakeCache, gltf_sources},
+        content::CompressionType,
+        file_key,
+    },
     crate::{
         AnimationId,
         anim::{Animation, Channel, Outputs},
+        compression::Compression,
     },
     anyhow::Context as _,
     gltf::{
@@ -12,7 +18,7 @@ use {
         },
         import,
     },
-    log::{Level::Debug, debug, info, log_enabled, warn},
+    log::{Level::Debug, debug, info, log_enabled},
     parking_lot::Mutex,
     serde::Deserialize,
     std::{
@@ -26,6 +32,7 @@ use {
 /// Holds a description of `.glb` or `.gltf` mesh animations.
 #[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
 pub struct AnimationAsset {
+    compression: Option<CompressionType>,
     name: Option<String>,
     src: Option<PathBuf>,
 
@@ -35,12 +42,15 @@ pub struct AnimationAsset {
 
 impl AnimationAsset {
     /// Reads and processes animation source files into an existing `.pak` file buffer.
+    ///
+    /// The animation is compressed using `compression` unless it chooses its own compression.
     #[allow(unused)]
     pub(super) fn bake(
         &self,
         writer: &Arc<Mutex<Writer>>,
         project_dir: impl AsRef<Path>,
         path: impl AsRef<Path>,
+        compression: Option<Compression>,
     ) -> anyhow::Result<AnimationId> {
         let src = self
             .src()
@@ -57,146 +67,150 @@ impl AnimationAsset {
         let key = file_key(&project_dir, &path);
         info!("Baking animation: {}", key);
 
-        let name = self.name();
-        let (doc, bufs, _) = import(src).context("Importing animation source")?;
+        let anim = BakeCache::get_or_bake(writer, &asset, &gltf_sources(src), || {
+            let name = self.name();
+            let (doc, bufs, _) = import(src).context("Importing animation source")?;
 
-        let available_anim_names = doc
-            .animations()
-            .map(|anim| anim.name().unwrap_or("<unnamed>").to_owned())
-            .collect::<Vec<_>>();
+            let available_anim_names = doc
+                .animations()
+                .map(|anim| anim.name().unwrap_or("<unnamed>").to_owned())
+                .collect::<Vec<_>>();
 
-        if log_enabled!(Debug) {
-            for name in &available_anim_names {
-                debug!("Found animation '{}'", name);
+            if log_enabled!(Debug) {
+                for name in &available_anim_names {
+                    debug!("Found animation '{}'", name);
+                }
             }
-        }
 
-        let mut anim = doc.animations().find(|anim| name == anim.name());
-        if anim.is_none() && name.is_none() {
-            anim = doc.animations().next();
-        }
-
-        let anim = anim.ok_or_else(|| {
-            let available = if available_anim_names.is_empty() {
-                "none".to_owned()
-            } else {
-                available_anim_names.join(", ")
-            };
-
-            if let Some(name) = name {
-                anyhow::anyhow!(
-                    "animation '{name}' not found in {}; available animations: {available}",
-                    src.display()
-                )
-            } else {
-                anyhow::anyhow!(
-                    "no animation found in {}; available animations: {available}",
-                    src.display()
-                )
-            }
-        })?;
-        let exclude = self
-            .exclude()
-            .unwrap_or_default()
-            .iter()
-            .map(|s| s.as_str())
-            .collect::<HashSet<_>>();
-        let mut channels = vec![];
-        let mut channels_used = HashSet::new();
-
-        'channel: for channel in anim.channels() {
-            let name = if let Some(name) = channel.target().node().name() {
-                name
-            } else {
-                warn!("Unnamed channel");
-
-                continue;
-            };
-
-            if exclude.contains(name) {
-                continue;
+            let mut anim = doc.animations().find(|anim| name == anim.name());
+            if anim.is_none() && name.is_none() {
+                anim = doc.animations().next();
             }
 
-            let data = channel.reader(|buf| bufs.get(buf.index()).map(|data| &*data.0));
-            let inputs = data
-                .read_inputs()
-                .context("reading animation inputs")?
-                .map(|input| Duration::from_secs_f32(input).as_millis() as u32)
-                .collect::<Vec<_>>();
-            if inputs.is_empty() {
-                warn!("Empty channel data");
-
-                continue;
-            }
+            let anim = anim.ok_or_else(|| {
+                let available = if available_anim_names.is_empty() {
+                    "none".to_owned()
+                } else {
+                    available_anim_names.join(", ")
+                };
+
+                if let Some(name) = name {
+                    anyhow::anyhow!(
+                        "animation '{name}' not found in {}; available animations: {available}",
+                        src.display()
+                    )
+                } else {
+                    anyhow::anyhow!(
+                        "no animation found in {}; available animations: {available}",
+                        src.display()
+                    )
+                }
+            })?;
+            let exclude = self
+                .exclude()
+                .unwrap_or_default()
+                .iter()
+                .map(|s| s.as_str())
+                .collect::<HashSet<_>>();
+            let mut channels = vec![];
+            let mut channels_used = HashSet::new();
+
+            'channel: for channel in anim.channels() {
+                let name = if let Some(name) = channel.target().node().name() {
+                    name
+                } else {
+                    Writer::warn(writer, "Unnamed channel");
 
-            // Assure increasing sort
-            {
-                let mut input = inputs[0];
-                for val in inputs.iter().skip(1).copied() {
-                    if val > input {
-                        input = val
-                    } else {
-                        warn!("Unsorted input data");
+                    continue;
+                };
 
-                        continue 'channel;
-                    }
+                if exclude.contains(name) {
+                    continue;
                 }
-            }
 
-            let outputs = match data.read_outputs().context("reading animation outputs")? {
-                ReadOutputs::Rotations(Rotations::F32(rotations)) => {
-                    Outputs::Rotations(rotations.collect())
+                let data = channel.reader(|buf| bufs.get(buf.index()).map(|data| &*data.0));
+                let inputs = data
+                    .read_inputs()
+                    .context("reading animation inputs")?
+                    .map(|input| Duration::from_secs_f32(input).as_millis() as u32)
+                    .collect::<Vec<_>>();
+                if inputs.is_empty() {
+                    Writer::warn(writer, "Empty channel data");
+
+                    continue;
                 }
-                ReadOutputs::Scales(scales) => Outputs::Scales(scales.collect()),
-                ReadOutputs::Translations(translations) => {
-                    Outputs::Translations(translations.collect())
+
+                // Assure increasing sort
+                {
+                    let mut input = inputs[0];
+                    for val in inputs.iter().skip(1).copied() {
+                        if val > input {
+                            input = val
+                        } else {
+                            Writer::warn(writer, "Unsorted input data");
+
+                            continue 'channel;
+                        }
+                    }
                 }
-                _ => {
-                    warn!("Unsupported morph target channel");
 
-                    continue;
+                let outputs = match data.read_outputs().context("reading animation outputs")? {
+                    ReadOutputs::Rotations(Rotations::F32(rotations)) => {
+                        Outputs::Rotations(rotations.collect())
+                    }
+                    ReadOutputs::Scales(scales) => Outputs::Scales(scales.collect()),
+                    ReadOutputs::Translations(translations) => {
+                        Outputs::Translations(translations.collect())
+                    }
+                    _ => {
+                        Writer::warn(writer, "Unsupported morph target channel");
+
+                        continue;
+                    }
+                };
+
+                #[derive(Eq, Hash, PartialEq)]
+                enum ChannelType {
+                    Rotation,
+                    Scale,
+                    Translation,
                 }
-            };
 
-            #[derive(Eq, Hash, PartialEq)]
-            enum ChannelType {
-                Rotation,
-                Scale,
-                Translation,
-            }
+                let channel_ty = match &outputs {
+                    Outputs::Rotations(rotations) => ChannelType::Rotation,
+                    Outputs::Scales(scales) => ChannelType::Scale,
+                    Outputs::Translations(translations) => ChannelType::Translation,
+                };
 
-            let channel_ty = match &outputs {
-                Outputs::Rotations(rotations) => ChannelType::Rotation,
-                Outputs::Scales(scales) => ChannelType::Scale,
-                Outputs::Translations(translations) => ChannelType::Translation,
-            };
+                if !channels_used.insert((name, channel_ty)) {
+                    Writer::warn(writer, "Duplicate channels found");
 
-            if !channels_used.insert((name, channel_ty)) {
-                warn!("Duplicate channels found");
+                    continue;
+                }
 
-                continue;
-            }
+                let outputs_len = match &outputs {
+                    Outputs::Rotations(rotations) => rotations.len(),
+                    Outputs::Scales(scales) => scales.len(),
+                    Outputs::Translations(translations) => translations.len(),
+                };
+                let sampler = channel.sampler();
+                let interpolation = sampler.interpolation();
+                let expected_outputs = match interpolation {
+                    GltfInterpolation::Linear | GltfInterpolation::Step => inputs.len(),
+                    GltfInterpolation::CubicSpline => inputs.len() * 3,
+                };
+
+                if outputs_len != expected_outputs {
+                    Writer::warn(writer, "Invalid output data");
+
+                    continue;
+                }
 
-            let outputs_len = match &outputs {
-                Outputs::Rotations(rotations) => rotations.len(),
-                Outputs::Scales(scales) => scales.len(),
-                Outputs::Translations(translations) => translations.len(),
-            };
-            let sampler = channel.sampler();
-            let interpolation = sampler.interpolation();
-            let expected_outputs = match interpolation {
-                GltfInterpolation::Linear | GltfInterpolation::Step => inputs.len(),
-                GltfInterpolation::CubicSpline => inputs.len() * 3,
-            };
-
-            if outputs_len != expected_outputs {
-                warn!("Invalid output data");
-
-                continue;
+                channels.push(Channel::new(name, interpolation, inputs, outputs)?);
             }
 
-            channels.push(Channel::new(name, interpolation, inputs, outputs)?);
-        }
+            Ok(Animation::new(channels))
+        })?;
 
         let mut writer = writer.lock();
         if let Some(id) = writer.ctx.get(&asset) {
@@ -205,7 +219,8 @@ impl AnimationAsset {
                 .context("asset context returned non-animation id");
         }
 
-        let id = writer.push_animation(Animation::new(channels), Some(key));
+        let compression = writer.asset_compression(self.compression, compression);
+        let id = writer.push_animation(anim, Some(key), compression);
         writer.ctx.insert(asset, id.into());
 
         Ok(id)
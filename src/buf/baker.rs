@@ -0,0 +1,457 @@
+use {
+    super::{
+        BakeFailure, BakeFailures, Canonicalize,
+        asset::Asset,
+        bitmap::BitmapAsset,
+        blob::BlobAsset,
+        cache::BakeCache,
+        content::CompressionType,
+        mesh::MeshAsset,
+        observer::{BakeEvent, BakeObserver},
+        parent,
+        writer::Writer,
+    },
+    crate::compression::Compression,
+    anyhow::Context,
+    log::info,
+    parking_lot::Mutex,
+    std::{
+        fmt::{Debug, Formatter},
+        fs::create_dir_all,
+        io::{Read, Seek, Write},
+        path::{Path, PathBuf},
+        sync::Arc,
+        time::Instant,
+    },
+    tokio::{
+        runtime::{Builder, Runtime},
+        task::JoinHandle,
+    },
+};
+
+/// Reports that a selected asset of a content file has been processed.
+#[derive(Clone, Copy, Debug)]
+pub struct BakeProgress<'a> {
+    /// The asset file which was processed.
+    pub path: &'a Path,
+    /// The number of selected assets processed so far, including this one.
+    pub completed: usize,
+    /// The number of assets selected by the content file.
+    pub total: usize,
+}
+
+type ProgressFn = dyn Fn(BakeProgress<'_>) + Send + Sync;
+
+/// Bakes content files into `.pak` files using the given settings.
+///
+/// All settings are held by the baker, so any number of bakes may run in one process without
+/// affecting each other.
+#[derive(Clone)]
+pub struct Baker {
+    asset_dir: Option<PathBuf>,
+    cache_dir: Option<PathBuf>,
+    cargo_watches: bool,
+    compression: Option<Option<Compression>>,
+    keep_going: bool,
+    observer: Option<Arc<dyn BakeObserver>>,
+    progress: Option<Arc<ProgressFn>>,
+    threads: Option<usize>,
+}
+
+impl Baker {
+    /// Creates a baker which uses the directory of each content file as the asset root.
+    pub fn new() -> Self {
+        Self {
+            asset_dir: None,
+            cache_dir: None,
+            cargo_watches: true,
+            compression: None,
+            keep_going: false,
+            observer: None,
+            progress: None,
+            threads: None,
+        }
+    }
+
+    /// Bakes the content file `src` into a `.pak` file at `dst`.
+    ///
+    /// Unless keep-going is set, baking stops at the first asset which fails. Otherwise every
+    /// asset which can be baked is baked and a `BakeFailures` error lists the assets which failed.
+    /// No `.pak` file is written if any asset fails.
+    pub fn bake(&self, src: impl AsRef<Path>, dst: impl AsRef<Path>) -> anyhow::Result<()> {
+        let writer = self.bake_assets(src.as_ref())?;
+
+        let dst = dst.as_ref();
+        if let Some(parent) = dst.parent() {
+            create_dir_all(parent).context("Unable to create directory")?;
+        }
+
+        writer.lock().write(dst).context("Unable to write pak file")
+    }
+
+    /// Bakes the content file `src` into `writer`, such as a `Cursor<Vec<u8>>` which is later read
+    /// using `PakBuf::from_bytes`.
+    ///
+    /// Nothing is written if any asset fails.
+    pub fn bake_to_writer(
+        &self,
+        src: impl AsRef<Path>,
+        writer: impl Read + Write + Seek,
+    ) -> anyhow::Result<()> {
+        self.bake_assets(src.as_ref())?
+            .lock()
+            .write_to(writer)
+            .context("Unable to write pak")
+    }
+
+    /// Bakes each asset selected by the content file `src`, returning the writer holding them.
+    fn bake_assets(&self, src: &Path) -> anyhow::Result<Arc<Mutex<Writer>>> {
+        let writer: Arc<Mutex<Writer>> = Arc::new(Mutex::new(Default::default()));
+        writer
+            .lock()
+            .with_cargo_watches(self.cargo_watches)
+            .with_observer_is(self.observer.clone());
+        writer.lock().re_run_if_changed(src);
+
+        let rt = Arc::new(self.runtime()?);
+
+        // Load the source file into an Asset::Content instance
+        let src_dir = self.asset_dir.clone().unwrap_or_else(|| parent(src));
+        let content = Asset::read(src)?
+            .into_content()
+            .context("Unable to read asset file")?;
+
+        let compression = self.compression.unwrap_or_else(|| content.compression());
+        if let Some(compression) = compression {
+            writer.lock().with_compression_is(Some(compression));
+        }
+
+        for compression_type in CompressionType::ALL {
+            writer.lock().with_compression_type(
+                compression_type,
+                self.compression
+                    .unwrap_or_else(|| content.compression_of(compression_type)),
+            );
+        }
+
+        writer
+            .lock()
+            .with_format_version_is(content.format_version()?)
+            .with_min_compress_size(content.min_compress_size());
+        writer.lock().cache = self.cache_dir.as_deref().map(BakeCache::new);
+
+        // Process each file we find as a separate runtime task
+        let selected_assets = content.selected_assets(&src_dir)?;
+        let total = selected_assets.len();
+        let mut failures = vec![];
+        for (idx, (asset_path, group_compression)) in selected_assets.into_iter().enumerate() {
+            info!("processing {}", asset_path.display());
+
+            writer.lock().re_run_if_changed(&asset_path);
+            Writer::notify(&writer, BakeEvent::AssetStarted { path: &asset_path });
+
+            let started = Instant::now();
+            let baked_len = writer.lock().baked_len();
+
+            // IDs are assigned when an asset commits to Writer. Await each top-level asset before
+            // spawning the next one so task completion timing cannot change the generated IDs.
+            let res = self
+                .spawn_asset_bake(&rt, &writer, &src_dir, &asset_path, group_compression)
+                .and_then(|task| rt.block_on(task).context("spawned task failed")?);
+
+            let event = match &res {
+                Ok(()) => BakeEvent::AssetFinished {
+                    path: &asset_path,
+                    duration: started.elapsed(),
+                    len: writer.lock().baked_len() - baked_len,
+                },
+                Err(error) => BakeEvent::Error {
+                    path: &asset_path,
+                    error,
+                },
+            };
+            Writer::notify(&writer, event);
+
+            if let Some(progress) = &self.progress {
+                progress(BakeProgress {
+                    path: &asset_path,
+                    completed: idx + 1,
+                    total,
+                });
+            }
+
+            if let Err(error) = res {
+                if !self.keep_going {
+                    return Err(error.context(asset_path.to_string_lossy().into_owned()));
+                }
+
+                failures.push(BakeFailure {
+                    path: asset_path,
+                    error,
+                });
+            }
+        }
+
+        if !failures.is_empty() {
+            return Err(BakeFailures { failures }.into());
+        }
+
+        Ok(writer)
+    }
+
+    fn runtime(&self) -> anyhow::Result<Runtime> {
+        let Some(threads) = self.threads else {
+            return Ok(Runtime::new()?);
+        };
+
+        let threads = threads.max(1);
+
+        // Each top-level asset holds a blocking thread while the bitmaps of its materials bake
+        Ok(Builder::new_multi_thread()
+            .enable_all()
+            .worker_threads(threads)
+            .max_blocking_threads(threads + 1)
+            .build()?)
+    }
+
+    /// Spawns the task which bakes one selected asset of a content file.
+    fn spawn_asset_bake(
+        &self,
+        rt: &Arc<Runtime>,
+        writer: &Arc<Mutex<Writer>>,
+        src_dir: &Path,
+        asset_path: &Path,
+        group_compression: Option<CompressionType>,
+    ) -> anyhow::Result<JoinHandle<anyhow::Result<()>>> {
+        // Assets (and the assets they bake) use the compression of their group, if any
+        let compression = {
+            let writer = writer.lock();
+            writer.asset_compression(group_compression, writer.compression())
+        };
+
+        match asset_path
+            .extension()
+            .map(|ext| ext.to_string_lossy().into_owned())
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "glb" | "gltf" => {
+                // Note that direct references like this build a mesh, not an animation
+                // To build an animation you must specify a .toml file
+                let writer = Arc::clone(writer);
+                let src_dir = src_dir.to_path_buf();
+                let asset_path = asset_path.to_path_buf();
+                Ok(rt.spawn_blocking(move || {
+                    MeshAsset::new(&asset_path).bake(
+                        &writer,
+                        &src_dir,
+                        Some(&asset_path),
+                        compression,
+                    )?;
+                    Ok(())
+                }))
+            }
+            "jpg" | "jpeg" | "png" | "bmp" | "tga" | "dds" | "webp" | "gif" | "ico" | "tiff" => {
+                let writer = Arc::clone(writer);
+                let src_dir = src_dir.to_path_buf();
+                let asset_path = asset_path.to_path_buf();
+                Ok(rt.spawn_blocking(move || {
+                    BitmapAsset::new(&asset_path).bake_from_path(
+                        &writer,
+                        src_dir,
+                        Some(&asset_path),
+                        compression,
+                    )?;
+                    Ok(())
+                }))
+            }
+            "toml" => {
+                let asset = Asset::read(asset_path)?;
+                let asset_parent = parent(asset_path);
+
+                match asset {
+                    Asset::Animation(mut anim) => {
+                        let writer = Arc::clone(writer);
+                        let src_dir = src_dir.to_path_buf();
+                        let asset_path = asset_path.to_path_buf();
+                        Ok(rt.spawn_blocking(move || {
+                            anim.canonicalize(&src_dir, &asset_parent);
+                            anim.bake(&writer, src_dir, &asset_path, compression)?;
+                            Ok(())
+                        }))
+                    }
+                    Asset::Bitmap(mut bitmap) => {
+                        let writer = Arc::clone(writer);
+                        let src_dir = src_dir.to_path_buf();
+                        let asset_path = asset_path.to_path_buf();
+                        Ok(rt.spawn_blocking(move || {
+                            bitmap.canonicalize(&src_dir, &asset_parent);
+                            bitmap.bake_from_path(
+                                &writer,
+                                src_dir,
+                                Some(&asset_path),
+                                compression,
+                            )?;
+                            Ok(())
+                        }))
+                    }
+                    Asset::BitmapFont(mut blob) => {
+                        let writer = Arc::clone(writer);
+                        let src_dir = src_dir.to_path_buf();
+                        let asset_path = asset_path.to_path_buf();
+                        Ok(rt.spawn_blocking(move || {
+                            blob.canonicalize(&src_dir, &asset_parent);
+                            blob.bake_bitmap_font(&writer, src_dir, &asset_path, compression)?;
+                            Ok(())
+                        }))
+                    }
+                    Asset::Blob(mut blob) => {
+                        let writer = Arc::clone(writer);
+                        let src_dir = src_dir.to_path_buf();
+                        let asset_path = asset_path.to_path_buf();
+                        Ok(rt.spawn_blocking(move || {
+                            blob.canonicalize(&src_dir, &asset_parent);
+                            blob.bake_from_path(&writer, src_dir, &asset_path, compression)?;
+                            Ok(())
+                        }))
+                    }
+                    Asset::Material(mut material) => {
+                        let writer = Arc::clone(writer);
+                        let src_dir = src_dir.to_path_buf();
+                        let asset_path = asset_path.to_path_buf();
+                        let rt2 = Arc::clone(rt);
+                        Ok(rt.spawn_blocking(move || {
+                            material.canonicalize(&src_dir, &asset_parent);
+                            material.bake(
+                                &rt2,
+                                &writer,
+                                src_dir,
+                                Some(&asset_path),
+                                compression,
+                            )?;
+                            Ok(())
+                        }))
+                    }
+                    Asset::Mesh(mut mesh) => {
+                        let writer = Arc::clone(writer);
+                        let src_dir = src_dir.to_path_buf();
+                        let asset_path = asset_path.to_path_buf();
+                        Ok(rt.spawn_blocking(move || {
+                            mesh.canonicalize(&src_dir, &asset_parent);
+                            mesh.bake(&writer, &src_dir, Some(&asset_path), compression)?;
+                            Ok(())
+                        }))
+                    }
+                    Asset::Scene(mut scene) => {
+                        let writer = Arc::clone(writer);
+                        let src_dir = src_dir.to_path_buf();
+                        let asset_path = asset_path.to_path_buf();
+                        let rt2 = Arc::clone(rt);
+                        Ok(rt.spawn_blocking(move || {
+                            scene.canonicalize(&src_dir, &asset_parent);
+                            scene.bake(&rt2, &writer, &src_dir, &asset_path, compression)?;
+                            Ok(())
+                        }))
+                    }
+                    _ => anyhow::bail!("unhandled asset type"),
+                }
+            }
+            _ => {
+                let writer = Arc::clone(writer);
+                let src_dir = src_dir.to_path_buf();
+                let asset_path = asset_path.to_path_buf();
+                Ok(rt.spawn_blocking(move || {
+                    let blob = BlobAsset::new(&asset_path);
+                    blob.bake(&writer, &src_dir, compression)?;
+                    Ok(())
+                }))
+            }
+        }
+    }
+
+    /// Uses `asset_dir` as the asset root instead of the directory of the content file.
+    ///
+    /// The content file may live outside the asset root. Asset globs, project-rooted paths, and
+    /// generated pak keys are resolved from the asset root.
+    pub fn with_asset_dir(mut self, asset_dir: impl AsRef<Path>) -> Self {
+        self.asset_dir = Some(asset_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Uses `cache_dir` as a persistent bake cache.
+    ///
+    /// Bitmaps, meshes and animations whose settings and source files have not changed since they
+    /// were last baked using the same cache directory are read from the cache instead of being
+    /// baked again.
+    pub fn with_cache_dir(mut self, cache_dir: impl AsRef<Path>) -> Self {
+        self.cache_dir = Some(cache_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets whether source files are reported to Cargo as `rerun-if-changed` watches when baking
+    /// from a build script, which is the default.
+    ///
+    /// Build scripts which collect and emit their own precise watch list may disable this.
+    pub fn with_cargo_watches(mut self, cargo_watches: bool) -> Self {
+        self.cargo_watches = cargo_watches;
+        self
+    }
+
+    /// Compresses the index and every asset using `compression`, or stores them uncompressed if
+    /// `None`, instead of using the compression of the content file, its groups and its assets.
+    pub fn with_compression(mut self, compression: Option<Compression>) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Sets whether every asset which can be baked is baked before failures are returned, instead
+    /// of stopping at the first failure.
+    pub fn with_keep_going(mut self, keep_going: bool) -> Self {
+        self.keep_going = keep_going;
+        self
+    }
+
+    /// Sends the events of each bake, such as the start and finish of each asset, to `observer`.
+    pub fn with_observer(mut self, observer: impl BakeObserver + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Calls `progress` after each selected asset of a content file has been processed.
+    pub fn with_progress(
+        mut self,
+        progress: impl Fn(BakeProgress<'_>) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Limits the number of threads used to bake assets, which otherwise defaults to the number of
+    /// CPU cores.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+}
+
+impl Debug for Baker {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Baker")
+            .field("asset_dir", &self.asset_dir)
+            .field("cache_dir", &self.cache_dir)
+            .field("cargo_watches", &self.cargo_watches)
+            .field("compression", &self.compression)
+            .field("keep_going", &self.keep_going)
+            .field("observer", &self.observer.is_some())
+            .field("progress", &self.progress.is_some())
+            .field("threads", &self.threads)
+            .finish()
+    }
+}
+
+impl Default for Baker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
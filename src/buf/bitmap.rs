@@ -1,8 +1,9 @@
 use {
-    super::{Canonicalize, Writer, file_key, re_run_if_changed},
+    super::{Canonicalize, Writer, cache::BakeCache, content::CompressionType, file_key},
     crate::{
         BitmapId,
         bitmap::{Bitmap, BitmapColor, BitmapFormat},
+        compression::Compression,
     },
     anyhow::{Context, bail},
     image::{DynamicImage, RgbaImage, buffer::ConvertBuffer, imageops::FilterType, open},
@@ -19,6 +20,10 @@ use {
 const MIP_LEVELS_MAX: u32 = u32::BITS;
 const MIP_LEVELS_MIN: u32 = 1;
 
+/// Pushes a bitmap which has already been read into an existing `.pak` file buffer, returning its
+/// ID.
+pub(super) type PushBitmap = Box<dyn FnOnce(&mut Writer) -> anyhow::Result<BitmapId> + Send>;
+
 fn de_mip_levels<'de, D>(deserializer: D) -> Result<u32, D::Error>
 where
     D: Deserializer<'de>,
@@ -70,6 +75,7 @@ fn default_mip_levels() -> u32 {
 #[serde(rename_all = "kebab-case")]
 pub struct BitmapAsset {
     color: Option<BitmapColor>,
+    compression: Option<CompressionType>,
 
     #[serde(default = "default_mip_levels", deserialize_with = "de_mip_levels")]
     mip_levels: u32,
@@ -87,6 +93,7 @@ impl BitmapAsset {
     pub fn new(src: impl AsRef<Path>) -> Self {
         Self {
             color: None,
+            compression: None,
             mip_levels: 1,
             resize: None,
             src: Some(src.as_ref().to_path_buf()),
@@ -113,21 +120,48 @@ impl BitmapAsset {
     }
 
     /// Reads and processes image source files into an existing `.pak` file buffer.
+    #[allow(unused)]
     pub fn bake(
         &mut self,
         writer: &Arc<Mutex<Writer>>,
         project_dir: impl AsRef<Path>,
+        compression: Option<Compression>,
     ) -> anyhow::Result<BitmapId> {
-        self.bake_from_path(writer, project_dir, None as Option<&'static str>)
+        self.bake_from_path(
+            writer,
+            project_dir,
+            None as Option<&'static str>,
+            compression,
+        )
     }
 
     /// Reads and processes image source files into an existing `.pak` file buffer.
+    ///
+    /// The bitmap is compressed using `compression` unless it chooses its own compression.
     pub fn bake_from_path(
         &mut self,
         writer: &Arc<Mutex<Writer>>,
         project_dir: impl AsRef<Path>,
         path: Option<impl AsRef<Path>>,
+        compression: Option<Compression>,
     ) -> anyhow::Result<BitmapId> {
+        let push = self.read_from_path(writer, project_dir, path, compression)?;
+
+        push(&mut writer.lock())
+    }
+
+    /// Reads and processes image source files, returning the function which pushes the bitmap into
+    /// an existing `.pak` file buffer.
+    ///
+    /// Bitmaps may be read in parallel and then pushed in a stable order, so that their IDs do not
+    /// depend on which is read first.
+    pub(super) fn read_from_path(
+        &self,
+        writer: &Arc<Mutex<Writer>>,
+        project_dir: impl AsRef<Path>,
+        path: Option<impl AsRef<Path>>,
+        compression: Option<Compression>,
+    ) -> anyhow::Result<PushBitmap> {
         let Some(src) = self.src() else {
             return Err(anyhow::Error::msg("unspecified bitmap source"));
         };
@@ -135,9 +169,11 @@ impl BitmapAsset {
         // Early-out if we have already baked this bitmap
         let asset = self.clone().into();
         if let Some(id) = writer.lock().ctx.get(&asset) {
-            return id
+            let id = id
                 .as_bitmap()
-                .context("asset context returned non-bitmap id");
+                .context("asset context returned non-bitmap id")?;
+
+            return Ok(Box::new(move |_| Ok(id)));
         }
 
         let key = path.as_ref().map(|path| file_key(&project_dir, path));
@@ -149,24 +185,39 @@ impl BitmapAsset {
             info!("Baking bitmap: {} (inline)", file_key(&project_dir, src));
         }
 
-        let bitmap = self
-            .as_bitmap_buf()
-            .context("Unable to create bitmap buf")?;
+        // Watch the source even when the bitmap is read from the cache
+        writer.lock().re_run_if_changed(src);
 
-        let mut writer = writer.lock();
-        if let Some(id) = writer.ctx.get(&asset) {
-            return id
-                .as_bitmap()
-                .context("asset context returned non-bitmap id");
-        }
+        let bitmap = BakeCache::get_or_bake(writer, &asset, &[src.to_path_buf()], || {
+            self.read_bitmap_buf()
+                .context("Unable to create bitmap buf")
+        })?;
+        let own_compression = self.compression;
+
+        Ok(Box::new(move |writer| {
+            if let Some(id) = writer.ctx.get(&asset) {
+                return id
+                    .as_bitmap()
+                    .context("asset context returned non-bitmap id");
+            }
 
-        let id = writer.push_bitmap(bitmap, key);
-        writer.ctx.insert(asset, id.into());
+            let compression = writer.asset_compression(own_compression, compression);
+            let id = writer.push_bitmap(bitmap, key, compression);
+            writer.ctx.insert(asset, id.into());
 
-        Ok(id)
+            Ok(id)
+        }))
     }
 
-    pub fn as_bitmap_buf(&self) -> anyhow::Result<Bitmap> {
+    pub fn as_bitmap_buf(&self, writer: &Mutex<Writer>) -> anyhow::Result<Bitmap> {
+        if let Some(src) = self.src() {
+            writer.lock().re_run_if_changed(src);
+        }
+
+        self.read_bitmap_buf()
+    }
+
+    fn read_bitmap_buf(&self) -> anyhow::Result<Bitmap> {
         let Some(src) = self.src() else {
             return Err(anyhow::Error::msg("unspecified bitmap source"));
         };
@@ -205,8 +256,6 @@ impl BitmapAsset {
         swizzle: Option<BitmapSwizzle>,
         resize: Option<u32>,
     ) -> anyhow::Result<(BitmapFormat, u32, Vec<u8>)> {
-        re_run_if_changed(&path);
-
         //let started = std::time::Instant::now();
 
         /*
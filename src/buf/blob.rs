@@ -2,12 +2,14 @@ use {
     super::{
         Canonicalize, Writer,
         bitmap::{BitmapAsset, BitmapSwizzle},
-        file_key, re_run_if_changed,
+        content::CompressionType,
+        file_key,
     },
     crate::{
         BitmapFontId, BlobId,
         bitmap::{Bitmap, BitmapColor, BitmapFormat},
         bitmap_font::BitmapFont,
+        compression::Compression,
     },
     anyhow::{Context, bail},
     bmfont::{BMFont, OrdinateOrientation},
@@ -26,6 +28,8 @@ use {
 /// Holds a description of any generic file.
 #[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
 pub struct BlobAsset {
+    compression: Option<CompressionType>,
+
     /// The file source.
     src: Option<PathBuf>,
 }
@@ -34,21 +38,27 @@ impl BlobAsset {
     pub fn new(src: impl AsRef<Path>) -> Self {
         let src = src.as_ref().to_path_buf();
 
-        Self { src: Some(src) }
+        Self {
+            compression: None,
+            src: Some(src),
+        }
     }
 
     /// Reads and processes arbitrary binary source files into an existing `.pak` file buffer.
+    ///
+    /// The blob is compressed using `compression` unless it chooses its own compression.
     pub fn bake(
         &self,
         writer: &Arc<Mutex<Writer>>,
         project_dir: impl AsRef<Path>,
+        compression: Option<Compression>,
     ) -> anyhow::Result<BlobId> {
         let key_path = self
             .src()
             .ok_or_else(|| anyhow::Error::msg("unspecified blob source"))?
             .to_path_buf();
 
-        self.bake_from_path(writer, project_dir, key_path)
+        self.bake_from_path(writer, project_dir, key_path, compression)
     }
 
     pub(super) fn bake_from_path(
@@ -56,6 +66,7 @@ impl BlobAsset {
         writer: &Arc<Mutex<Writer>>,
         project_dir: impl AsRef<Path>,
         path: impl AsRef<Path>,
+        compression: Option<Compression>,
     ) -> anyhow::Result<BlobId> {
         let Some(src) = self.src() else {
             return Err(anyhow::Error::msg("unspecified blob source"));
@@ -72,7 +83,7 @@ impl BlobAsset {
 
         info!("Baking blob: {}", key);
 
-        re_run_if_changed(src);
+        writer.lock().re_run_if_changed(src);
 
         let mut file = File::open(src).context("Unable to open blob file")?;
         let mut value = vec![];
@@ -84,7 +95,8 @@ impl BlobAsset {
             return id.as_blob().context("asset context returned non-blob id");
         }
 
-        let id = writer.push_blob(value, Some(key));
+        let compression = writer.asset_compression(self.compression, compression);
+        let id = writer.push_blob(value, Some(key), compression);
         writer.ctx.insert(asset, id.into());
 
         Ok(id)
@@ -96,6 +108,7 @@ impl BlobAsset {
         writer: &Arc<Mutex<Writer>>,
         project_dir: impl AsRef<Path>,
         path: impl AsRef<Path>,
+        compression: Option<Compression>,
     ) -> anyhow::Result<BitmapFontId> {
         let Some(src) = self.src() else {
             return Err(anyhow::Error::msg("unspecified blob source"));
@@ -114,7 +127,7 @@ impl BlobAsset {
 
         info!("Baking bitmap font: {}", key);
 
-        re_run_if_changed(src);
+        writer.lock().re_run_if_changed(src);
 
         // Get the fs objects for this asset
         let Some(def_parent) = src.parent() else {
@@ -126,6 +139,7 @@ impl BlobAsset {
         let mut pages = Vec::new();
         for page in def.pages() {
             let path = def_parent.join(page);
+            writer.lock().re_run_if_changed(&path);
             let (_, width, pixels) =
                 BitmapAsset::read_pixels(path, Some(BitmapSwizzle::RGBA), None)
                     .context("Unable to read bitmap font page")?;
@@ -182,7 +196,9 @@ impl BlobAsset {
                 .context("asset context returned non-bitmap-font id");
         }
 
-        let id = writer.push_bitmap_font(BitmapFont::new(def_file, page_bufs), Some(key));
+        let compression = writer.asset_compression(self.compression, compression);
+        let id =
+            writer.push_bitmap_font(BitmapFont::new(def_file, page_bufs), Some(key), compression);
         writer.ctx.insert(asset, id.into());
 
         Ok(id)
@@ -0,0 +1,152 @@
+use {
+    super::{asset::Asset, observer::BakeEvent, writer::Writer},
+    log::debug,
+    parking_lot::Mutex,
+    serde::{Serialize, de::DeserializeOwned},
+    std::{
+        fs::{create_dir_all, read, rename, write},
+        hash::{Hash, Hasher},
+        path::{Path, PathBuf},
+    },
+};
+
+const FNV_OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+const FNV_PRIME: u128 = 0x0000000001000000000000000000013b;
+
+/// An on-disk cache of baked assets which lets unchanged assets skip being rebaked.
+///
+/// Entries are keyed by the version of this crate, the settings of the asset and the contents of
+/// every source file the asset reads; any change to these produces a different entry. Entries are
+/// never removed by the cache itself.
+#[derive(Clone, Debug)]
+pub struct BakeCache {
+    dir: PathBuf,
+}
+
+impl BakeCache {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns the cached value for `asset`, or bakes and caches it if there is none.
+    ///
+    /// Without a cache set on `writer` this simply bakes the value.
+    pub fn get_or_bake<T>(
+        writer: &Mutex<Writer>,
+        asset: &Asset,
+        sources: &[PathBuf],
+        bake: impl FnOnce() -> anyhow::Result<T>,
+    ) -> anyhow::Result<T>
+    where
+        T: DeserializeOwned + Serialize,
+    {
+        let cache = writer.lock().cache.clone();
+        let Some(cache) = cache else {
+            return bake();
+        };
+
+        // Sources which cannot be read are left for the bake function to report
+        let Some(path) = cache.path(asset, sources) else {
+            return bake();
+        };
+
+        if let Ok(data) = read(&path) {
+            match bincode::serde::decode_from_slice(&data, bincode::config::legacy()) {
+                Ok((value, _)) => {
+                    debug!("Using cached bake: {}", path.display());
+
+                    if let Some(src) = sources.first() {
+                        Writer::notify(writer, BakeEvent::CacheHit { path: src });
+                    }
+
+                    return Ok(value);
+                }
+                Err(err) => Writer::warn(
+                    writer,
+                    &format!("Ignoring unreadable cached bake {}: {err}", path.display()),
+                ),
+            }
+        }
+
+        let value = bake()?;
+
+        if let Err(err) = cache.write(&path, &value) {
+            Writer::warn(
+                writer,
+                &format!("Unable to cache bake {}: {err}", path.display()),
+            );
+        }
+
+        Ok(value)
+    }
+
+    fn path(&self, asset: &Asset, sources: &[PathBuf]) -> Option<PathBuf> {
+        let mut hasher = StableHasher::default();
+        env!("CARGO_PKG_VERSION").hash(&mut hasher);
+        asset.hash(&mut hasher);
+
+        for source in sources {
+            source.hash(&mut hasher);
+            read(source).ok()?.hash(&mut hasher);
+        }
+
+        Some(self.dir.join(format!("{:032x}.bin", hasher.state)))
+    }
+
+    fn write(&self, path: &Path, value: &impl Serialize) -> anyhow::Result<()> {
+        create_dir_all(&self.dir)?;
+
+        // Write a temporary file first so that concurrent bakes never read a partial entry
+        let data = bincode::serde::encode_to_vec(value, bincode::config::legacy())?;
+        let temp_path = path.with_extension(format!("tmp-{}", std::process::id()));
+        write(&temp_path, data)?;
+        rename(temp_path, path)?;
+
+        Ok(())
+    }
+}
+
+/// Returns the files read when importing a glTF source: the source and any external buffers.
+pub fn gltf_sources(src: impl AsRef<Path>) -> Vec<PathBuf> {
+    let src = src.as_ref();
+    let mut res = vec![src.to_path_buf()];
+
+    if let Ok(gltf) = gltf::Gltf::open(src) {
+        let dir = src.parent().unwrap_or(Path::new(""));
+        for buffer in gltf.buffers() {
+            if let gltf::buffer::Source::Uri(uri) = buffer.source()
+                && !uri.starts_with("data:")
+            {
+                res.push(dir.join(uri));
+            }
+        }
+    }
+
+    res
+}
+
+/// A 128-bit FNV-1a hasher which, unlike `DefaultHasher`, produces the same keys on every run.
+#[derive(Debug)]
+struct StableHasher {
+    state: u128,
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self { state: FNV_OFFSET }
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.state as _
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state = (self.state ^ u128::from(*byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+}
@@ -1,7 +1,10 @@
 use {
     super::project_path,
-    crate::compression::{BrotliParams, Compression},
-    anyhow::Context,
+    crate::{
+        FORMAT_VERSION,
+        compression::{BrotliParams, Compression},
+    },
+    anyhow::{Context, bail},
     glob::glob,
     serde::Deserialize,
     std::{
@@ -20,6 +23,19 @@ pub struct Content {
     #[serde(rename = "buffer-size")]
     buffer_size: Option<usize>,
 
+    // Zstandard-specific compression parameter
+    #[serde(default)]
+    dictionary: bool,
+
+    #[serde(rename = "format-version")]
+    format_version: Option<u16>,
+
+    #[serde(default, rename = "min-compress-size")]
+    min_compress_size: usize,
+
+    // Zstandard-specific compression parameter
+    level: Option<i32>,
+
     // Brotli-specific compression parameter
     quality: Option<u32>,
 
@@ -39,10 +55,38 @@ impl Content {
         self.groups.iter()
     }
 
+    /// The pak format version to write, if an older one than `FORMAT_VERSION` is required.
+    pub(crate) fn format_version(&self) -> anyhow::Result<Option<u16>> {
+        match self.format_version {
+            Some(version) if !(2..=FORMAT_VERSION).contains(&version) => {
+                bail!("format-version must be between 2 and {FORMAT_VERSION}")
+            }
+            version => Ok(version),
+        }
+    }
+
+    /// The size in bytes below which assets are stored uncompressed.
+    pub(crate) fn min_compress_size(&self) -> usize {
+        self.min_compress_size
+    }
+
     pub(crate) fn selected_asset_paths(
         &self,
         asset_root: impl AsRef<Path>,
     ) -> anyhow::Result<Vec<PathBuf>> {
+        Ok(self
+            .selected_assets(asset_root)?
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect())
+    }
+
+    /// Returns the selected asset paths along with the compression of the group which selected
+    /// them, if the group sets one.
+    pub(crate) fn selected_assets(
+        &self,
+        asset_root: impl AsRef<Path>,
+    ) -> anyhow::Result<Vec<(PathBuf, Option<CompressionType>)>> {
         let asset_root = asset_root.as_ref();
         let enabled_groups = || self.groups().filter(|group| group.enabled());
 
@@ -54,13 +98,19 @@ impl Content {
         }
 
         let mut asset_paths = Vec::new();
-        for pattern in enabled_groups().flat_map(|group| group.asset_globs()) {
-            for path in glob(project_path(asset_root, pattern).to_string_lossy().as_ref())
-                .context("Unable to glob source directory")?
-            {
-                let path = path?;
-                if !excluded_assets.contains(&path) {
-                    asset_paths.push(path);
+        for group in enabled_groups() {
+            for pattern in group.asset_globs() {
+                let mut paths = glob(project_path(asset_root, pattern).to_string_lossy().as_ref())
+                    .context("Unable to glob source directory")?
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                // Baking assigns IDs in this order, which must not depend on the file system
+                paths.sort();
+
+                for path in paths {
+                    if !excluded_assets.contains(&path) {
+                        asset_paths.push((path, group.compression));
+                    }
                 }
             }
         }
@@ -69,7 +119,13 @@ impl Content {
     }
 
     pub(crate) fn compression(&self) -> Option<Compression> {
-        self.compression.map(|compression| match compression {
+        self.compression
+            .and_then(|compression| self.compression_of(compression))
+    }
+
+    /// Returns the compression described by `compression` using the parameters of this content.
+    pub(crate) fn compression_of(&self, compression: CompressionType) -> Option<Compression> {
+        Some(match compression {
             CompressionType::Brotli => Compression::Brotli(BrotliParams {
                 buffer_size: self
                     .buffer_size
@@ -81,7 +137,13 @@ impl Content {
                     .window_size
                     .unwrap_or_else(|| BrotliParams::default().window_size),
             }),
+            CompressionType::Lz4 => Compression::Lz4,
+            CompressionType::None => return None,
             CompressionType::Snap => Compression::Snap,
+            CompressionType::Zstd => Compression::Zstd {
+                level: self.level.unwrap_or(Compression::DEFAULT_ZSTD_LEVEL),
+                dictionary: self.dictionary,
+            },
         })
     }
 }
@@ -91,9 +153,23 @@ pub enum CompressionType {
     /// Higher compression ratio but slower to decode and encode.
     #[serde(rename = "brotli")]
     Brotli,
+    /// Lowest compression ratio but fastest to decode and encode.
+    #[serde(rename = "lz4")]
+    Lz4,
+    /// Stored as-is, such as for assets which are already compressed.
+    #[serde(rename = "none")]
+    None,
     /// Lower compression ratio but faster to decode and encode.
     #[serde(rename = "snap")]
     Snap,
+    /// High compression ratio and fast to decode.
+    #[serde(rename = "zstd")]
+    Zstd,
+}
+
+impl CompressionType {
+    /// Every compression type, in declaration order.
+    pub(crate) const ALL: [Self; 5] = [Self::Brotli, Self::Lz4, Self::None, Self::Snap, Self::Zstd];
 }
 
 /// Holds a description of asset files.
@@ -102,6 +178,8 @@ pub struct Group {
     #[serde(default)]
     assets: Vec<String>,
 
+    compression: Option<CompressionType>,
+
     #[serde(default = "Group::default_enabled")]
     enabled: bool,
 
@@ -144,7 +222,7 @@ impl Group {
 
 #[cfg(test)]
 mod test {
-    use super::Content;
+    use {super::Content, crate::compression::Compression};
 
     #[test]
     fn content_deserializes_without_groups() {
@@ -153,4 +231,27 @@ mod test {
 
         assert_eq!(content.groups().count(), 0);
     }
+
+    #[test]
+    fn content_selects_zstd_level() {
+        let content = toml::from_str::<Content>("compression = 'zstd'\nlevel = 19").unwrap();
+
+        assert_eq!(
+            content.compression(),
+            Some(Compression::Zstd {
+                level: 19,
+                dictionary: false
+            })
+        );
+
+        let content = toml::from_str::<Content>("compression = 'zstd'").unwrap();
+
+        assert_eq!(
+            content.compression(),
+            Some(Compression::Zstd {
+                level: Compression::DEFAULT_ZSTD_LEVEL,
+                dictionary: false
+            })
+        );
+    }
 }
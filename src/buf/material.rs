@@ -1,16 +1,18 @@
 use {
     super::{
         Asset, Canonicalize, Writer,
-        bitmap::{BitmapAsset, BitmapSwizzle},
+        bitmap::{BitmapAsset, BitmapSwizzle, PushBitmap},
+        content::CompressionType,
         file_key, is_toml, parent, parse_hex_color, parse_hex_scalar,
     },
     crate::{
-        BitmapId, MaterialId, MaterialInfo, MaterialParameterFlags,
+        MaterialId, MaterialInfo, MaterialParameterFlags,
         bitmap::{Bitmap, BitmapColor, BitmapFormat},
+        compression::Compression,
     },
     anyhow::Context as _,
     image::{DynamicImage, GenericImageView, GrayImage, imageops::FilterType},
-    log::{info, warn},
+    log::info,
     ordered_float::OrderedFloat,
     parking_lot::Mutex,
     serde::{
@@ -289,6 +291,9 @@ pub struct MaterialAsset {
     #[serde(deserialize_with = "ColorRef::de")]
     pub color: Option<ColorRef>,
 
+    /// The compression of the bitmaps of this material, if it differs from its content group.
+    pub compression: Option<CompressionType>,
+
     #[serde(deserialize_with = "ScalarRef::de")]
     pub height: Option<ScalarRef>,
 
@@ -333,12 +338,16 @@ impl MaterialAsset {
     }
 
     /// Reads and processes 3D mesh material source files into an existing `.pak` file buffer.
+    ///
+    /// The bitmaps of the material are compressed using `compression` unless the material, or the
+    /// bitmap, chooses its own compression.
     pub(super) fn bake(
         &mut self,
         rt: &Runtime,
         writer: &Arc<Mutex<Writer>>,
         project_dir: impl AsRef<Path>,
         path: Option<impl AsRef<Path>>,
+        compression: Option<Compression>,
     ) -> anyhow::Result<MaterialId> {
         // Early-out if we have already baked this material
         let asset = self.clone().into();
@@ -359,7 +368,7 @@ impl MaterialAsset {
             info!("Baking material: (inline)");
         }
 
-        let material_info = self.as_material_info(rt, writer, project_dir)?;
+        let material_info = self.as_material_info(rt, writer, project_dir, compression)?;
 
         let mut writer = writer.lock();
         if let Some(id) = writer.ctx.get(&asset) {
@@ -379,21 +388,28 @@ impl MaterialAsset {
         rt: &Runtime,
         writer: &Arc<Mutex<Writer>>,
         project_dir: impl AsRef<Path>,
+        compression: Option<Compression>,
     ) -> anyhow::Result<MaterialInfo> {
+        let compression = writer
+            .lock()
+            .asset_compression(self.compression, compression);
+
+        // The bitmaps of this material are read at the same time but pushed in the order they are
+        // declared, once every one has been read, so that their IDs do not depend on timing
         let color = match &self.color {
             Some(ColorRef::Asset(bitmap)) => {
                 let writer = writer.clone();
                 let project_dir = project_dir.as_ref().to_path_buf();
-                let mut bitmap = bitmap.clone();
+                let bitmap = bitmap.clone();
 
                 rt.spawn_blocking(move || {
                     bitmap
-                        .bake(&writer, &project_dir)
+                        .read_from_path(&writer, &project_dir, None::<PathBuf>, compression)
                         .context("Unable to bake color asset bitmap")
                 })
             }
             Some(ColorRef::Path(src)) => {
-                let mut bitmap = if is_toml(src) {
+                let bitmap = if is_toml(src) {
                     let mut bitmap = Asset::read(src)
                         .context("Unable to read color bitmap asset")?
                         .into_bitmap()
@@ -408,47 +424,44 @@ impl MaterialAsset {
 
                 rt.spawn_blocking(move || {
                     bitmap
-                        .bake_from_path(&writer, &project_dir, Option::<PathBuf>::None)
+                        .read_from_path(&writer, &project_dir, None::<PathBuf>, compression)
                         .context("Unable to bake color asset bitmap from path")
                 })
             }
             &Some(ColorRef::Value(val)) => {
-                let writer = writer.clone();
-
-                rt.spawn_blocking(move || -> anyhow::Result<BitmapId> {
-                    let mut writer = writer.lock();
-                    if let Some(id) = writer.ctx.get(&Asset::ColorRgba(val)) {
-                        id.as_bitmap().context("expected bitmap id for color value")
-                    } else {
-                        let bitmap = Bitmap::new(
-                            BitmapColor::Linear,
-                            BitmapFormat::Rgba,
-                            1,
-                            1,
-                            [
-                                (val[0].0 * u8::MAX as f32) as u8,
-                                (val[1].0 * u8::MAX as f32) as u8,
-                                (val[2].0 * u8::MAX as f32) as u8,
-                                (val[3].0 * u8::MAX as f32) as u8,
-                            ],
-                        );
-                        Ok(writer.push_bitmap(bitmap, None))
-                    }
+                rt.spawn_blocking(move || -> anyhow::Result<PushBitmap> {
+                    Ok(Box::new(move |writer| {
+                        if let Some(id) = writer.ctx.get(&Asset::ColorRgba(val)) {
+                            id.as_bitmap().context("expected bitmap id for color value")
+                        } else {
+                            let bitmap = Bitmap::new(
+                                BitmapColor::Linear,
+                                BitmapFormat::Rgba,
+                                1,
+                                1,
+                                [
+                                    (val[0].0 * u8::MAX as f32) as u8,
+                                    (val[1].0 * u8::MAX as f32) as u8,
+                                    (val[2].0 * u8::MAX as f32) as u8,
+                                    (val[3].0 * u8::MAX as f32) as u8,
+                                ],
+                            );
+                            Ok(writer.push_bitmap(bitmap, None, compression))
+                        }
+                    }))
                 })
             }
-            None => {
-                let writer = writer.clone();
-
-                rt.spawn_blocking(move || -> anyhow::Result<BitmapId> {
-                    let potters_clay =
-                        parse_hex_color("#8C5738").expect("compile-time hex color is valid");
-                    let potters_clay = [
-                        OrderedFloat(potters_clay[0] as f32 / u8::MAX as f32),
-                        OrderedFloat(potters_clay[1] as f32 / u8::MAX as f32),
-                        OrderedFloat(potters_clay[2] as f32 / u8::MAX as f32),
-                        OrderedFloat(1.0),
-                    ];
-                    let mut writer = writer.lock();
+            None => rt.spawn_blocking(move || -> anyhow::Result<PushBitmap> {
+                let potters_clay =
+                    parse_hex_color("#8C5738").expect("compile-time hex color is valid");
+                let potters_clay = [
+                    OrderedFloat(potters_clay[0] as f32 / u8::MAX as f32),
+                    OrderedFloat(potters_clay[1] as f32 / u8::MAX as f32),
+                    OrderedFloat(potters_clay[2] as f32 / u8::MAX as f32),
+                    OrderedFloat(1.0),
+                ];
+
+                Ok(Box::new(move |writer| {
                     if let Some(id) = writer.ctx.get(&Asset::ColorRgba(potters_clay)) {
                         id.as_bitmap()
                             .context("expected bitmap id for default color")
@@ -465,10 +478,10 @@ impl MaterialAsset {
                                 (potters_clay[3].0 * u8::MAX as f32) as u8,
                             ],
                         );
-                        Ok(writer.push_bitmap(bitmap, None))
+                        Ok(writer.push_bitmap(bitmap, None, compression))
                     }
-                })
-            }
+                }))
+            }),
         };
 
         let normal = self
@@ -479,14 +492,15 @@ impl MaterialAsset {
                     NormalRef::Asset(bitmap) => {
                         let writer = writer.clone();
                         let project_dir = project_dir.as_ref().to_path_buf();
-                        let mut bitmap = bitmap.clone().with_swizzle(BitmapSwizzle::RGB);
+                        let bitmap = bitmap.clone().with_swizzle(BitmapSwizzle::RGB);
 
                         rt.spawn_blocking(move || {
-                            Self::bake_normal_bitmap(
-                                &mut bitmap,
+                            Self::read_normal_bitmap(
+                                &bitmap,
                                 &writer,
                                 &project_dir,
                                 None::<PathBuf>,
+                                compression,
                             )
                             .context("Unable to bake normal asset bitmap")
                         })
@@ -507,11 +521,12 @@ impl MaterialAsset {
 
                         rt.spawn_blocking(move || {
                             bitmap = bitmap.with_swizzle(BitmapSwizzle::RGB);
-                            Self::bake_normal_bitmap(
-                                &mut bitmap,
+                            Self::read_normal_bitmap(
+                                &bitmap,
                                 &writer,
                                 &project_dir,
                                 None::<PathBuf>,
+                                compression,
                             )
                             .context("Unable to bake normal asset bitmap from path")
                         })
@@ -528,11 +543,11 @@ impl MaterialAsset {
                     EmissiveRef::Asset(bitmap) => {
                         let writer = writer.clone();
                         let project_dir = project_dir.as_ref().to_path_buf();
-                        let mut bitmap = bitmap.clone().with_swizzle(BitmapSwizzle::RGB);
+                        let bitmap = bitmap.clone().with_swizzle(BitmapSwizzle::RGB);
 
-                        rt.spawn_blocking(move || -> anyhow::Result<BitmapId> {
+                        rt.spawn_blocking(move || {
                             bitmap
-                                .bake(&writer, &project_dir)
+                                .read_from_path(&writer, &project_dir, None::<PathBuf>, compression)
                                 .context("Unable to bake emissive asset bitmap")
                         })
                     }
@@ -550,35 +565,33 @@ impl MaterialAsset {
                         let writer = writer.clone();
                         let project_dir = project_dir.as_ref().to_path_buf();
 
-                        rt.spawn_blocking(move || -> anyhow::Result<BitmapId> {
+                        rt.spawn_blocking(move || {
                             bitmap
                                 .with_swizzle(BitmapSwizzle::RGB)
-                                .bake_from_path(&writer, &project_dir, Option::<PathBuf>::None)
+                                .read_from_path(&writer, &project_dir, None::<PathBuf>, compression)
                                 .context("Unable to bake emissive asset bitmap from path")
                         })
                     }
-                    EmissiveRef::Value(val) => {
-                        let writer = writer.clone();
-                        let val = *val;
-
-                        rt.spawn_blocking(move || -> anyhow::Result<BitmapId> {
-                            let mut writer = writer.lock();
-                            if let Some(id) = writer.ctx.get(&Asset::ColorRgb(val)) {
-                                id.as_bitmap().context("expected bitmap id for emissive")
-                            } else {
-                                let bitmap = Bitmap::new(
-                                    BitmapColor::Linear,
-                                    BitmapFormat::Rgb,
-                                    1,
-                                    1,
-                                    [
-                                        (val[0].0 * u8::MAX as f32) as u8,
-                                        (val[1].0 * u8::MAX as f32) as u8,
-                                        (val[2].0 * u8::MAX as f32) as u8,
-                                    ],
-                                );
-                                Ok(writer.push_bitmap(bitmap, None))
-                            }
+                    &EmissiveRef::Value(val) => {
+                        rt.spawn_blocking(move || -> anyhow::Result<PushBitmap> {
+                            Ok(Box::new(move |writer| {
+                                if let Some(id) = writer.ctx.get(&Asset::ColorRgb(val)) {
+                                    id.as_bitmap().context("expected bitmap id for emissive")
+                                } else {
+                                    let bitmap = Bitmap::new(
+                                        BitmapColor::Linear,
+                                        BitmapFormat::Rgb,
+                                        1,
+                                        1,
+                                        [
+                                            (val[0].0 * u8::MAX as f32) as u8,
+                                            (val[1].0 * u8::MAX as f32) as u8,
+                                            (val[2].0 * u8::MAX as f32) as u8,
+                                        ],
+                                    );
+                                    Ok(writer.push_bitmap(bitmap, None, compression))
+                                }
+                            }))
                         })
                     }
                 })
@@ -618,25 +631,27 @@ impl MaterialAsset {
             let rough = self.rough.clone();
             let transmission = self.transmission.clone();
 
-            rt.spawn_blocking(move || {
+            rt.spawn_blocking(move || -> anyhow::Result<PushBitmap> {
                 if let Some(id) = writer.lock().ctx.get(&params_asset) {
-                    return id.as_bitmap().context("expected bitmap id for params");
+                    let id = id.as_bitmap().context("expected bitmap id for params")?;
+
+                    return Ok(Box::new(move |_| Ok(id)));
                 }
 
                 let mut metal_image = DynamicImage::ImageLuma8(
-                    Self::scalar_ref_into_gray_image(&metal, &project_dir, 0)
+                    Self::scalar_ref_into_gray_image(&metal, &writer, &project_dir, 0)
                         .context("Unable to create metal bitmap buf")?,
                 );
                 let mut rough_image = DynamicImage::ImageLuma8(
-                    Self::scalar_ref_into_gray_image(&rough, &project_dir, u8::MAX)
+                    Self::scalar_ref_into_gray_image(&rough, &writer, &project_dir, u8::MAX)
                         .context("Unable to create rough bitmap buf")?,
                 );
                 let mut height_image = DynamicImage::ImageLuma8(
-                    Self::scalar_ref_into_gray_image(&height_ref, &project_dir, 0)
+                    Self::scalar_ref_into_gray_image(&height_ref, &writer, &project_dir, 0)
                         .context("Unable to create height bitmap buf")?,
                 );
                 let mut transmission_image = DynamicImage::ImageLuma8(
-                    Self::scalar_ref_into_gray_image(&transmission, &project_dir, 0)
+                    Self::scalar_ref_into_gray_image(&transmission, &writer, &project_dir, 0)
                         .context("Unable to create transmission bitmap buf")?,
                 );
 
@@ -704,28 +719,28 @@ impl MaterialAsset {
                     }
                 }
 
-                let mut writer = writer.lock();
-
-                if let Some(id) = writer.ctx.get(&params_asset) {
-                    id.as_bitmap().context("expected bitmap id for params")
-                } else {
-                    let params =
-                        Bitmap::new(BitmapColor::Linear, BitmapFormat::Rgba, width, 1, params);
-                    Ok(writer.push_bitmap(params, None))
-                }
+                Ok(Box::new(move |writer| {
+                    if let Some(id) = writer.ctx.get(&params_asset) {
+                        id.as_bitmap().context("expected bitmap id for params")
+                    } else {
+                        let params =
+                            Bitmap::new(BitmapColor::Linear, BitmapFormat::Rgba, width, 1, params);
+                        Ok(writer.push_bitmap(params, None, compression))
+                    }
+                }))
             })
         });
 
-        let (color, emissive, normal, params) = rt
+        let (color, normal, emissive, params) = rt
             .block_on(async move {
                 let color = color.await.context("color task failed")??;
-                let emissive = if let Some(emissive) = emissive {
-                    Some(emissive.await.context("emissive task failed")??)
+                let normal = if let Some(normal) = normal {
+                    normal.await.context("normal task failed")??
                 } else {
                     None
                 };
-                let normal = if let Some(normal) = normal {
-                    normal.await.context("normal task failed")??
+                let emissive = if let Some(emissive) = emissive {
+                    Some(emissive.await.context("emissive task failed")??)
                 } else {
                     None
                 };
@@ -735,10 +750,16 @@ impl MaterialAsset {
                     None
                 };
 
-                anyhow::Ok((color, emissive, normal, params))
+                anyhow::Ok((color, normal, emissive, params))
             })
             .context("material bake tasks failed")?;
 
+        let mut writer = writer.lock();
+        let color = color(&mut writer)?;
+        let normal = normal.map(|push| push(&mut writer)).transpose()?;
+        let emissive = emissive.map(|push| push(&mut writer)).transpose()?;
+        let params = params.map(|push| push(&mut writer)).transpose()?;
+
         Ok(MaterialInfo {
             alpha_test: self.alpha_test,
             color,
@@ -749,26 +770,35 @@ impl MaterialAsset {
         })
     }
 
-    fn bake_normal_bitmap(
-        bitmap: &mut BitmapAsset,
+    fn read_normal_bitmap(
+        bitmap: &BitmapAsset,
         writer: &Arc<Mutex<Writer>>,
         project_dir: impl AsRef<Path>,
         path: Option<impl AsRef<Path>>,
-    ) -> anyhow::Result<Option<BitmapId>> {
+        compression: Option<Compression>,
+    ) -> anyhow::Result<Option<PushBitmap>> {
         let bitmap_buf = bitmap
-            .as_bitmap_buf()
+            .as_bitmap_buf(writer)
             .context("Unable to create normal bitmap buf")?;
 
         if Self::normal_bitmap_is_valid(&bitmap_buf) {
-            bitmap.bake_from_path(writer, project_dir, path).map(Some)
+            bitmap
+                .read_from_path(writer, project_dir, path, compression)
+                .map(Some)
         } else {
             if let Some(src) = bitmap.src() {
-                warn!(
-                    "Invalid normal map {}; treating material as having no normal map",
-                    src.display()
+                Writer::warn(
+                    writer,
+                    &format!(
+                        "Invalid normal map {}; treating material as having no normal map",
+                        src.display()
+                    ),
                 );
             } else {
-                warn!("Invalid inline normal map; treating material as having no normal map");
+                Writer::warn(
+                    writer,
+                    "Invalid inline normal map; treating material as having no normal map",
+                );
             }
 
             Ok(None)
@@ -811,12 +841,13 @@ impl MaterialAsset {
 
     fn scalar_ref_into_gray_image(
         scalar: &Option<ScalarRef>,
+        writer: &Mutex<Writer>,
         project_dir: impl AsRef<Path>,
         default: u8,
     ) -> anyhow::Result<GrayImage> {
         let bitmap = match scalar {
             Some(ScalarRef::Asset(bitmap)) => bitmap
-                .as_bitmap_buf()
+                .as_bitmap_buf(writer)
                 .context("Unable to create bitmap buf from scalar bitmap asset")?,
             Some(ScalarRef::Path(src)) => {
                 if is_toml(src) {
@@ -829,7 +860,7 @@ impl MaterialAsset {
                     BitmapAsset::new(src)
                 }
             }
-            .as_bitmap_buf()
+            .as_bitmap_buf(writer)
             .context("Unable to create bitmap buf")?,
             &Some(ScalarRef::Value(val)) => Bitmap::new(
                 BitmapColor::Linear,
@@ -1,7 +1,14 @@
 use {
-    super::{Canonicalize, Euler, Rotation, Writer, blob::BlobAsset, file_key, re_run_if_changed},
+    super::{
+        Canonicalize, Euler, Rotation, Writer,
+        blob::BlobAsset,
+        cache::{BakeCache, gltf_sources},
+        content::CompressionType,
+        file_key,
+    },
     crate::{
         MeshId,
+        compression::Compression,
         index::IndexBuffer,
         mesh::{Joint, Mesh, Primitive, Skin, VertexType},
     },
@@ -49,6 +56,7 @@ fn extract_transform(node: &Node) -> Mat4 {
 #[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct MeshAsset {
+    compression: Option<CompressionType>,
     data: Option<PathBuf>,
     euler: Option<Euler>,
     flip_x: Option<bool>,
@@ -86,6 +94,7 @@ impl MeshAsset {
 
     pub fn new(src: impl AsRef<Path>) -> Self {
         Self {
+            compression: None,
             data: None,
             euler: None,
             flip_x: None,
@@ -112,15 +121,22 @@ impl MeshAsset {
     }
 
     /// Reads and processes 3D mesh source files into an existing `.pak` file buffer.
+    ///
+    /// The mesh and its data blob are compressed using `compression` unless the mesh chooses its
+    /// own compression.
     pub fn bake(
         &self,
         writer: &Arc<Mutex<Writer>>,
         project_dir: impl AsRef<Path>,
         path: Option<impl AsRef<Path>>,
+        compression: Option<Compression>,
     ) -> anyhow::Result<MeshId> {
         let src = self
             .src()
             .ok_or(anyhow::Error::msg("unspecified mesh source"))?;
+        let compression = writer
+            .lock()
+            .asset_compression(self.compression, compression);
 
         // Early-out if we have already baked this mesh
         let asset = self.clone().into();
@@ -129,7 +145,7 @@ impl MeshAsset {
             return id.as_mesh().context("asset context returned non-mesh id");
         }
 
-        self.re_run_if_changed();
+        self.re_run_if_changed(&writer.lock());
 
         // If a path is given it will be available as a key inside the .pak (paths are not
         // given if the asset is specified inline - those are only available in the .pak via ID)
@@ -142,15 +158,16 @@ impl MeshAsset {
             info!("Baking mesh: {} (inline)", file_key(&project_dir, src));
         }
 
-        let mut mesh = self
-            .to_mesh(src)
-            .map_err(|err| Error::new(ErrorKind::InvalidData, err))
-            .context("Baking mesh data")?;
+        let mut mesh = BakeCache::get_or_bake(writer, &asset, &gltf_sources(src), || {
+            self.to_mesh(src)
+                .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+                .context("Baking mesh data")
+        })?;
 
         // Bake the unstructured data blob too
         if let Some(data) = &self.data {
             let data_id = BlobAsset::new(data)
-                .bake(writer, project_dir)
+                .bake(writer, project_dir, compression)
                 .context("Baking unstructured mesh data")?;
             mesh.set_data(data_id);
         }
@@ -161,7 +178,7 @@ impl MeshAsset {
             return id.as_mesh().context("asset context returned non-mesh id");
         }
 
-        let id = writer.push_mesh(mesh, key);
+        let id = writer.push_mesh(mesh, key, compression);
         writer.ctx.insert(asset, id.into());
 
         Ok(id)
@@ -1008,20 +1025,20 @@ impl MeshAsset {
         Ok(Mesh::new(primitives, skin))
     }
 
-    fn re_run_if_changed(&self) {
+    fn re_run_if_changed(&self, writer: &Writer) {
         // Watch the unstructered data file for changes, only if we're in a cargo build
         if let Some(data) = self.data() {
-            re_run_if_changed(data);
+            writer.re_run_if_changed(data);
         }
 
         if let Some(src) = &self.src {
             // Watch the GLTF file for changes, only if we're in a cargo build
-            re_run_if_changed(src);
+            writer.re_run_if_changed(src);
 
             // Just in case there is a GLTF bin file; also watch it for changes
             let mut src_bin = src.to_path_buf();
             src_bin.set_extension("bin");
-            re_run_if_changed(src_bin);
+            writer.re_run_if_changed(src_bin);
         }
     }
 }
@@ -1318,43 +1335,43 @@ impl VertexData {
 
         for idx in 0..self.positions.len() {
             let position = self.positions[idx];
-            buf.extend_from_slice(&position[0].to_ne_bytes());
-            buf.extend_from_slice(&position[1].to_ne_bytes());
-            buf.extend_from_slice(&position[2].to_ne_bytes());
+            buf.extend_from_slice(&position[0].to_le_bytes());
+            buf.extend_from_slice(&position[1].to_le_bytes());
+            buf.extend_from_slice(&position[2].to_le_bytes());
 
             if vertex_type.contains(VertexType::NORMAL) {
                 let normal = self.normals[idx];
-                buf.extend_from_slice(&normal[0].to_ne_bytes());
-                buf.extend_from_slice(&normal[1].to_ne_bytes());
-                buf.extend_from_slice(&normal[2].to_ne_bytes());
+                buf.extend_from_slice(&normal[0].to_le_bytes());
+                buf.extend_from_slice(&normal[1].to_le_bytes());
+                buf.extend_from_slice(&normal[2].to_le_bytes());
             }
 
             if vertex_type.contains(VertexType::TEXTURE0) {
                 let textures = self.textures.0[idx];
-                buf.extend_from_slice(&textures[0].to_ne_bytes());
-                buf.extend_from_slice(&textures[1].to_ne_bytes());
+                buf.extend_from_slice(&textures[0].to_le_bytes());
+                buf.extend_from_slice(&textures[1].to_le_bytes());
             }
 
             if vertex_type.contains(VertexType::TEXTURE1) {
                 let textures = self.textures.1[idx];
-                buf.extend_from_slice(&textures[0].to_ne_bytes());
-                buf.extend_from_slice(&textures[1].to_ne_bytes());
+                buf.extend_from_slice(&textures[0].to_le_bytes());
+                buf.extend_from_slice(&textures[1].to_le_bytes());
             }
 
             if vertex_type.contains(VertexType::TANGENT) {
                 let tangent = self.tangents[idx];
-                buf.extend_from_slice(&tangent[0].to_ne_bytes());
-                buf.extend_from_slice(&tangent[1].to_ne_bytes());
-                buf.extend_from_slice(&tangent[2].to_ne_bytes());
-                buf.extend_from_slice(&tangent[3].to_ne_bytes());
+                buf.extend_from_slice(&tangent[0].to_le_bytes());
+                buf.extend_from_slice(&tangent[1].to_le_bytes());
+                buf.extend_from_slice(&tangent[2].to_le_bytes());
+                buf.extend_from_slice(&tangent[3].to_le_bytes());
             }
 
             if let Some(skin) = self.skin.as_ref() {
                 let joints = skin.0[idx];
-                buf.extend_from_slice(&joints.to_ne_bytes());
+                buf.extend_from_slice(&joints.to_le_bytes());
 
                 let weights = skin.1[idx];
-                buf.extend_from_slice(&weights.to_ne_bytes());
+                buf.extend_from_slice(&weights.to_le_bytes());
             }
 
             assert_eq!(buf.len() % vertex_stride, 0);
@@ -1378,16 +1395,16 @@ impl VertexData {
 
         for idx in 0..self.positions.len() {
             let position = self.positions[idx];
-            buf.extend_from_slice(&position[0].to_ne_bytes());
-            buf.extend_from_slice(&position[1].to_ne_bytes());
-            buf.extend_from_slice(&position[2].to_ne_bytes());
+            buf.extend_from_slice(&position[0].to_le_bytes());
+            buf.extend_from_slice(&position[1].to_le_bytes());
+            buf.extend_from_slice(&position[2].to_le_bytes());
 
             if let Some(skin) = self.skin.as_ref() {
                 let joints = skin.0[idx];
-                buf.extend_from_slice(&joints.to_ne_bytes());
+                buf.extend_from_slice(&joints.to_le_bytes());
 
                 let weights = skin.1[idx];
-                buf.extend_from_slice(&weights.to_ne_bytes());
+                buf.extend_from_slice(&weights.to_le_bytes());
             }
 
             assert_eq!(buf.len() % vertex_stride, 0);
@@ -1454,9 +1471,9 @@ mod test {
             let stride = primitive.vertex_type().stride();
 
             for vertex in primitive.vertex_data().chunks_exact(stride) {
-                let x = f32::from_ne_bytes(vertex[0..4].try_into().unwrap());
-                let y = f32::from_ne_bytes(vertex[4..8].try_into().unwrap());
-                let z = f32::from_ne_bytes(vertex[8..12].try_into().unwrap());
+                let x = f32::from_le_bytes(vertex[0..4].try_into().unwrap());
+                let y = f32::from_le_bytes(vertex[4..8].try_into().unwrap());
+                let z = f32::from_le_bytes(vertex[8..12].try_into().unwrap());
 
                 max = max.max(x.abs()).max(y.abs()).max(z.abs());
             }
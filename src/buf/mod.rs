@@ -4,19 +4,27 @@
 
 mod anim;
 mod asset;
+mod baker;
 mod bitmap;
 mod blob;
+mod cache;
 mod content;
 mod material;
 mod mesh;
+mod observer;
 mod scene;
+mod unpack;
 mod writer;
 
+pub use self::{
+    baker::{BakeProgress, Baker},
+    observer::{BakeEvent, BakeObserver},
+};
+
 use {
     self::{
         asset::Asset,
         bitmap::BitmapAsset,
-        blob::BlobAsset,
         material::{ColorRef, EmissiveRef, MaterialAsset, NormalRef, ScalarRef},
         mesh::MeshAsset,
         scene::AssetRef,
@@ -24,9 +32,7 @@ use {
     },
     crate::PakBuf,
     anyhow::Context,
-    log::info,
     ordered_float::OrderedFloat,
-    parking_lot::Mutex,
     serde::{
         Deserialize, Deserializer,
         de::{Error, SeqAccess, Visitor, value::SeqAccessDeserializer},
@@ -34,16 +40,11 @@ use {
     std::{
         collections::BTreeSet,
         env::var,
-        fmt::{Debug, Formatter},
-        fs::create_dir_all,
+        fmt::{Debug, Display, Formatter},
+        io::{Read, Seek, Write},
         num::FpCategory,
         path::{Path, PathBuf},
-        sync::{
-            Arc,
-            atomic::{AtomicBool, Ordering},
-        },
     },
-    tokio::runtime::Runtime,
 };
 
 /// Given some parent directory and a filename, returns just the portion after the directory.
@@ -151,32 +152,50 @@ fn parse_hex_scalar(val: &str) -> Option<u8> {
     }
 }
 
-static CARGO_WATCHES_ENABLED: AtomicBool = AtomicBool::new(true);
+/// Options which control how content is baked.
+#[deprecated(note = "use `Baker`, which holds every bake setting")]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct BakeOptions {
+    /// Bakes every asset which can be baked and then returns a `BakeFailures` error describing all
+    /// assets which failed, instead of returning the first error.
+    ///
+    /// No `.pak` file is written if any asset fails.
+    pub keep_going: bool,
+}
 
-fn re_run_if_changed(p: impl AsRef<Path>) {
-    if is_cargo_build() && CARGO_WATCHES_ENABLED.load(Ordering::Relaxed) {
-        println!("cargo:rerun-if-changed={}", p.as_ref().display());
-    }
+/// Describes a selected asset which could not be baked.
+#[derive(Debug)]
+pub struct BakeFailure {
+    /// The asset file selected by the content file.
+    pub path: PathBuf,
+    /// Why the asset could not be baked.
+    pub error: anyhow::Error,
 }
 
-struct CargoWatchesGuard {
-    enabled: bool,
+/// Describes every selected asset which could not be baked when keep-going is set.
+#[derive(Debug)]
+pub struct BakeFailures {
+    /// The failed assets, in the order they were selected.
+    pub failures: Vec<BakeFailure>,
 }
 
-impl CargoWatchesGuard {
-    fn set(enabled: bool) -> Self {
-        Self {
-            enabled: CARGO_WATCHES_ENABLED.swap(enabled, Ordering::Relaxed),
+impl Display for BakeFailures {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.failures.len() {
+            1 => write!(f, "1 asset failed to bake")?,
+            len => write!(f, "{len} assets failed to bake")?,
         }
-    }
-}
 
-impl Drop for CargoWatchesGuard {
-    fn drop(&mut self) {
-        CARGO_WATCHES_ENABLED.store(self.enabled, Ordering::Relaxed);
+        for failure in &self.failures {
+            write!(f, "\n{}: {:#}", failure.path.display(), failure.error)?;
+        }
+
+        Ok(())
     }
 }
 
+impl std::error::Error for BakeFailures {}
+
 /// Describes an animation source selected by a content manifest.
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub struct SourceAnimation {
@@ -535,8 +554,21 @@ impl PakBuf {
         Ok(res.into_iter().collect())
     }
 
+    /// Bakes content into a `.pak` file using the directory of `src` as the asset root.
+    ///
+    /// See `Baker` for more settings.
     pub fn bake(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> anyhow::Result<()> {
-        Self::bake_with_dir(&src, dst, parent(&src))
+        Baker::new().bake(src, dst)
+    }
+
+    /// Bakes content into `writer` using the directory of `src` as the asset root.
+    ///
+    /// The written bytes may be read using `PakBuf::from_bytes`, so no files are written.
+    pub fn bake_to_writer(
+        src: impl AsRef<Path>,
+        writer: impl Read + Write + Seek,
+    ) -> anyhow::Result<()> {
+        Baker::new().bake_to_writer(src, writer)
     }
 
     /// Bakes content into a `.pak` file using `dir` as the asset root.
@@ -548,227 +580,58 @@ impl PakBuf {
         dst: impl AsRef<Path>,
         dir: impl AsRef<Path>,
     ) -> anyhow::Result<()> {
-        Self::bake_with_dir_impl(src, dst, dir, true)
+        Baker::new().with_asset_dir(dir).bake(src, dst)
     }
 
-    /// Bakes content into a `.pak` file using `dir` as the asset root without emitting Cargo
-    /// change watches.
+    /// Bakes content into a `.pak` file using `dir` as the asset root and the given options.
     ///
-    /// This is intended for build scripts that collect and emit their own precise watch list.
-    pub fn bake_with_dir_without_cargo_watches(
+    /// When `options.keep_going` is set, a failure returns a `BakeFailures` error which lists every
+    /// asset that failed to bake.
+    #[deprecated(note = "use `Baker::with_keep_going`")]
+    #[allow(deprecated)]
+    pub fn bake_with_options(
         src: impl AsRef<Path>,
         dst: impl AsRef<Path>,
         dir: impl AsRef<Path>,
+        options: BakeOptions,
     ) -> anyhow::Result<()> {
-        Self::bake_with_dir_impl(src, dst, dir, false)
+        Baker::new()
+            .with_asset_dir(dir)
+            .with_keep_going(options.keep_going)
+            .bake(src, dst)
     }
 
-    fn bake_with_dir_impl(
+    /// Bakes content into a `.pak` file using `dir` as the asset root and `cache_dir` as a
+    /// persistent bake cache.
+    ///
+    /// Bitmaps, meshes and animations whose settings and source files have not changed since they
+    /// were last baked using the same cache directory are read from the cache instead of being
+    /// baked again.
+    pub fn bake_with_cache(
         src: impl AsRef<Path>,
         dst: impl AsRef<Path>,
         dir: impl AsRef<Path>,
-        cargo_watches: bool,
+        cache_dir: impl AsRef<Path>,
     ) -> anyhow::Result<()> {
-        let _cargo_watches = CargoWatchesGuard::set(cargo_watches);
-
-        re_run_if_changed(&src);
-
-        let rt = Arc::new(Runtime::new()?);
-        let mut tasks: Vec<tokio::task::JoinHandle<anyhow::Result<()>>> = vec![];
-        let writer: Arc<Mutex<Writer>> = Arc::new(Mutex::new(Default::default()));
-
-        // Load the source file into an Asset::Content instance
-        let src_dir = dir.as_ref().to_path_buf();
-        let content = Asset::read(&src)?
-            .into_content()
-            .context("Unable to read asset file")?;
-
-        if let Some(compression) = content.compression() {
-            writer.lock().with_compression_is(Some(compression));
-        }
-
-        // Process each file we find as a separate runtime task
-        for asset_path in content.selected_asset_paths(&src_dir)? {
-            info!("processing {}", asset_path.display());
-
-            re_run_if_changed(&asset_path);
-
-            match asset_path
-                .extension()
-                .map(|ext| ext.to_string_lossy().into_owned())
-                .unwrap_or_default()
-                .to_lowercase()
-                .as_str()
-            {
-                "glb" | "gltf" => {
-                    // Note that direct references like this build a mesh, not an animation
-                    // To build an animation you must specify a .toml file
-                    let writer = Arc::clone(&writer);
-                    let src_dir = src_dir.clone();
-                    let asset_path = asset_path.clone();
-                    tasks.push(rt.spawn_blocking(move || {
-                        MeshAsset::new(&asset_path)
-                            .bake(&writer, &src_dir, Some(&asset_path))
-                            .context(asset_path.as_os_str().to_string_lossy().into_owned())?;
-                        Ok(())
-                    }));
-                }
-                "jpg" | "jpeg" | "png" | "bmp" | "tga" | "dds" | "webp" | "gif" | "ico"
-                | "tiff" => {
-                    let writer = Arc::clone(&writer);
-                    let src_dir = src_dir.clone();
-                    let asset_path = asset_path.clone();
-                    tasks.push(rt.spawn_blocking(move || {
-                        BitmapAsset::new(&asset_path)
-                            .bake_from_path(&writer, src_dir, Some(&asset_path))
-                            .context(asset_path.as_os_str().to_string_lossy().into_owned())?;
-                        Ok(())
-                    }));
-                }
-                "toml" => {
-                    let asset = Asset::read(&asset_path)?;
-                    let asset_parent = parent(&asset_path);
-
-                    match asset {
-                        Asset::Animation(mut anim) => {
-                            let writer = Arc::clone(&writer);
-                            let src_dir = src_dir.clone();
-                            let asset_path = asset_path.clone();
-                            let asset_parent = asset_parent.clone();
-                            tasks.push(rt.spawn_blocking(move || {
-                                anim.canonicalize(&src_dir, &asset_parent);
-                                anim.bake(&writer, src_dir, &asset_path).context(
-                                    asset_path.as_os_str().to_string_lossy().into_owned(),
-                                )?;
-                                Ok(())
-                            }));
-                        }
-                        Asset::Bitmap(mut bitmap) => {
-                            let writer = Arc::clone(&writer);
-                            let src_dir = src_dir.clone();
-                            let asset_path = asset_path.clone();
-                            let asset_parent = asset_parent.clone();
-                            tasks.push(rt.spawn_blocking(move || {
-                                bitmap.canonicalize(&src_dir, &asset_parent);
-                                bitmap
-                                    .bake_from_path(&writer, src_dir, Some(&asset_path))
-                                    .context(
-                                        asset_path.as_os_str().to_string_lossy().into_owned(),
-                                    )?;
-                                Ok(())
-                            }));
-                        }
-                        Asset::BitmapFont(mut blob) => {
-                            let writer = Arc::clone(&writer);
-                            let src_dir = src_dir.clone();
-                            let asset_path = asset_path.clone();
-                            let asset_parent = asset_parent.clone();
-                            tasks.push(rt.spawn_blocking(move || {
-                                blob.canonicalize(&src_dir, &asset_parent);
-                                blob.bake_bitmap_font(&writer, src_dir, &asset_path)
-                                    .context(
-                                        asset_path.as_os_str().to_string_lossy().into_owned(),
-                                    )?;
-                                Ok(())
-                            }));
-                        }
-                        Asset::Blob(mut blob) => {
-                            let writer = Arc::clone(&writer);
-                            let src_dir = src_dir.clone();
-                            let asset_path = asset_path.clone();
-                            let asset_parent = asset_parent.clone();
-                            tasks.push(rt.spawn_blocking(move || {
-                                blob.canonicalize(&src_dir, &asset_parent);
-                                blob.bake_from_path(&writer, src_dir, &asset_path).context(
-                                    asset_path.as_os_str().to_string_lossy().into_owned(),
-                                )?;
-                                Ok(())
-                            }));
-                        }
-                        Asset::Material(mut material) => {
-                            let writer = Arc::clone(&writer);
-                            let src_dir = src_dir.clone();
-                            let asset_path = asset_path.clone();
-                            let asset_parent = asset_parent.clone();
-                            let rt2 = rt.clone();
-                            tasks.push(rt.spawn_blocking(move || {
-                                material.canonicalize(&src_dir, &asset_parent);
-                                material
-                                    .bake(&rt2, &writer, src_dir, Some(&asset_path))
-                                    .context(
-                                        asset_path.as_os_str().to_string_lossy().into_owned(),
-                                    )?;
-                                Ok(())
-                            }));
-                        }
-                        Asset::Mesh(mut mesh) => {
-                            let writer = Arc::clone(&writer);
-                            let src_dir = src_dir.clone();
-                            let asset_path = asset_path.clone();
-                            let asset_parent = asset_parent.clone();
-                            tasks.push(rt.spawn_blocking(move || {
-                                mesh.canonicalize(&src_dir, &asset_parent);
-                                mesh.bake(&writer, &src_dir, Some(&asset_path)).context(
-                                    asset_path.as_os_str().to_string_lossy().into_owned(),
-                                )?;
-                                Ok(())
-                            }));
-                        }
-                        Asset::Scene(mut scene) => {
-                            let writer = Arc::clone(&writer);
-                            let src_dir = src_dir.clone();
-                            let asset_path = asset_path.clone();
-                            let asset_parent = asset_parent.clone();
-                            let rt2 = rt.clone();
-                            tasks.push(rt.spawn_blocking(move || {
-                                scene.canonicalize(&src_dir, &asset_parent);
-                                scene.bake(&rt2, &writer, &src_dir, &asset_path).context(
-                                    asset_path.as_os_str().to_string_lossy().into_owned(),
-                                )?;
-                                Ok(())
-                            }));
-                        }
-                        _ => anyhow::bail!("unhandled asset type"),
-                    }
-                }
-                _ => {
-                    let writer = Arc::clone(&writer);
-                    let src_dir = src_dir.clone();
-                    let asset_path = asset_path.clone();
-                    tasks.push(rt.spawn_blocking(move || {
-                        let blob = BlobAsset::new(&asset_path);
-                        blob.bake(&writer, &src_dir)
-                            .context(asset_path.as_os_str().to_string_lossy().into_owned())?;
-                        Ok(())
-                    }));
-                }
-            }
-
-            // IDs are assigned when an asset commits to Writer. Await each top-level asset before
-            // spawning the next one so task completion timing cannot change the generated IDs.
-            let task = tasks.pop().expect("asset branch must create one bake task");
-            rt.block_on(task).context("spawned task failed")??;
-        }
-
-        let result: anyhow::Result<()> = rt.block_on(async {
-            for task in tasks.into_iter() {
-                task.await.context("spawned task failed")??;
-            }
-
-            let dst = dst.as_ref().to_path_buf();
-            if let Some(parent) = dst.parent() {
-                create_dir_all(parent).context("Unable to create directory")?;
-            }
-
-            writer
-                .lock()
-                .write(&dst)
-                .context("Unable to write pak file")?;
-
-            Ok(())
-        });
+        Baker::new()
+            .with_asset_dir(dir)
+            .with_cache_dir(cache_dir)
+            .bake(src, dst)
+    }
 
-        result
+    /// Bakes content into a `.pak` file using `dir` as the asset root without emitting Cargo
+    /// change watches.
+    ///
+    /// This is intended for build scripts that collect and emit their own precise watch list.
+    pub fn bake_with_dir_without_cargo_watches(
+        src: impl AsRef<Path>,
+        dst: impl AsRef<Path>,
+        dir: impl AsRef<Path>,
+    ) -> anyhow::Result<()> {
+        Baker::new()
+            .with_asset_dir(dir)
+            .with_cargo_watches(false)
+            .bake(src, dst)
     }
 }
 
@@ -0,0 +1,59 @@
+use std::{path::Path, time::Duration};
+
+/// Describes something which happened while baking content.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub enum BakeEvent<'a> {
+    /// A selected asset of the content file has started baking.
+    AssetStarted {
+        /// The asset file selected by the content file.
+        path: &'a Path,
+    },
+
+    /// A selected asset of the content file, along with the assets it references, has been baked.
+    AssetFinished {
+        /// The asset file selected by the content file.
+        path: &'a Path,
+        /// How long the asset took to bake.
+        duration: Duration,
+        /// Serialized length, before compression, of the assets baked for this asset.
+        len: usize,
+    },
+
+    /// A baked asset was read from the bake cache instead of being baked again.
+    CacheHit {
+        /// The main source file of the cached asset.
+        path: &'a Path,
+    },
+
+    /// Baking continued after a problem which may need attention.
+    Warning {
+        /// Describes the problem.
+        message: &'a str,
+    },
+
+    /// A selected asset of the content file could not be baked.
+    Error {
+        /// The asset file selected by the content file.
+        path: &'a Path,
+        /// Why the asset could not be baked.
+        error: &'a anyhow::Error,
+    },
+}
+
+/// Receives the events of a bake, such as to show progress or to time each asset.
+///
+/// Events may be sent from any of the threads which bake assets.
+pub trait BakeObserver: Send + Sync {
+    /// Called for each event of a bake.
+    fn event(&self, event: BakeEvent<'_>);
+}
+
+impl<F> BakeObserver for F
+where
+    F: Fn(BakeEvent<'_>) + Send + Sync,
+{
+    fn event(&self, event: BakeEvent<'_>) {
+        self(event)
+    }
+}
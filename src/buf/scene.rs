@@ -1,10 +1,11 @@
 use {
     super::{
-        Asset, Canonicalize, Euler, Rotation, Writer, file_key, is_toml, material::MaterialAsset,
-        mesh::MeshAsset, parent,
+        Asset, Canonicalize, Euler, Rotation, Writer, content::CompressionType, file_key, is_toml,
+        material::MaterialAsset, mesh::MeshAsset, parent,
     },
     crate::{
         SceneId,
+        compression::Compression,
         scene::{DataData, GeometryData, ReferenceData, Scene},
     },
     anyhow::Context,
@@ -188,6 +189,8 @@ impl Geometry {
 /// Holds a description of scene entities and tagged data.
 #[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
 pub struct SceneAsset {
+    compression: Option<CompressionType>,
+
     #[serde(rename = "geometry")]
     geometries: Option<Box<[Geometry]>>,
 
@@ -197,12 +200,16 @@ pub struct SceneAsset {
 
 impl SceneAsset {
     /// Reads and processes scene source files into an existing `.pak` file buffer.
+    ///
+    /// The scene, and the materials and meshes it refers to, are compressed using `compression`
+    /// unless they choose their own compression.
     pub fn bake(
         &self,
         rt: &Runtime,
         writer: &Arc<Mutex<Writer>>,
         project_dir: impl AsRef<Path>,
         path: impl AsRef<Path>,
+        compression: Option<Compression>,
     ) -> anyhow::Result<SceneId> {
         // Early-out if we have already baked this scene
         let asset = self.clone().into();
@@ -210,6 +217,10 @@ impl SceneAsset {
             return h.as_scene().context("asset context returned non-scene id");
         }
 
+        let compression = writer
+            .lock()
+            .asset_compression(self.compression, compression);
+
         let key = file_key(&project_dir, &path);
 
         info!("Baking scene: {}", key);
@@ -236,7 +247,7 @@ impl SceneAsset {
                 geometry
                     .vertices
                     .iter()
-                    .map(|vertex| vertex.0.to_ne_bytes())
+                    .map(|vertex| vertex.0.to_le_bytes())
                     .for_each(|vertex| vertices.extend_from_slice(&vertex));
 
                 GeometryData {
@@ -254,94 +265,15 @@ impl SceneAsset {
         let references = self
             .refs()
             .iter()
-            .map(|reference| {
-                // all tags must be lower case (no localized text!)
-                let mut tags = vec![];
-                for tag in reference.tags() {
-                    let baked = tag.as_str().trim().to_lowercase();
-                    if let Err(idx) = tags.binary_search(&baked) {
-                        tags.insert(idx, baked);
-                    }
-                }
-
-                let data = reference
-                    .data()
-                    .map(|(key, value)| (key.clone(), value.clone().into()))
-                    .collect();
-
-                let materials = reference
-                    .materials()
-                    .iter()
-                    .map(|material| match material {
-                        AssetRef::Asset(material) => {
-                            // Material asset specified inline
-                            let material = material.clone();
-                            (None, material)
-                        }
-                        AssetRef::Path(src) => {
-                            if is_toml(src) {
-                                let asset = Asset::read(src)
-                                    .context("Reading material asset")
-                                    .expect("Unable to read material asset");
-
-                                // Asset file reference
-                                let mut material = asset.into_material().unwrap_or_else(|| {
-                                    panic!("Not a material: {}", src.display());
-                                });
-                                let src_dir = parent(src);
-                                material.canonicalize(&project_dir, &src_dir);
-                                (Some(src), material)
-                            } else {
-                                // Material color file reference
-                                (None, MaterialAsset::new(src))
-                            }
-                        }
+            .enumerate()
+            .map(|(idx, reference)| {
+                self.bake_reference(rt, writer, &project_dir, reference, compression)
+                    .with_context(|| match reference.id() {
+                        Some(id) => format!("Unable to bake scene {key} ref `{id}`"),
+                        None => format!("Unable to bake scene {key} ref {idx}"),
                     })
-                    .map(|(src, mut material)| {
-                        material
-                            .bake(rt, writer, &project_dir, src)
-                            .expect("material")
-                    })
-                    .collect();
-
-                let mesh = reference
-                    .mesh()
-                    .map(|mesh| match mesh {
-                        AssetRef::Asset(mesh) => {
-                            // Mesh asset specified inline
-                            let mesh = mesh.clone();
-                            (None, mesh)
-                        }
-                        AssetRef::Path(src) => {
-                            if is_toml(src) {
-                                // Asset file reference
-                                let mut mesh = Asset::read(src)
-                                    .context("Reading mesh asset")
-                                    .expect("Unable to read mesh asset")
-                                    .into_mesh()
-                                    .expect("Not a mesh");
-                                let src_dir = parent(src);
-                                mesh.canonicalize(&project_dir, &src_dir);
-                                (Some(src), mesh)
-                            } else {
-                                // Mesh file reference
-                                (None, MeshAsset::new(src))
-                            }
-                        }
-                    })
-                    .map(|(src, mesh)| mesh.bake(writer, &project_dir, src).expect("bake mesh"));
-
-                ReferenceData {
-                    data,
-                    id: reference.id().map(str::to_owned),
-                    materials,
-                    mesh,
-                    rotation: reference.rotation().into(),
-                    tags,
-                    translation: reference.translation().into(),
-                }
             })
-            .collect::<Box<_>>();
+            .collect::<anyhow::Result<Box<_>>>()?;
 
         let scene = Scene::new(geometries, references)?;
 
@@ -350,12 +282,118 @@ impl SceneAsset {
             return h.as_scene().context("asset context returned non-scene id");
         }
 
-        let id = writer.push_scene(scene, key);
+        let id = writer.push_scene(scene, key, compression);
         writer.ctx.insert(asset, id.into());
 
         Ok(id)
     }
 
+    /// Bakes the materials and mesh of one scene reference.
+    fn bake_reference(
+        &self,
+        rt: &Runtime,
+        writer: &Arc<Mutex<Writer>>,
+        project_dir: impl AsRef<Path>,
+        reference: &Reference,
+        compression: Option<Compression>,
+    ) -> anyhow::Result<ReferenceData> {
+        // all tags must be lower case (no localized text!)
+        let mut tags = vec![];
+        for tag in reference.tags() {
+            let baked = tag.as_str().trim().to_lowercase();
+            if let Err(idx) = tags.binary_search(&baked) {
+                tags.insert(idx, baked);
+            }
+        }
+
+        let data = reference
+            .data()
+            .map(|(key, value)| (key.clone(), value.clone().into()))
+            .collect();
+
+        let materials = reference
+            .materials()
+            .iter()
+            .map(|material| match material {
+                AssetRef::Asset(material) => {
+                    // Material asset specified inline
+                    let mut material = material.clone();
+                    material
+                        .bake(rt, writer, &project_dir, None::<&Path>, compression)
+                        .context("Unable to bake inline material")
+                }
+                AssetRef::Path(src) => {
+                    let mut material = if is_toml(src) {
+                        // Asset file reference
+                        let mut material = Asset::read(src)
+                            .with_context(|| {
+                                format!("Unable to read material asset {}", src.display())
+                            })?
+                            .into_material()
+                            .with_context(|| format!("Not a material: {}", src.display()))?;
+                        let src_dir = parent(src);
+                        material.canonicalize(&project_dir, &src_dir);
+                        material
+                    } else {
+                        // Material color file reference
+                        MaterialAsset::new(src)
+                    };
+
+                    material
+                        .bake(
+                            rt,
+                            writer,
+                            &project_dir,
+                            is_toml(src).then_some(src),
+                            compression,
+                        )
+                        .with_context(|| format!("Unable to bake material {}", src.display()))
+                }
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let mesh = reference
+            .mesh()
+            .map(|mesh| match mesh {
+                AssetRef::Asset(mesh) => {
+                    // Mesh asset specified inline
+                    mesh.bake(writer, &project_dir, None::<&Path>, compression)
+                        .context("Unable to bake inline mesh")
+                }
+                AssetRef::Path(src) => {
+                    let (src_path, mesh) = if is_toml(src) {
+                        // Asset file reference
+                        let mut mesh = Asset::read(src)
+                            .with_context(|| {
+                                format!("Unable to read mesh asset {}", src.display())
+                            })?
+                            .into_mesh()
+                            .with_context(|| format!("Not a mesh: {}", src.display()))?;
+                        let src_dir = parent(src);
+                        mesh.canonicalize(&project_dir, &src_dir);
+                        (Some(src), mesh)
+                    } else {
+                        // Mesh file reference
+                        (None, MeshAsset::new(src))
+                    };
+
+                    mesh.bake(writer, &project_dir, src_path, compression)
+                        .with_context(|| format!("Unable to bake mesh {}", src.display()))
+                }
+            })
+            .transpose()?;
+
+        Ok(ReferenceData {
+            data,
+            id: reference.id().map(str::to_owned),
+            materials,
+            mesh,
+            rotation: reference.rotation().into(),
+            tags,
+            translation: reference.translation().into(),
+        })
+    }
+
     /// Individual geometries within a scene.
     #[allow(unused)]
     pub fn geometries(&self) -> &[Geometry] {
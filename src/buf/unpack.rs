@@ -0,0 +1,574 @@
+use {
+    crate::{
+        AssetKind, Id, MeshId, Pak, PakBuf,
+        bitmap::{Bitmap, BitmapColor, BitmapFormat},
+        index::IndexType,
+        mesh::{Mesh, VertexType},
+        scene::{DataRef, Scene},
+    },
+    anyhow::{Context, bail},
+    glam::Mat4,
+    gltf::binary::{Glb, Header},
+    log::info,
+    png::{BitDepth, ColorType, Encoder, ScaledFloat, SrgbRenderingIntent},
+    serde_json::json,
+    std::{
+        borrow::Cow,
+        collections::HashMap,
+        fs::{create_dir_all, write},
+        path::{Component, Path, PathBuf},
+    },
+    toml::{Table, Value as TomlValue},
+};
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_BYTE: u32 = 5121;
+const UNSIGNED_INT: u32 = 5125;
+const UNSIGNED_SHORT: u32 = 5123;
+
+impl Bitmap {
+    /// Encodes this bitmap as a PNG image.
+    ///
+    /// Only the base level is stored in a pak, so the image holds no mip levels.
+    ///
+    /// The PNG color type matches `format` and `color` is recorded using the `sRGB` chunk, or a
+    /// `gAMA` chunk with a gamma of `1.0` for linear bitmaps.
+    pub fn to_png(&self) -> anyhow::Result<Vec<u8>> {
+        let (width, height) = self.extent();
+        let mut res = vec![];
+
+        {
+            let mut encoder = Encoder::new(&mut res, width, height);
+            encoder.set_color(match self.format() {
+                BitmapFormat::R => ColorType::Grayscale,
+                BitmapFormat::Rg => ColorType::GrayscaleAlpha,
+                BitmapFormat::Rgb => ColorType::Rgb,
+                BitmapFormat::Rgba => ColorType::Rgba,
+            });
+            encoder.set_depth(BitDepth::Eight);
+
+            match self.color() {
+                BitmapColor::Linear => encoder.set_source_gamma(ScaledFloat::new(1.0)),
+                BitmapColor::Srgb => encoder.set_source_srgb(SrgbRenderingIntent::Perceptual),
+            }
+
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(self.pixels())?;
+            writer.finish()?;
+        }
+
+        Ok(res)
+    }
+}
+
+impl Mesh {
+    /// Encodes this mesh as a binary glTF (`.glb`) file.
+    ///
+    /// Each primitive is written using its first level of detail and refers to a placeholder
+    /// material named after its material index. Skinned meshes include a joint hierarchy whose
+    /// transforms are found from the inverse bind matrices.
+    pub fn to_glb(&self) -> anyhow::Result<Vec<u8>> {
+        let mut accessors = vec![];
+        let mut bin = vec![];
+        let mut buffer_views = vec![];
+        let mut push_view = |bin: &mut Vec<u8>, data: &[u8], stride: Option<usize>, target| {
+            while !bin.len().is_multiple_of(4) {
+                bin.push(0);
+            }
+
+            let mut view = json!({
+                "buffer": 0,
+                "byteLength": data.len(),
+                "byteOffset": bin.len(),
+            });
+            if let Some(stride) = stride {
+                view["byteStride"] = json!(stride);
+            }
+            if let Some(target) = target {
+                view["target"] = json!(target);
+            }
+
+            bin.extend_from_slice(data);
+            buffer_views.push(view);
+
+            buffer_views.len() - 1
+        };
+
+        let mut material_count = 0;
+        let mut primitives = vec![];
+
+        for primitive in self.primitives() {
+            let vertex_type = primitive.vertex_type();
+            let stride = vertex_type.stride();
+            let count = primitive.vertex_count();
+            let vertices = primitive.vertex_data();
+            let view = push_view(&mut bin, vertices, Some(stride), Some(ARRAY_BUFFER));
+
+            // Positions are required to have bounds
+            let mut min = [f32::MAX; 3];
+            let mut max = [f32::MIN; 3];
+            for vertex in vertices.chunks_exact(stride) {
+                for (axis, value) in vertex[..12].chunks_exact(4).enumerate() {
+                    let value = f32::from_le_bytes(value.try_into().unwrap());
+                    min[axis] = min[axis].min(value);
+                    max[axis] = max[axis].max(value);
+                }
+            }
+
+            // Attributes are interleaved in this order by the baker
+            let mut attributes = serde_json::Map::new();
+            let mut offset = 0;
+            for (flag, name, ty, component_type, len) in [
+                (VertexType::POSITION, "POSITION", "VEC3", FLOAT, 12),
+                (VertexType::NORMAL, "NORMAL", "VEC3", FLOAT, 12),
+                (VertexType::TEXTURE0, "TEXCOORD_0", "VEC2", FLOAT, 8),
+                (VertexType::TEXTURE1, "TEXCOORD_1", "VEC2", FLOAT, 8),
+                (VertexType::TANGENT, "TANGENT", "VEC4", FLOAT, 16),
+                (
+                    VertexType::JOINTS_WEIGHTS,
+                    "JOINTS_0",
+                    "VEC4",
+                    UNSIGNED_BYTE,
+                    4,
+                ),
+                (
+                    VertexType::JOINTS_WEIGHTS,
+                    "WEIGHTS_0",
+                    "VEC4",
+                    UNSIGNED_BYTE,
+                    4,
+                ),
+            ] {
+                if !vertex_type.contains(flag) {
+                    continue;
+                }
+
+                let mut accessor = json!({
+                    "bufferView": view,
+                    "byteOffset": offset,
+                    "componentType": component_type,
+                    "count": count,
+                    "type": ty,
+                });
+                if name == "POSITION" {
+                    accessor["min"] = json!(min);
+                    accessor["max"] = json!(max);
+                } else if name == "WEIGHTS_0" {
+                    accessor["normalized"] = json!(true);
+                }
+
+                attributes.insert(name.to_owned(), json!(accessors.len()));
+                accessors.push(accessor);
+                offset += len;
+            }
+
+            let mut res = json!({
+                "attributes": attributes,
+                "material": primitive.material(),
+            });
+            material_count = material_count.max(primitive.material() as usize + 1);
+
+            if let Some(indices) = primitive.lods().first() {
+                let view = push_view(
+                    &mut bin,
+                    indices.as_bytes(),
+                    None,
+                    Some(ELEMENT_ARRAY_BUFFER),
+                );
+                res["indices"] = json!(accessors.len());
+                accessors.push(json!({
+                    "bufferView": view,
+                    "componentType": match indices.index_type() {
+                        IndexType::U8 => UNSIGNED_BYTE,
+                        IndexType::U16 => UNSIGNED_SHORT,
+                        IndexType::U32 => UNSIGNED_INT,
+                    },
+                    "count": indices.index_count(),
+                    "type": "SCALAR",
+                }));
+            }
+
+            primitives.push(res);
+        }
+
+        let mut nodes = vec![json!({ "mesh": 0 })];
+        let mut root_nodes = vec![json!(0)];
+        let mut skins = vec![];
+
+        if let Some(skin) = self.skin() {
+            let joints = skin.joints();
+            let first_node = nodes.len();
+            let world = joints
+                .iter()
+                .map(|joint| Mat4::from_cols_array(&joint.inverse_bind).inverse())
+                .collect::<Vec<_>>();
+
+            for (idx, joint) in joints.iter().enumerate() {
+                let is_root = joint.parent_index == idx;
+                let local = if is_root {
+                    world[idx]
+                } else {
+                    world[joint.parent_index].inverse() * world[idx]
+                };
+                let children = joints
+                    .iter()
+                    .enumerate()
+                    .filter(|(child, other)| *child != idx && other.parent_index == idx)
+                    .map(|(child, _)| first_node + child)
+                    .collect::<Vec<_>>();
+
+                let mut node = json!({
+                    "matrix": local.to_cols_array(),
+                    "name": joint.name,
+                });
+                if !children.is_empty() {
+                    node["children"] = json!(children);
+                }
+                if is_root {
+                    root_nodes.push(json!(first_node + idx));
+                }
+
+                nodes.push(node);
+            }
+
+            let inverse_binds = joints
+                .iter()
+                .flat_map(|joint| joint.inverse_bind)
+                .flat_map(f32::to_le_bytes)
+                .collect::<Vec<_>>();
+            let view = push_view(&mut bin, &inverse_binds, None, None);
+            skins.push(json!({
+                "inverseBindMatrices": accessors.len(),
+                "joints": (first_node..nodes.len()).collect::<Vec<_>>(),
+            }));
+            accessors.push(json!({
+                "bufferView": view,
+                "componentType": FLOAT,
+                "count": joints.len(),
+                "type": "MAT4",
+            }));
+            nodes[0]["skin"] = json!(0);
+        }
+
+        let mut root = json!({
+            "accessors": accessors,
+            "asset": { "generator": concat!("pak ", env!("CARGO_PKG_VERSION")), "version": "2.0" },
+            "buffers": [{ "byteLength": bin.len() }],
+            "bufferViews": buffer_views,
+            "materials": (0..material_count)
+                .map(|idx| json!({ "name": format!("material{idx}") }))
+                .collect::<Vec<_>>(),
+            "meshes": [{ "primitives": primitives }],
+            "nodes": nodes,
+            "scene": 0,
+            "scenes": [{ "nodes": root_nodes }],
+        });
+        if !skins.is_empty() {
+            root["skins"] = json!(skins);
+        }
+
+        let json = serde_json::to_vec(&root)?;
+        let glb = Glb {
+            header: Header {
+                magic: *b"glTF",
+                version: 2,
+                length: 0,
+            },
+            json: Cow::Owned(json),
+            bin: Some(Cow::Owned(bin)),
+        };
+
+        Ok(glb.to_vec()?)
+    }
+}
+
+impl PakBuf {
+    /// Writes the assets of this pak back to standard files within `dir` for inspection, returning
+    /// the paths which were written.
+    ///
+    /// See `unpack_key` for the files written for each kind of asset. Animations, bitmap fonts and
+    /// materials are skipped. Meshes which have no key are written to `unkeyed/mesh-{id}.glb` so
+    /// that the scenes which refer to them remain complete.
+    pub fn unpack(&mut self, dir: impl AsRef<Path>) -> anyhow::Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        let ids = self
+            .data
+            .ids
+            .iter()
+            .map(|(key, id)| (key.clone(), *id))
+            .collect::<Vec<_>>();
+        let mut res = vec![];
+
+        for (key, id) in &ids {
+            match id.kind() {
+                AssetKind::Animation | AssetKind::BitmapFont | AssetKind::Material => {
+                    info!("Skipping {}: {key}", id.kind());
+                }
+                _ => res.push(self.unpack_key(key, dir)?),
+            }
+        }
+
+        for id in 0..self.mesh_count() {
+            if !ids
+                .iter()
+                .any(|(_, key_id)| *key_id == Id::Mesh(MeshId(id)))
+            {
+                let path = dir.join(unkeyed_path(AssetKind::Mesh, id));
+                let mesh = self.read_mesh_id(MeshId(id))?;
+                write_file(&path, mesh.to_glb()?)?;
+                res.push(path);
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Writes the asset stored under `key` back to a standard file within `dir`, returning the
+    /// path which was written.
+    ///
+    /// - Blobs are written as-is to `{key}`
+    /// - Bitmaps are written as PNG images to `{key}.png` (see `Bitmap::to_png`)
+    /// - Meshes are written as binary glTF files to `{key}.glb` (see `Mesh::to_glb`)
+    /// - Scenes are written using the `[scene]` schema to `{key}.toml`
+    ///
+    /// The extension is not repeated when `key` already ends with it. Scene references name the
+    /// meshes and materials they use by the project-rooted path of their key; materials are not
+    /// unpacked and unkeyed materials are named `/unkeyed/material-{id}.toml`.
+    ///
+    /// Keys which are absolute or contain `..` are rejected instead of being written outside of
+    /// `dir`.
+    pub fn unpack_key(&mut self, key: &str, dir: impl AsRef<Path>) -> anyhow::Result<PathBuf> {
+        let dir = dir.as_ref();
+        let (path, data) = match self.asset_kind(key) {
+            Some(AssetKind::Blob) => (key_path(dir, key)?, self.read_blob(key)?),
+            Some(AssetKind::Bitmap) => (
+                key_path(dir, &with_extension(key, "png"))?,
+                self.read_bitmap(key)?.to_png()?,
+            ),
+            Some(AssetKind::Mesh) => (
+                key_path(dir, &with_extension(key, "glb"))?,
+                self.read_mesh(key)?.to_glb()?,
+            ),
+            Some(AssetKind::Scene) => {
+                let scene = self.read_scene(key)?;
+                let toml = toml::to_string(&self.scene_table(&scene))?;
+
+                (
+                    key_path(dir, &with_extension(key, "toml"))?,
+                    toml.into_bytes(),
+                )
+            }
+            Some(kind) => bail!("unable to unpack {kind} `{key}`"),
+            None => bail!("key `{key}` not found"),
+        };
+
+        write_file(&path, data)?;
+
+        Ok(path)
+    }
+
+    /// Returns the `[scene]` table which describes the given scene.
+    fn scene_table(&self, scene: &Scene) -> Table {
+        let keys = self
+            .data
+            .ids
+            .iter()
+            .map(|(key, id)| (*id, key.as_str()))
+            .collect::<HashMap<_, _>>();
+        let asset_path = |id: Id, ext| match keys.get(&id) {
+            Some(key) => format!("/{}", with_extension(key, ext)),
+            None => format!("/{}", unkeyed_path(id.kind(), id.index())),
+        };
+
+        let geometries = scene
+            .geometries()
+            .map(|geometry| {
+                let mut table = Table::new();
+
+                if let Some(id) = geometry.id() {
+                    table.insert("id".to_owned(), id.into());
+                }
+
+                table.insert(
+                    "indices".to_owned(),
+                    geometry
+                        .index_buf()
+                        .as_u32()
+                        .into_iter()
+                        .map(i64::from)
+                        .collect::<Vec<_>>()
+                        .into(),
+                );
+                table.insert(
+                    "vertices".to_owned(),
+                    geometry
+                        .vertices()
+                        .flatten()
+                        .map(f64::from)
+                        .collect::<Vec<_>>()
+                        .into(),
+                );
+                transform_table(&mut table, geometry.rotation(), geometry.translation());
+                tags_table(&mut table, geometry.tags());
+                data_table(&mut table, geometry.data_keys(), |key| geometry.data(key));
+
+                TomlValue::Table(table)
+            })
+            .collect::<Vec<_>>();
+
+        let refs = scene
+            .refs()
+            .map(|reference| {
+                let mut table = Table::new();
+
+                if let Some(id) = reference.id() {
+                    table.insert("id".to_owned(), id.into());
+                }
+
+                if let Some(mesh) = reference.mesh() {
+                    table.insert("mesh".to_owned(), asset_path(mesh.into(), "glb").into());
+                }
+
+                if !reference.materials().is_empty() {
+                    table.insert(
+                        "materials".to_owned(),
+                        reference
+                            .materials()
+                            .iter()
+                            .map(|material| asset_path((*material).into(), "toml"))
+                            .collect::<Vec<_>>()
+                            .into(),
+                    );
+                }
+
+                transform_table(&mut table, reference.rotation(), reference.translation());
+                tags_table(&mut table, reference.tags());
+                data_table(&mut table, reference.data_keys(), |key| reference.data(key));
+
+                TomlValue::Table(table)
+            })
+            .collect::<Vec<_>>();
+
+        let mut table = Table::new();
+        if !geometries.is_empty() {
+            table.insert("geometry".to_owned(), geometries.into());
+        }
+        if !refs.is_empty() {
+            table.insert("ref".to_owned(), refs.into());
+        }
+
+        Table::from_iter([("scene".to_owned(), table.into())])
+    }
+}
+
+fn data_table<'a>(
+    table: &mut Table,
+    keys: impl Iterator<Item = &'a str>,
+    data: impl Fn(&str) -> Option<DataRef<'a>>,
+) {
+    fn value(data: DataRef) -> TomlValue {
+        if let Some(value) = data.as_bool() {
+            value.into()
+        } else if let Some(value) = data.as_f32() {
+            f64::from(value).into()
+        } else if let Some(value) = data.as_i32() {
+            i64::from(value).into()
+        } else if let Some(value) = data.as_str() {
+            value.into()
+        } else {
+            data.expect_iter().map(value).collect::<Vec<_>>().into()
+        }
+    }
+
+    let data = keys
+        .filter_map(|key| data(key).map(|data| (key.to_owned(), value(data))))
+        .collect::<Table>();
+
+    if !data.is_empty() {
+        table.insert("data".to_owned(), data.into());
+    }
+}
+
+fn tags_table<'a>(table: &mut Table, tags: impl ExactSizeIterator<Item = &'a str>) {
+    if tags.len() > 0 {
+        table.insert("tags".to_owned(), tags.collect::<Vec<_>>().into());
+    }
+}
+
+fn transform_table(table: &mut Table, rotation: [f32; 4], translation: [f32; 3]) {
+    table.insert(
+        "rotation".to_owned(),
+        rotation.map(f64::from).to_vec().into(),
+    );
+    table.insert(
+        "translation".to_owned(),
+        translation.map(f64::from).to_vec().into(),
+    );
+}
+
+/// Joins `key` to `dir`, unless the key would name a file outside of `dir`.
+fn key_path(dir: &Path, key: &str) -> anyhow::Result<PathBuf> {
+    let path = Path::new(key);
+    if path.components().next().is_none()
+        || !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        bail!("unable to unpack key `{key}` outside of {}", dir.display());
+    }
+
+    Ok(dir.join(path))
+}
+
+/// Returns the path used for an asset which has no key.
+fn unkeyed_path(kind: AssetKind, id: usize) -> String {
+    let ext = match kind {
+        AssetKind::Mesh => "glb",
+        _ => "toml",
+    };
+
+    format!("unkeyed/{kind}-{id}.{ext}")
+}
+
+/// Appends `ext` to `key` unless it already ends with that extension.
+fn with_extension(key: &str, ext: &str) -> String {
+    match Path::new(key).extension() {
+        Some(key_ext) if key_ext.eq_ignore_ascii_case(ext) => key.to_owned(),
+        _ => format!("{key}.{ext}"),
+    }
+}
+
+fn write_file(path: &Path, data: impl AsRef<[u8]>) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    write(path, data).with_context(|| format!("writing {}", path.display()))
+}
+
+#[cfg(test)]
+mod test {
+    use {super::key_path, std::path::Path};
+
+    #[test]
+    fn key_paths_stay_within_dir() {
+        let dir = Path::new("unpacked");
+
+        assert_eq!(
+            key_path(dir, "art/bitmap.png").unwrap(),
+            dir.join("art/bitmap.png")
+        );
+
+        for key in [
+            "",
+            "/etc/passwd",
+            "../escaped",
+            "art/../../escaped",
+            "./art",
+        ] {
+            assert!(key_path(dir, key).is_err(), "{key}");
+        }
+    }
+}
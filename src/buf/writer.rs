@@ -1,31 +1,119 @@
 use {
-    super::{super::compression::Compression, Asset},
+    super::{
+        super::{
+            compression::Compression,
+            format::{self, FORMAT_VERSION, Header},
+        },
+        Asset,
+        cache::BakeCache,
+        content::CompressionType,
+        is_cargo_build,
+        observer::{BakeEvent, BakeObserver},
+    },
     crate::{
         AnimationId, BitmapFontId, BitmapId, BlobId, Data, DataRef, Id, MaterialId, MaterialInfo,
         MeshId, SceneId, anim::Animation, bitmap::Bitmap, bitmap_font::BitmapFont, mesh::Mesh,
-        pak_hash_stream, scene::Scene,
+        scene::Scene, write_hash_trailer, write_stream_hash_trailer,
     },
-    log::trace,
+    log::{trace, warn},
+    parking_lot::Mutex,
     serde::Serialize,
     std::{
+        borrow::Cow,
         collections::HashMap,
-        fs::{File, OpenOptions},
-        io::{BufWriter, Error, ErrorKind, Seek, SeekFrom, Write},
+        fs::File,
+        io::{BufWriter, Error, ErrorKind, Read, Seek, Write},
         path::Path,
+        sync::Arc,
     },
 };
 
-#[derive(Default)]
+/// The largest Zstandard dictionary trained for a pak.
+const DICTIONARY_LEN: usize = 112_640;
+
 pub struct Writer {
+    baked_len: usize,
+    pub(super) cache: Option<BakeCache>,
+    cargo_watches: bool,
     compression: Option<Compression>,
+    compression_types: HashMap<CompressionType, Option<Compression>>,
+    compressions: HashMap<Id, Option<Compression>>,
     pub(super) ctx: HashMap<Asset, Id>,
     data: Data,
+    format_version: Option<u16>,
+    min_compress_size: usize,
+    observer: Option<Arc<dyn BakeObserver>>,
+    serialized: HashMap<Id, Option<Vec<u8>>>,
 }
 
 impl Writer {
-    pub fn push_animation(&mut self, animation: Animation, key: Option<String>) -> AnimationId {
+    /// Returns the serialized length, before compression, of every asset pushed so far.
+    pub fn baked_len(&self) -> usize {
+        self.baked_len
+    }
+
+    /// Serializes an asset being pushed; the bytes are compressed and stored, replacing the
+    /// placeholder ref of the asset, when the pak is written.
+    fn serialize(&mut self, id: impl Into<Id>, data: &impl Serialize) {
+        // Assets which cannot be serialized are left for `write` to report
+        let data = bincode::serde::encode_to_vec(data, bincode::config::legacy()).ok();
+        self.baked_len += data.as_ref().map_or(0, Vec::len);
+        self.serialized.insert(id.into(), data);
+    }
+
+    /// Sends `event` to the observer of the bake held by `writer`, if any; the lock is released
+    /// before the observer is called, so observers may be slow without stalling other assets.
+    pub fn notify(writer: &Mutex<Self>, event: BakeEvent<'_>) {
+        let observer = writer.lock().observer.clone();
+        if let Some(observer) = observer {
+            observer.event(event);
+        }
+    }
+
+    /// Logs a warning and sends it to the observer of the bake held by `writer`, if any.
+    pub fn warn(writer: &Mutex<Self>, message: &str) {
+        warn!("{message}");
+        Self::notify(writer, BakeEvent::Warning { message });
+    }
+
+    /// Returns the compression of an asset which chose `compression`, or `inherited` (the
+    /// compression of the asset or group which refers to it) if it did not choose one.
+    pub fn asset_compression(
+        &self,
+        compression: Option<CompressionType>,
+        inherited: Option<Compression>,
+    ) -> Option<Compression> {
+        compression.map_or(inherited, |compression| {
+            self.compression_types
+                .get(&compression)
+                .copied()
+                .unwrap_or(self.compression)
+        })
+    }
+
+    /// Returns the compression of the index, which assets use unless they choose another.
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+
+    /// Tells Cargo to re-run the build script when `path` changes, if this is a build script and
+    /// Cargo watches are enabled.
+    pub fn re_run_if_changed(&self, path: impl AsRef<Path>) {
+        if self.cargo_watches && is_cargo_build() {
+            println!("cargo:rerun-if-changed={}", path.as_ref().display());
+        }
+    }
+
+    pub fn push_animation(
+        &mut self,
+        animation: Animation,
+        key: Option<String>,
+        compression: Option<Compression>,
+    ) -> AnimationId {
         let id = AnimationId(self.data.anims.len());
-        self.data.anims.push(DataRef::Data(animation));
+        self.serialize(id, &animation);
+        self.compressions.insert(id.into(), compression);
+        self.data.anims.push(DataRef::Ref(0..0));
 
         if let Some(key) = key {
             assert!(!self.data.ids.contains_key(&key));
@@ -40,9 +128,12 @@ impl Writer {
         &mut self,
         bitmap_font: BitmapFont,
         key: Option<String>,
+        compression: Option<Compression>,
     ) -> BitmapFontId {
         let id = BitmapFontId(self.data.bitmap_fonts.len());
-        self.data.bitmap_fonts.push(DataRef::Data(bitmap_font));
+        self.serialize(id, &bitmap_font);
+        self.compressions.insert(id.into(), compression);
+        self.data.bitmap_fonts.push(DataRef::Ref(0..0));
 
         if let Some(key) = key {
             assert!(!self.data.ids.contains_key(&key));
@@ -53,9 +144,16 @@ impl Writer {
         id
     }
 
-    pub fn push_bitmap(&mut self, bitmap: Bitmap, key: Option<String>) -> BitmapId {
+    pub fn push_bitmap(
+        &mut self,
+        bitmap: Bitmap,
+        key: Option<String>,
+        compression: Option<Compression>,
+    ) -> BitmapId {
         let id = BitmapId(self.data.bitmaps.len());
-        self.data.bitmaps.push(DataRef::Data(bitmap));
+        self.serialize(id, &bitmap);
+        self.compressions.insert(id.into(), compression);
+        self.data.bitmaps.push(DataRef::Ref(0..0));
 
         if let Some(key) = key {
             assert!(!self.data.ids.contains_key(&key));
@@ -66,9 +164,16 @@ impl Writer {
         id
     }
 
-    pub fn push_blob(&mut self, blob: Vec<u8>, key: Option<String>) -> BlobId {
+    pub fn push_blob(
+        &mut self,
+        blob: Vec<u8>,
+        key: Option<String>,
+        compression: Option<Compression>,
+    ) -> BlobId {
         let id = BlobId(self.data.blobs.len());
-        self.data.blobs.push(DataRef::Data(blob));
+        self.serialize(id, &blob);
+        self.compressions.insert(id.into(), compression);
+        self.data.blobs.push(DataRef::Ref(0..0));
 
         if let Some(key) = key {
             assert!(!self.data.ids.contains_key(&key));
@@ -92,9 +197,16 @@ impl Writer {
         id
     }
 
-    pub fn push_mesh(&mut self, mesh: Mesh, key: Option<String>) -> MeshId {
+    pub fn push_mesh(
+        &mut self,
+        mesh: Mesh,
+        key: Option<String>,
+        compression: Option<Compression>,
+    ) -> MeshId {
         let id = MeshId(self.data.meshes.len());
-        self.data.meshes.push(DataRef::Data(mesh));
+        self.serialize(id, &mesh);
+        self.compressions.insert(id.into(), compression);
+        self.data.meshes.push(DataRef::Ref(0..0));
 
         if let Some(key) = key {
             assert!(!self.data.ids.contains_key(&key));
@@ -105,9 +217,16 @@ impl Writer {
         id
     }
 
-    pub fn push_scene(&mut self, scene: Scene, key: String) -> SceneId {
+    pub fn push_scene(
+        &mut self,
+        scene: Scene,
+        key: String,
+        compression: Option<Compression>,
+    ) -> SceneId {
         let id = SceneId(self.data.scenes.len());
-        self.data.scenes.push(DataRef::Data(scene));
+        self.serialize(id, &scene);
+        self.compressions.insert(id.into(), compression);
+        self.data.scenes.push(DataRef::Ref(0..0));
 
         assert!(!self.data.ids.contains_key(&key));
 
@@ -116,17 +235,112 @@ impl Writer {
         id
     }
 
+    /// Trains a dictionary across the serialized assets which are compressed using one, or returns
+    /// `None` (and stops using a dictionary) when training is not possible.
+    fn train_dictionary(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let uses_dictionary = |compression: Option<Compression>| {
+            compression.is_some_and(|compression| compression.uses_dictionary())
+        };
+
+        if !uses_dictionary(self.compression)
+            && !self.compressions.values().copied().any(uses_dictionary)
+        {
+            return Ok(None);
+        }
+
+        let data = &self.data;
+        let ids = (0..data.anims.len())
+            .map(|idx| AnimationId(idx).into())
+            .chain((0..data.bitmaps.len()).map(|idx| BitmapId(idx).into()))
+            .chain((0..data.blobs.len()).map(|idx| BlobId(idx).into()))
+            .chain((0..data.bitmap_fonts.len()).map(|idx| BitmapFontId(idx).into()))
+            .chain((0..data.meshes.len()).map(|idx| MeshId(idx).into()))
+            .chain((0..data.scenes.len()).map(|idx| SceneId(idx).into()));
+        let mut res = vec![];
+        for id in ids {
+            if uses_dictionary(self.compressions[&id]) {
+                res.push(serialized(&self.serialized, id)?);
+            }
+        }
+
+        match zstd::dict::from_samples(&res, DICTIONARY_LEN) {
+            Ok(dictionary) => {
+                trace!(
+                    "Trained {} byte dictionary from {} assets",
+                    dictionary.len(),
+                    res.len()
+                );
+
+                Ok(Some(dictionary))
+            }
+            Err(err) => {
+                // Assets are no longer being baked, so the observer may be called under the lock
+                let message = format!(
+                    "Unable to train dictionary from {} assets: {err}",
+                    res.len()
+                );
+                warn!("{message}");
+                if let Some(observer) = &self.observer {
+                    observer.event(BakeEvent::Warning { message: &message });
+                }
+
+                self.compression = self.compression.map(Compression::without_dictionary);
+                for compression in self.compressions.values_mut() {
+                    *compression = compression.map(Compression::without_dictionary);
+                }
+
+                Ok(None)
+            }
+        }
+    }
+
+    /// Sets the compression of assets which choose the given compression type.
+    pub fn with_compression_type(
+        &mut self,
+        compression_type: CompressionType,
+        compression: Option<Compression>,
+    ) -> &mut Self {
+        self.compression_types.insert(compression_type, compression);
+        self
+    }
+
     #[allow(dead_code)]
     pub fn with_compression(&mut self, compression: Compression) -> &mut Self {
-        self.compression = Some(compression);
-        self
+        self.with_compression_is(Some(compression))
     }
 
+    /// Sets the compression of the index.
     pub fn with_compression_is(&mut self, compression: Option<Compression>) -> &mut Self {
         self.compression = compression;
         self
     }
 
+    /// Sets whether source files are reported to Cargo as `rerun-if-changed` watches.
+    pub fn with_cargo_watches(&mut self, cargo_watches: bool) -> &mut Self {
+        self.cargo_watches = cargo_watches;
+        self
+    }
+
+    /// Sends the events of this bake to `observer`.
+    pub fn with_observer_is(&mut self, observer: Option<Arc<dyn BakeObserver>>) -> &mut Self {
+        self.observer = observer;
+        self
+    }
+
+    /// Stores assets smaller than `min_compress_size` bytes uncompressed.
+    ///
+    /// Assets which do not become smaller when compressed are always stored uncompressed.
+    pub fn with_min_compress_size(&mut self, min_compress_size: usize) -> &mut Self {
+        self.min_compress_size = min_compress_size;
+        self
+    }
+
+    /// Writes an older format version for readers which have not been updated yet.
+    pub fn with_format_version_is(&mut self, format_version: Option<u16>) -> &mut Self {
+        self.format_version = format_version;
+        self
+    }
+
     pub fn write(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
         let path = path.as_ref();
         {
@@ -135,36 +349,30 @@ impl Writer {
             writer.flush()?;
         }
 
-        let mut reader = File::open(path)?;
-        let len = reader.metadata()?.len();
-        let hash = pak_hash_stream(&mut reader, len)?;
-        let mut writer = OpenOptions::new().append(true).open(path)?;
-        bincode::serde::encode_into_std_write(hash, &mut writer, bincode::config::legacy())
-            .map_err(|_| Error::from(ErrorKind::InvalidData))?;
-        writer.flush()
+        write_hash_trailer(path)
     }
 
-    fn write_data(&mut self, mut writer: impl Write + Seek) -> Result<(), Error> {
-        let mut magic_bytes = [0u8; 20];
-        magic_bytes.copy_from_slice(b"ATTACKGOAT-PAK-V1.0 ");
-
-        // Write a known value so we can identify this file
-        bincode::serde::encode_into_std_write(magic_bytes, &mut writer, bincode::config::legacy())
-            .map_err(|_| Error::from(ErrorKind::InvalidData))?;
-
-        let skip_position = writer.stream_position()?;
-
-        // Write a blank spot that we'll use for the skip header later
-        bincode::serde::encode_into_std_write(0u32, &mut writer, bincode::config::legacy())
-            .map_err(|_| Error::from(ErrorKind::InvalidData))?;
+    /// Writes the pak, followed by its hash trailer, to `writer`, which is read back to hash the
+    /// pak.
+    pub fn write_to(&mut self, mut writer: impl Read + Write + Seek) -> Result<(), Error> {
+        let start = writer.stream_position()?;
+        self.write_data(&mut writer)?;
+        write_stream_hash_trailer(writer, start)
+    }
 
-        // Write the compression we're going to be using, if any
-        bincode::serde::encode_into_std_write(
-            self.compression,
-            &mut writer,
-            bincode::config::legacy(),
-        )
-        .map_err(|_| Error::from(ErrorKind::InvalidData))?;
+    fn write_data(&mut self, mut writer: impl Write + Seek) -> Result<(), Error> {
+        let dictionary = self.train_dictionary()?;
+
+        // Write a known value and format version so we can identify this file; skip is a blank
+        // spot that we'll fill in later
+        let mut header = Header {
+            base: None,
+            compression: self.compression,
+            dictionary,
+            skip: 0,
+            version: self.format_version.unwrap_or(FORMAT_VERSION),
+        };
+        let skip_position = header.write(&mut writer)?;
 
         // Update these items with the refs we created; saving with bincode was very
         // slow when serializing the byte vectors - that is why those are saved raw.
@@ -173,7 +381,14 @@ impl Writer {
             self.data.anims.len(),
             if self.data.anims.len() == 1 { "" } else { "s" }
         );
-        Self::write_refs(self.compression, &mut writer, &mut self.data.anims)?;
+        Self::write_refs(
+            &header,
+            self.min_compress_size,
+            &mut writer,
+            &mut self.data.anims,
+            |idx| serialized(&self.serialized, AnimationId(idx).into()),
+            |idx| self.compressions[&AnimationId(idx).into()],
+        )?;
 
         trace!(
             "Writing {} bitmap{}",
@@ -184,14 +399,28 @@ impl Writer {
                 "s"
             }
         );
-        Self::write_refs(self.compression, &mut writer, &mut self.data.bitmaps)?;
+        Self::write_refs(
+            &header,
+            self.min_compress_size,
+            &mut writer,
+            &mut self.data.bitmaps,
+            |idx| serialized(&self.serialized, BitmapId(idx).into()),
+            |idx| self.compressions[&BitmapId(idx).into()],
+        )?;
 
         trace!(
             "Writing {} blob{}",
             self.data.blobs.len(),
             if self.data.blobs.len() == 1 { "" } else { "s" }
         );
-        Self::write_refs(self.compression, &mut writer, &mut self.data.blobs)?;
+        Self::write_refs(
+            &header,
+            self.min_compress_size,
+            &mut writer,
+            &mut self.data.blobs,
+            |idx| serialized(&self.serialized, BlobId(idx).into()),
+            |idx| self.compressions[&BlobId(idx).into()],
+        )?;
 
         trace!(
             "Writing {} bitmap font{}",
@@ -202,7 +431,14 @@ impl Writer {
                 "s"
             }
         );
-        Self::write_refs(self.compression, &mut writer, &mut self.data.bitmap_fonts)?;
+        Self::write_refs(
+            &header,
+            self.min_compress_size,
+            &mut writer,
+            &mut self.data.bitmap_fonts,
+            |idx| serialized(&self.serialized, BitmapFontId(idx).into()),
+            |idx| self.compressions[&BitmapFontId(idx).into()],
+        )?;
 
         trace!(
             "Writing {} mesh{}",
@@ -213,67 +449,86 @@ impl Writer {
                 "es"
             }
         );
-        Self::write_refs(self.compression, &mut writer, &mut self.data.meshes)?;
+        Self::write_refs(
+            &header,
+            self.min_compress_size,
+            &mut writer,
+            &mut self.data.meshes,
+            |idx| serialized(&self.serialized, MeshId(idx).into()),
+            |idx| self.compressions[&MeshId(idx).into()],
+        )?;
 
         trace!(
             "Writing {} scene{}",
             self.data.scenes.len(),
             if self.data.scenes.len() == 1 { "" } else { "s" }
         );
-        Self::write_refs(self.compression, &mut writer, &mut self.data.scenes)?;
-
-        // Write the data portion and then re-seek to the beginning to write the skip header
-        let skip = writer.stream_position()? as u32;
-        {
-            let mut compressed = if let Some(compressed) = self.compression {
-                compressed.new_writer(&mut writer)
-            } else {
-                Box::new(&mut writer)
-            };
-            bincode::serde::encode_into_std_write(
-                &self.data,
-                &mut compressed,
-                bincode::config::legacy(),
-            )
-            .map_err(|_| Error::from(ErrorKind::InvalidData))?;
-        }
+        Self::write_refs(
+            &header,
+            self.min_compress_size,
+            &mut writer,
+            &mut self.data.scenes,
+            |idx| serialized(&self.serialized, SceneId(idx).into()),
+            |idx| self.compressions[&SceneId(idx).into()],
+        )?;
 
-        writer.seek(SeekFrom::Start(skip_position))?;
-        bincode::serde::encode_into_std_write(skip, &mut writer, bincode::config::legacy())
-            .map_err(|_| Error::from(ErrorKind::InvalidData))?;
+        self.serialized.clear();
 
-        Ok(())
+        // Write the data portion and then re-seek to the beginning to write the skip header
+        format::write_index(&mut header, skip_position, &self.data, &mut writer)
     }
 
-    fn write_refs<T>(
-        compression: Option<Compression>,
+    fn write_refs<'a, T>(
+        header: &Header,
+        min_compress_size: usize,
         mut writer: impl Seek + Write,
         refs: &mut Vec<DataRef<T>>,
-    ) -> Result<(), Error>
-    where
-        T: Serialize,
-    {
+        serialized: impl Fn(usize) -> Result<&'a [u8], Error>,
+        compression: impl Fn(usize) -> Option<Compression>,
+    ) -> Result<(), Error> {
         let mut res = vec![];
-        let mut start = writer.stream_position()? as _;
-
-        for (idx, data) in refs.drain(..).map(|data| data.serialize()).enumerate() {
-            // Write this data, compressed
-            {
-                let data = data?;
-                let mut compressed = if let Some(compressed) = compression {
-                    compressed.new_writer(&mut writer)
-                } else {
-                    Box::new(&mut writer)
-                };
-                compressed.write_all(&data)?;
+        let mut start = header.check_offset(writer.stream_position()?)?;
+
+        for idx in 0..refs.len() {
+            // Compress this data so the stored bytes may be checksummed before writing
+            let data = serialized(idx)?;
+            let mut compression = compression(idx);
+
+            // Format versions which record the compression of each asset may store small assets,
+            // and those which do not become smaller, uncompressed
+            if header.records_asset_compression() && data.len() < min_compress_size {
+                compression = None;
             }
 
+            let data = if let Some(compressed) = compression {
+                let mut buf = vec![];
+                {
+                    let mut compressed =
+                        compressed.new_writer(&mut buf, header.dictionary.as_deref())?;
+                    compressed.write_all(data)?;
+                }
+
+                if header.records_asset_compression() && buf.len() >= data.len() {
+                    compression = None;
+                    Cow::Borrowed(data)
+                } else {
+                    Cow::Owned(buf)
+                }
+            } else {
+                Cow::Borrowed(data)
+            };
+            writer.write_all(&data)?;
+
             // Push a ref
-            let end = writer.stream_position()? as _;
+            let end = header.check_offset(writer.stream_position()?)?;
+            let checksum = crc32fast::hash(&data);
 
-            trace!("Index {idx} = {} bytes ({start}..{end})", end - start);
+            trace!(
+                "Index {idx} = {} bytes ({start}..{end}, crc {checksum:08x})",
+                end - start
+            );
 
-            res.push(DataRef::<T>::Ref(start..end));
+            res.push(DataRef::<T>::Compressed(start..end, checksum, compression));
             start = end;
         }
 
@@ -282,3 +537,222 @@ impl Writer {
         Ok(())
     }
 }
+
+/// Returns the serialized bytes of a pushed asset.
+fn serialized(serialized: &HashMap<Id, Option<Vec<u8>>>, id: Id) -> Result<&[u8], Error> {
+    serialized
+        .get(&id)
+        .and_then(Option::as_deref)
+        .ok_or_else(|| Error::from(ErrorKind::InvalidData))
+}
+
+impl Default for Writer {
+    fn default() -> Self {
+        Self {
+            baked_len: 0,
+            cache: None,
+            cargo_watches: true,
+            compression: None,
+            compression_types: Default::default(),
+            compressions: Default::default(),
+            ctx: Default::default(),
+            data: Default::default(),
+            format_version: None,
+            min_compress_size: 0,
+            observer: None,
+            serialized: Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::{Pak, PakBuf},
+        std::io::{Cursor, ErrorKind, SeekFrom},
+    };
+
+    /// Discards written bytes while tracking the position, as if writing past `start`; reads
+    /// return zeros.
+    struct OffsetSink {
+        len: u64,
+        pos: u64,
+    }
+
+    impl Read for OffsetSink {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            let len = buf.len().min(self.len.saturating_sub(self.pos) as usize);
+            buf[..len].fill(0);
+            self.pos += len as u64;
+
+            Ok(len)
+        }
+    }
+
+    impl Seek for OffsetSink {
+        fn seek(&mut self, pos: SeekFrom) -> Result<u64, Error> {
+            self.pos = match pos {
+                SeekFrom::Start(pos) => pos,
+                SeekFrom::End(offset) => self.len.saturating_add_signed(offset),
+                SeekFrom::Current(offset) => self.pos.saturating_add_signed(offset),
+            };
+
+            Ok(self.pos)
+        }
+    }
+
+    impl Write for OffsetSink {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            self.pos += buf.len() as u64;
+            self.len = self.len.max(self.pos);
+
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    fn blob_writer(format_version: Option<u16>) -> Writer {
+        let mut writer = Writer::default();
+        writer.push_blob(b"blob".to_vec(), Some("blob".to_owned()), None);
+        writer.with_format_version_is(format_version);
+        writer
+    }
+
+    /// Writes `writer` to memory and opens the result.
+    fn read_back(writer: &mut Writer) -> PakBuf {
+        let mut buf = Cursor::new(vec![]);
+        writer.write_to(&mut buf).unwrap();
+
+        PakBuf::from_bytes(buf.into_inner()).unwrap()
+    }
+
+    fn stored_compression(pak: &mut PakBuf, key: &str) -> Option<Compression> {
+        pak.report()
+            .unwrap()
+            .assets
+            .into_iter()
+            .find(|asset| asset.key == key)
+            .unwrap()
+            .compression
+    }
+
+    #[test]
+    fn incompressible_assets_are_stored_uncompressed() {
+        // A xorshift sequence does not compress
+        let mut state = 0x2545_f491_u32;
+        let noise = (0..4096)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect::<Vec<_>>();
+
+        let mut writer = Writer::default();
+        writer.with_compression(Compression::Snap);
+        writer.push_blob(
+            noise.clone(),
+            Some("noise".to_owned()),
+            Some(Compression::Snap),
+        );
+        writer.push_blob(
+            b"text".repeat(1024),
+            Some("text".to_owned()),
+            Some(Compression::Snap),
+        );
+        let mut pak = read_back(&mut writer);
+
+        assert_eq!(stored_compression(&mut pak, "noise"), None);
+        assert_eq!(
+            stored_compression(&mut pak, "text"),
+            Some(Compression::Snap)
+        );
+        assert_eq!(pak.read_blob("noise").unwrap(), noise);
+        assert_eq!(pak.read_blob("text").unwrap(), b"text".repeat(1024));
+    }
+
+    #[test]
+    fn small_assets_are_stored_uncompressed() {
+        let mut writer = Writer::default();
+        writer
+            .with_compression(Compression::Snap)
+            .with_min_compress_size(1024);
+        writer.push_blob(
+            b"text".repeat(128),
+            Some("small".to_owned()),
+            Some(Compression::Snap),
+        );
+        writer.push_blob(
+            b"text".repeat(1024),
+            Some("large".to_owned()),
+            Some(Compression::Snap),
+        );
+        let mut pak = read_back(&mut writer);
+
+        assert_eq!(stored_compression(&mut pak, "small"), None);
+        assert_eq!(
+            stored_compression(&mut pak, "large"),
+            Some(Compression::Snap)
+        );
+        assert_eq!(pak.read_blob("small").unwrap(), b"text".repeat(128));
+
+        // Format versions which cannot record the choice compress every asset
+        let mut writer = Writer::default();
+        writer
+            .with_compression(Compression::Snap)
+            .with_format_version_is(Some(3))
+            .with_min_compress_size(1024);
+        writer.push_blob(
+            b"text".repeat(128),
+            Some("small".to_owned()),
+            Some(Compression::Snap),
+        );
+        let mut pak = read_back(&mut writer);
+
+        assert_eq!(
+            stored_compression(&mut pak, "small"),
+            Some(Compression::Snap)
+        );
+    }
+
+    #[test]
+    fn written_paks_end_with_their_hash() {
+        let pak = read_back(&mut blob_writer(None));
+
+        assert!(pak.validate_hash().unwrap());
+    }
+
+    #[test]
+    fn older_format_versions_round_trip() {
+        for format_version in [Some(2), None] {
+            let mut pak = read_back(&mut blob_writer(format_version));
+
+            assert_eq!(pak.read_blob("blob").unwrap(), b"blob");
+        }
+    }
+
+    #[test]
+    fn offsets_past_4_gib_require_format_version_3() {
+        let start = u32::MAX as u64 - 16;
+
+        let err = blob_writer(Some(2))
+            .write_to(OffsetSink {
+                len: start,
+                pos: start,
+            })
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::FileTooLarge);
+
+        blob_writer(None)
+            .write_to(OffsetSink {
+                len: start,
+                pos: start,
+            })
+            .unwrap();
+    }
+}
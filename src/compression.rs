@@ -2,14 +2,14 @@ use {
     brotli::Decompressor,
     serde::{Deserialize, Serialize},
     snap::read::FrameDecoder,
-    std::io::Read,
+    std::io::{BufReader, Error, ErrorKind, Read},
 };
 
 #[cfg(feature = "bake")]
 use {brotli::CompressorWriter, snap::write::FrameEncoder, std::io::Write};
 
 /// Describes Brotli-based compression.
-#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub struct BrotliParams {
     /// Buffer size.
     pub buffer_size: usize,
@@ -29,23 +29,65 @@ impl Default for BrotliParams {
     }
 }
 
-#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+/// Describes how the stored assets and index of a `.pak` file are compressed.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub enum Compression {
+    /// Brotli compression using the given parameters.
     Brotli(BrotliParams),
+    /// Snappy frame compression.
     Snap,
+    /// Zstandard compression using the given level.
+    Zstd {
+        /// Compression level, from `1` (fastest) to `22` (smallest); `0` selects the default.
+        level: i32,
+        /// When set, compresses using the dictionary stored in the header of the pak.
+        dictionary: bool,
+    },
+    /// LZ4 frame compression.
+    Lz4,
 }
 
 impl Compression {
-    pub fn new_reader<'a>(self, reader: impl Read + 'a) -> Box<dyn Read + 'a> {
-        match self {
+    /// The Zstandard level used when none is given.
+    pub const DEFAULT_ZSTD_LEVEL: i32 = zstd::DEFAULT_COMPRESSION_LEVEL;
+
+    /// Returns the dictionary to use with this compression, if it requires one.
+    fn dictionary(self, dictionary: Option<&[u8]>) -> Result<Option<&[u8]>, Error> {
+        if self.uses_dictionary() {
+            dictionary
+                .map(Some)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "missing compression dictionary"))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub(crate) fn new_reader<'a>(
+        self,
+        reader: impl Read + 'a,
+        dictionary: Option<&[u8]>,
+    ) -> Result<Box<dyn Read + 'a>, Error> {
+        Ok(match self {
             Compression::Brotli(b) => Box::new(Decompressor::new(reader, b.buffer_size)),
             Compression::Snap => Box::new(FrameDecoder::new(reader)),
-        }
+            Compression::Zstd { .. } => match self.dictionary(dictionary)? {
+                Some(dictionary) => Box::new(zstd::Decoder::with_dictionary(
+                    BufReader::new(reader),
+                    dictionary,
+                )?),
+                None => Box::new(zstd::Decoder::new(reader)?),
+            },
+            Compression::Lz4 => Box::new(lz4_flex::frame::FrameDecoder::new(reader)),
+        })
     }
 
     #[cfg(feature = "bake")]
-    pub fn new_writer<'a>(self, writer: impl Write + 'a) -> Box<dyn Write + 'a> {
-        match self {
+    pub(crate) fn new_writer<'a>(
+        self,
+        writer: impl Write + 'a,
+        dictionary: Option<&[u8]>,
+    ) -> Result<Box<dyn Write + 'a>, Error> {
+        Ok(match self {
             Compression::Brotli(b) => Box::new(CompressorWriter::new(
                 writer,
                 b.buffer_size,
@@ -53,6 +95,36 @@ impl Compression {
                 b.window_size,
             )),
             Compression::Snap => Box::new(FrameEncoder::new(writer)),
+            Compression::Zstd { level, .. } => match self.dictionary(dictionary)? {
+                Some(dictionary) => Box::new(
+                    zstd::Encoder::with_dictionary(writer, level, dictionary)?.auto_finish(),
+                ),
+                None => Box::new(zstd::Encoder::new(writer, level)?.auto_finish()),
+            },
+            Compression::Lz4 => Box::new(lz4_flex::frame::FrameEncoder::new(writer).auto_finish()),
+        })
+    }
+
+    /// Returns `true` if this compression requires the dictionary stored in the header of the pak.
+    pub fn uses_dictionary(self) -> bool {
+        matches!(
+            self,
+            Compression::Zstd {
+                dictionary: true,
+                ..
+            }
+        )
+    }
+
+    /// Returns this compression without the dictionary stored in the header of the pak.
+    #[cfg(feature = "bake")]
+    pub(crate) fn without_dictionary(self) -> Self {
+        match self {
+            Compression::Zstd { level, .. } => Compression::Zstd {
+                level,
+                dictionary: false,
+            },
+            compression => compression,
         }
     }
 }
@@ -77,13 +149,14 @@ mod test {
         let mut compressed = Vec::new();
 
         {
-            let mut writer = compression.new_writer(&mut compressed);
+            let mut writer = compression.new_writer(&mut compressed, None).unwrap();
             writer.write_all(&input).unwrap();
         }
 
         let mut output = Vec::new();
         compression
-            .new_reader(compressed.as_slice())
+            .new_reader(compressed.as_slice(), None)
+            .unwrap()
             .read_to_end(&mut output)
             .unwrap();
 
@@ -97,16 +170,90 @@ mod test {
         let mut compressed = Vec::new();
 
         {
-            let mut writer = compression.new_writer(&mut compressed);
+            let mut writer = compression.new_writer(&mut compressed, None).unwrap();
+            writer.write_all(&input).unwrap();
+        }
+
+        let mut output = Vec::new();
+        compression
+            .new_reader(compressed.as_slice(), None)
+            .unwrap()
+            .read_to_end(&mut output)
+            .unwrap();
+
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn zstd_round_trip() {
+        let compression = Compression::Zstd {
+            level: Compression::DEFAULT_ZSTD_LEVEL,
+            dictionary: false,
+        };
+        let input = b"lossless compression round trip".repeat(32);
+        let mut compressed = Vec::new();
+
+        {
+            let mut writer = compression.new_writer(&mut compressed, None).unwrap();
+            writer.write_all(&input).unwrap();
+        }
+
+        let mut output = Vec::new();
+        compression
+            .new_reader(compressed.as_slice(), None)
+            .unwrap()
+            .read_to_end(&mut output)
+            .unwrap();
+
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn lz4_round_trip() {
+        let compression = Compression::Lz4;
+        let input = b"lossless compression round trip".repeat(32);
+        let mut compressed = Vec::new();
+
+        {
+            let mut writer = compression.new_writer(&mut compressed, None).unwrap();
+            writer.write_all(&input).unwrap();
+        }
+
+        let mut output = Vec::new();
+        compression
+            .new_reader(compressed.as_slice(), None)
+            .unwrap()
+            .read_to_end(&mut output)
+            .unwrap();
+
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn zstd_dictionary_round_trip() {
+        let compression = Compression::Zstd {
+            level: Compression::DEFAULT_ZSTD_LEVEL,
+            dictionary: true,
+        };
+        let dictionary = b"lossless compression dictionary".repeat(8);
+        let input = b"lossless compression round trip".repeat(32);
+        let mut compressed = Vec::new();
+
+        {
+            let mut writer = compression
+                .new_writer(&mut compressed, Some(&dictionary))
+                .unwrap();
             writer.write_all(&input).unwrap();
         }
 
         let mut output = Vec::new();
         compression
-            .new_reader(compressed.as_slice())
+            .new_reader(compressed.as_slice(), Some(&dictionary))
+            .unwrap()
             .read_to_end(&mut output)
             .unwrap();
 
         assert_eq!(output, input);
+        assert!(compression.new_reader(compressed.as_slice(), None).is_err());
     }
 }
@@ -0,0 +1,222 @@
+use {
+    super::AssetKind,
+    bincode::error::DecodeError,
+    std::{
+        error::Error,
+        fmt::{Display, Formatter},
+        io::{self, ErrorKind},
+    },
+};
+
+/// Describes why a `.pak` file or one of its assets could not be read.
+///
+/// `PakError` converts into `std::io::Error`, keeping the `ErrorKind` returned by `PakError::kind`,
+/// so it may be used with `?` in functions returning `std::io::Result`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PakError {
+    /// The stream does not start with the magic bytes of a `.pak` file.
+    BadMagic,
+
+    /// A patch pak was layered over a different base pak than the one it was made against.
+    BaseMismatch {
+        /// Hash trailer of the base pak recorded in the patch header.
+        expected: u64,
+        /// Hash trailer of the base pak which was given.
+        actual: u64,
+    },
+
+    /// The stored bytes of an asset do not match the checksum recorded when it was baked.
+    ChecksumMismatch {
+        /// Checksum recorded in the pak index.
+        expected: u32,
+        /// Checksum of the bytes which were read.
+        actual: u32,
+    },
+
+    /// Bincode was unable to decode the header or an asset.
+    Decode(DecodeError),
+
+    /// The decompressor failed while reading the header or an asset.
+    Decompression(io::Error),
+
+    /// The given ID does not refer to an asset of this pak.
+    InvalidId {
+        /// Kind of asset which was requested.
+        kind: AssetKind,
+        /// Index of the requested asset.
+        id: usize,
+    },
+
+    /// An underlying I/O operation failed.
+    Io(io::Error),
+
+    /// The given key does not exist in this pak.
+    KeyNotFound {
+        /// The requested key.
+        key: String,
+    },
+
+    /// A pak layered as a patch records no base pak, so it is not a patch.
+    MissingPatchBase,
+
+    /// Decoded data was followed by unexpected bytes.
+    TrailingBytes,
+
+    /// A stored byte range is inverted or extends past the end of the pak.
+    TruncatedRange,
+
+    /// The asset is stored in the base pak of a patch, which must be read using `PatchedPak`.
+    UnexpectedBaseData,
+
+    /// The asset is stored inline instead of as a byte range; only baking produces these.
+    UnexpectedInlineData,
+
+    /// The stream is a `.pak` file written using a format version this crate cannot read.
+    UnsupportedVersion {
+        /// Format version found in the header.
+        version: u16,
+    },
+
+    /// The given key exists but refers to a different kind of asset.
+    WrongAssetType {
+        /// The requested key.
+        key: String,
+        /// Kind of asset which was requested.
+        expected: AssetKind,
+        /// Kind of asset stored under `key`.
+        found: AssetKind,
+    },
+}
+
+impl PakError {
+    /// Describes a key lookup which found no asset of the `expected` kind.
+    pub(crate) fn missing_key(key: &str, expected: AssetKind, found: Option<AssetKind>) -> Self {
+        let key = key.to_owned();
+
+        match found {
+            Some(found) => Self::WrongAssetType {
+                key,
+                expected,
+                found,
+            },
+            None => Self::KeyNotFound { key },
+        }
+    }
+
+    /// Returns the `std::io::ErrorKind` this error is reported as when converted to `io::Error`.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(err) => err.kind(),
+            Self::BaseMismatch { .. }
+            | Self::InvalidId { .. }
+            | Self::KeyNotFound { .. }
+            | Self::MissingPatchBase
+            | Self::UnexpectedBaseData
+            | Self::UnexpectedInlineData
+            | Self::WrongAssetType { .. } => ErrorKind::InvalidInput,
+            Self::BadMagic
+            | Self::ChecksumMismatch { .. }
+            | Self::Decode(_)
+            | Self::Decompression(_)
+            | Self::TrailingBytes
+            | Self::TruncatedRange
+            | Self::UnsupportedVersion { .. } => ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl Display for PakError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadMagic => f.write_str("unsupported magic bytes"),
+            Self::BaseMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "patch base mismatch: expected {expected:016x}, found {actual:016x}"
+                )
+            }
+            Self::ChecksumMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "checksum mismatch: expected {expected:08x}, found {actual:08x}"
+                )
+            }
+            Self::Decode(err) => write!(f, "unable to decode data: {err}"),
+            Self::Decompression(err) => write!(f, "unable to decompress data: {err}"),
+            Self::InvalidId { kind, id } => write!(f, "invalid {kind} id {id}"),
+            Self::Io(err) => err.fmt(f),
+            Self::KeyNotFound { key } => write!(f, "key `{key}` not found"),
+            Self::MissingPatchBase => f.write_str("patch does not record a base pak"),
+            Self::TrailingBytes => f.write_str("trailing bytes after decoded data"),
+            Self::TruncatedRange => f.write_str("data range is invalid or truncated"),
+            Self::UnexpectedBaseData => {
+                f.write_str("expected a data range but found one stored in a base pak")
+            }
+            Self::UnexpectedInlineData => {
+                f.write_str("expected a data range but found inline data")
+            }
+            Self::UnsupportedVersion { version } => {
+                write!(f, "unsupported pak format version {version}")
+            }
+            Self::WrongAssetType {
+                key,
+                expected,
+                found,
+            } => write!(f, "key `{key}` is a {found}, not a {expected}"),
+        }
+    }
+}
+
+impl Error for PakError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Decode(err) => Some(err),
+            Self::Decompression(err) | Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeError> for PakError {
+    fn from(err: DecodeError) -> Self {
+        Self::Decode(err)
+    }
+}
+
+impl From<io::Error> for PakError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<PakError> for io::Error {
+    fn from(err: PakError) -> Self {
+        match err {
+            PakError::Io(err) => err,
+            err => io::Error::new(err.kind(), err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn converts_into_io_error() {
+        let err = io::Error::from(PakError::KeyNotFound {
+            key: "missing".to_owned(),
+        });
+
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(matches!(
+            err.get_ref().and_then(|err| err.downcast_ref::<PakError>()),
+            Some(PakError::KeyNotFound { key }) if key == "missing"
+        ));
+
+        let err = io::Error::from(PakError::Io(io::Error::from(ErrorKind::UnexpectedEof)));
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        assert!(err.get_ref().is_none());
+    }
+}
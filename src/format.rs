@@ -0,0 +1,593 @@
+use {
+    super::{
+        Data, DataRef, Id, MaterialInfo, PAK_HASH_LEN, PakError, compression::Compression,
+        decompression_error,
+    },
+    bincode::error::DecodeError,
+    serde::{Deserialize, de::DeserializeOwned},
+    serde_bytes::ByteBuf,
+    std::{collections::BTreeMap, io::Read, ops::Range},
+};
+
+#[cfg(feature = "bake")]
+use {
+    serde::Serialize,
+    serde_bytes::Bytes,
+    std::io::{Error, ErrorKind, Seek, SeekFrom, Write},
+};
+
+/// Magic bytes of paks written before the header carried an explicit format version.
+const MAGIC_V1: [u8; 20] = *b"ATTACKGOAT-PAK-V1.0 ";
+
+/// Magic bytes of versioned paks, which are followed by a `u16` format version.
+const MAGIC: [u8; 20] = *b"ATTACKGOAT-PAK\0\0\0\0\0\0";
+
+/// The format version written by this crate and the newest version it is able to read.
+pub const FORMAT_VERSION: u16 = 4;
+
+/// The first format version which stores byte ranges and `skip` as 64-bit offsets.
+const WIDE_OFFSETS_VERSION: u16 = 3;
+
+/// The first format version which records the compression of each stored asset.
+#[cfg(feature = "bake")]
+const ASSET_COMPRESSION_VERSION: u16 = 4;
+
+fn decode<T>(stream: &mut impl Read) -> Result<T, DecodeError>
+where
+    T: DeserializeOwned,
+{
+    bincode::serde::decode_from_std_read(stream, bincode::config::legacy())
+}
+
+#[cfg(feature = "bake")]
+fn encode(value: impl Serialize, writer: &mut impl Write) -> Result<(), Error> {
+    bincode::serde::encode_into_std_write(value, writer, bincode::config::legacy())
+        .map(|_| ())
+        .map_err(|_| Error::from(ErrorKind::InvalidData))
+}
+
+/// The fixed fields found at the start of every pak.
+#[derive(Clone, Debug)]
+pub(crate) struct Header {
+    /// Hash trailer of the pak a patch pak was made against; `None` for every other pak.
+    pub base: Option<u64>,
+    pub compression: Option<Compression>,
+
+    /// Zstandard dictionary trained across the stored assets, used by compression which sets
+    /// `dictionary`.
+    pub dictionary: Option<Vec<u8>>,
+
+    pub skip: u64,
+    pub version: u16,
+}
+
+impl Header {
+    /// Returns the length of the main data given the pak length, excluding the hash trailer.
+    pub fn data_len(&self, stream_end: u64) -> Result<u64, PakError> {
+        stream_end
+            .checked_sub(PAK_HASH_LEN as u64)
+            .and_then(|header_end| header_end.checked_sub(self.skip))
+            .ok_or(PakError::TruncatedRange)
+    }
+
+    /// Decodes the main data of a pak from a reader positioned at (and limited to) the data.
+    pub fn read_data(&self, data: impl Read) -> Result<Data, PakError> {
+        let mut data: Box<dyn Read + '_> = if let Some(compressed) = self.compression {
+            compressed
+                .new_reader(data, self.dictionary.as_deref())
+                .map_err(PakError::Decompression)?
+        } else {
+            Box::new(data)
+        };
+
+        if self.version == 1 {
+            let data: DataV1 = decode(&mut data).map_err(|err| {
+                if self.compression.is_some() {
+                    decompression_error(err)
+                } else {
+                    PakError::Decode(err)
+                }
+            })?;
+
+            return Ok(Data {
+                ids: data.ids,
+                materials: data.materials,
+                anims: widen_refs(data.anims)?,
+                bitmap_fonts: widen_refs(data.bitmap_fonts)?,
+                bitmaps: widen_refs(data.bitmaps)?,
+                blobs: widen_refs(data.blobs)?,
+                meshes: widen_refs(data.meshes)?,
+                scenes: widen_refs(data.scenes)?,
+            });
+        }
+
+        let mut buf = vec![];
+        data.read_to_end(&mut buf).map_err(|err| {
+            if self.compression.is_some() {
+                PakError::Decompression(err)
+            } else {
+                PakError::Io(err)
+            }
+        })?;
+
+        let mut fields = RecordReader::new(&buf);
+
+        Ok(Data {
+            ids: fields.read()?,
+            materials: fields
+                .read::<Vec<Vec<u8>>>()?
+                .iter()
+                .map(|record| read_material(record))
+                .collect::<Result<_, _>>()?,
+            anims: self.read_refs(&mut fields)?,
+            bitmap_fonts: self.read_refs(&mut fields)?,
+            bitmaps: self.read_refs(&mut fields)?,
+            blobs: self.read_refs(&mut fields)?,
+            meshes: self.read_refs(&mut fields)?,
+            scenes: self.read_refs(&mut fields)?,
+        })
+    }
+
+    fn read_refs<T>(&self, fields: &mut RecordReader) -> Result<Vec<DataRef<T>>, PakError>
+    where
+        T: DeserializeOwned,
+    {
+        if self.version < WIDE_OFFSETS_VERSION {
+            widen_refs(fields.read()?)
+        } else {
+            fields.read()
+        }
+    }
+
+    /// Returns the encoded length of a versioned header given at least its first bytes, or `None`
+    /// if `prefix` is too short to tell or belongs to a pak written before format version `2`.
+    ///
+    /// Headers which store a dictionary may be longer than readers expect.
+    #[cfg(feature = "async")]
+    pub fn encoded_len(prefix: &[u8]) -> Option<u64> {
+        const RECORD_LEN_END: usize = MAGIC.len() + size_of::<u16>() + size_of::<u32>();
+
+        if prefix.len() < RECORD_LEN_END || prefix[..MAGIC.len()] != MAGIC {
+            return None;
+        }
+
+        let record_len = prefix[RECORD_LEN_END - size_of::<u32>()..RECORD_LEN_END]
+            .try_into()
+            .map(u32::from_le_bytes)
+            .ok()?;
+
+        Some(RECORD_LEN_END as u64 + record_len as u64)
+    }
+
+    /// Reads the magic bytes, format version, skip length and compression of a pak.
+    pub fn read(stream: &mut impl Read) -> Result<Self, PakError> {
+        let magic: [u8; 20] = decode(stream)?;
+
+        if magic == MAGIC_V1 {
+            // Read the number of bytes we must 'skip' in order to read the main data
+            let skip = decode::<u32>(stream)? as _;
+            let compression = decode(stream)?;
+
+            return Ok(Self {
+                base: None,
+                compression,
+                dictionary: None,
+                skip,
+                version: 1,
+            });
+        } else if magic != MAGIC {
+            return Err(PakError::BadMagic);
+        }
+
+        let version: u16 = decode(stream)?;
+        if !(2..=FORMAT_VERSION).contains(&version) {
+            return Err(PakError::UnsupportedVersion { version });
+        }
+
+        let record_len: u32 = decode(stream)?;
+        let mut record = vec![];
+        stream.take(record_len as _).read_to_end(&mut record)?;
+        if record.len() != record_len as usize {
+            return Err(PakError::TruncatedRange);
+        }
+
+        let mut fields = RecordReader::new(&record);
+
+        let skip = if version < WIDE_OFFSETS_VERSION {
+            fields.read::<u32>()? as _
+        } else {
+            fields.read()?
+        };
+
+        Ok(Self {
+            skip,
+            compression: fields.read()?,
+            base: fields.read_or_default()?,
+            dictionary: fields
+                .read_or_default::<Option<ByteBuf>>()?
+                .map(ByteBuf::into_vec),
+            version,
+        })
+    }
+
+    /// Writes a versioned header, returning the position of `skip` so it may be patched later.
+    #[cfg(feature = "bake")]
+    pub fn write(&self, mut writer: impl Write + Seek) -> Result<u64, Error> {
+        let mut record = vec![];
+        self.write_skip(&mut record)?;
+        encode(self.compression, &mut record)?;
+        encode(self.base, &mut record)?;
+        encode(self.dictionary.as_deref().map(Bytes::new), &mut record)?;
+
+        encode(MAGIC, &mut writer)?;
+        encode(self.version, &mut writer)?;
+        encode(record.len() as u32, &mut writer)?;
+
+        // Skip is the first field of the record
+        let skip_position = writer.stream_position()?;
+        writer.write_all(&record)?;
+
+        Ok(skip_position)
+    }
+
+    /// Writes `skip` using the width of this format version.
+    #[cfg(feature = "bake")]
+    pub fn write_skip(&self, mut writer: impl Write) -> Result<(), Error> {
+        if self.version < WIDE_OFFSETS_VERSION {
+            encode(narrow_offset(self.skip)?, &mut writer)
+        } else {
+            encode(self.skip, &mut writer)
+        }
+    }
+
+    /// Returns `true` if this format version records the compression of each stored asset.
+    #[cfg(feature = "bake")]
+    pub fn records_asset_compression(&self) -> bool {
+        self.version >= ASSET_COMPRESSION_VERSION
+    }
+
+    /// Returns an error if `offset` cannot be stored by this format version.
+    #[cfg(feature = "bake")]
+    pub fn check_offset(&self, offset: u64) -> Result<u64, Error> {
+        if self.version < WIDE_OFFSETS_VERSION {
+            narrow_offset(offset)?;
+        }
+
+        Ok(offset)
+    }
+}
+
+/// The index of format version 1, which was a single bincode-encoded value.
+#[derive(Deserialize)]
+struct DataV1 {
+    ids: BTreeMap<String, Id>,
+    materials: Vec<MaterialInfo>,
+    anims: Vec<NarrowDataRef>,
+    bitmap_fonts: Vec<NarrowDataRef>,
+    bitmaps: Vec<NarrowDataRef>,
+    blobs: Vec<NarrowDataRef>,
+    meshes: Vec<NarrowDataRef>,
+    scenes: Vec<NarrowDataRef>,
+}
+
+/// A `DataRef` as stored by format versions before 64-bit offsets; inline data is never stored.
+#[derive(Deserialize)]
+#[cfg_attr(feature = "bake", derive(serde::Serialize))]
+enum NarrowDataRef {
+    Data(()),
+    Ref(Range<u32>),
+    Checked(Range<u32>, u32),
+}
+
+fn widen_refs<T>(refs: Vec<NarrowDataRef>) -> Result<Vec<DataRef<T>>, PakError> {
+    refs.into_iter()
+        .map(|data| match data {
+            NarrowDataRef::Data(()) => Err(PakError::UnexpectedInlineData),
+            NarrowDataRef::Ref(range) => Ok(DataRef::Ref(range.start as _..range.end as _)),
+            NarrowDataRef::Checked(range, checksum) => {
+                Ok(DataRef::Checked(range.start as _..range.end as _, checksum))
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "bake")]
+fn narrow_offset(offset: u64) -> Result<u32, Error> {
+    u32::try_from(offset).map_err(|_| {
+        Error::new(
+            ErrorKind::FileTooLarge,
+            format!(
+                "pak offset {offset} exceeds the 4 GiB limit of format versions before \
+                {WIDE_OFFSETS_VERSION}"
+            ),
+        )
+    })
+}
+
+#[cfg(feature = "bake")]
+fn narrow_refs<T>(refs: &[DataRef<T>]) -> Result<Vec<NarrowDataRef>, Error> {
+    refs.iter()
+        .map(|data| match data {
+            DataRef::Ref(range) => Ok(NarrowDataRef::Ref(
+                narrow_offset(range.start)?..narrow_offset(range.end)?,
+            )),
+            DataRef::Checked(range, checksum) => Ok(NarrowDataRef::Checked(
+                narrow_offset(range.start)?..narrow_offset(range.end)?,
+                *checksum,
+            )),
+            _ => Err(Error::from(ErrorKind::InvalidData)),
+        })
+        .collect()
+}
+
+/// Replaces refs which record their own compression with the refs understood by format versions
+/// before `ASSET_COMPRESSION_VERSION`, which requires every asset to use the pak compression.
+#[cfg(feature = "bake")]
+fn uniform_refs<T>(
+    refs: &[DataRef<T>],
+    compression: Option<Compression>,
+) -> Result<Vec<DataRef<T>>, Error> {
+    let check = |asset_compression| {
+        if asset_compression == compression {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "per-asset compression requires format version {ASSET_COMPRESSION_VERSION} \
+                    or later"
+                ),
+            ))
+        }
+    };
+
+    refs.iter()
+        .map(|data| match data {
+            DataRef::Ref(range) => Ok(DataRef::Ref(range.clone())),
+            DataRef::Checked(range, checksum) => Ok(DataRef::Checked(range.clone(), *checksum)),
+            DataRef::Base(range, checksum) => Ok(DataRef::Base(range.clone(), *checksum)),
+            DataRef::Compressed(range, checksum, asset_compression) => {
+                check(*asset_compression)?;
+                Ok(DataRef::Checked(range.clone(), *checksum))
+            }
+            DataRef::BaseCompressed(range, checksum, asset_compression) => {
+                check(*asset_compression)?;
+                Ok(DataRef::Base(range.clone(), *checksum))
+            }
+            DataRef::Data(_) => Err(Error::from(ErrorKind::InvalidData)),
+        })
+        .collect()
+}
+
+/// Reads the fields of a record: a run of bincode-encoded values which may grow over time.
+///
+/// Records written by newer versions of this crate may contain trailing fields this reader does
+/// not know about; these are ignored. Fields which were appended to a record after it was first
+/// written are read with `read_or_default` so that older records remain readable.
+pub(crate) struct RecordReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> RecordReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Reads a field which every version of this record contains.
+    pub fn read<T>(&mut self) -> Result<T, PakError>
+    where
+        T: DeserializeOwned,
+    {
+        let (value, consumed) =
+            bincode::serde::decode_from_slice(self.data, bincode::config::legacy())?;
+        self.data = &self.data[consumed..];
+
+        Ok(value)
+    }
+
+    /// Reads a field which was appended to this record, defaulting it for older records.
+    pub fn read_or_default<T>(&mut self) -> Result<T, PakError>
+    where
+        T: Default + DeserializeOwned,
+    {
+        if self.data.is_empty() {
+            Ok(T::default())
+        } else {
+            self.read()
+        }
+    }
+}
+
+fn read_material(record: &[u8]) -> Result<MaterialInfo, PakError> {
+    let mut fields = RecordReader::new(record);
+
+    Ok(MaterialInfo {
+        alpha_test: fields.read()?,
+        color: fields.read()?,
+        emissive: fields.read()?,
+        normal: fields.read()?,
+        params: fields.read()?,
+        params_used: fields.read()?,
+    })
+}
+
+#[cfg(feature = "bake")]
+fn write_material(material: &MaterialInfo) -> Result<ByteBuf, Error> {
+    let mut record = vec![];
+    encode(material.alpha_test, &mut record)?;
+    encode(material.color, &mut record)?;
+    encode(material.emissive, &mut record)?;
+    encode(material.normal, &mut record)?;
+    encode(material.params, &mut record)?;
+    encode(material.params_used, &mut record)?;
+
+    Ok(ByteBuf::from(record))
+}
+
+/// Writes the main data of a pak using the layout of the format version of `header`.
+#[cfg(feature = "bake")]
+fn write_data(data: &Data, header: &Header, mut writer: impl Write) -> Result<(), Error> {
+    fn write_refs<T>(
+        refs: &[DataRef<T>],
+        header: &Header,
+        writer: &mut impl Write,
+    ) -> Result<(), Error>
+    where
+        T: Serialize,
+    {
+        if header.records_asset_compression() {
+            return encode(refs, writer);
+        }
+
+        let refs = uniform_refs(refs, header.compression)?;
+
+        if header.version < WIDE_OFFSETS_VERSION {
+            encode(narrow_refs(&refs)?, writer)
+        } else {
+            encode(refs, writer)
+        }
+    }
+
+    encode(&data.ids, &mut writer)?;
+    encode(
+        data.materials
+            .iter()
+            .map(write_material)
+            .collect::<Result<Vec<_>, _>>()?,
+        &mut writer,
+    )?;
+    write_refs(&data.anims, header, &mut writer)?;
+    write_refs(&data.bitmap_fonts, header, &mut writer)?;
+    write_refs(&data.bitmaps, header, &mut writer)?;
+    write_refs(&data.blobs, header, &mut writer)?;
+    write_refs(&data.meshes, header, &mut writer)?;
+    write_refs(&data.scenes, header, &mut writer)
+}
+
+/// Writes the main data of a pak after its stored bytes and then fills in `skip` of the header,
+/// leaving `writer` at the end of the main data.
+#[cfg(feature = "bake")]
+pub(crate) fn write_index(
+    header: &mut Header,
+    skip_position: u64,
+    data: &Data,
+    mut writer: impl Write + Seek,
+) -> Result<(), Error> {
+    header.skip = header.check_offset(writer.stream_position()?)?;
+    {
+        let mut compressed = if let Some(compressed) = header.compression {
+            compressed.new_writer(&mut writer, header.dictionary.as_deref())?
+        } else {
+            Box::new(&mut writer)
+        };
+        write_data(data, header, &mut compressed)?;
+    }
+
+    let end = writer.stream_position()?;
+    writer.seek(SeekFrom::Start(skip_position))?;
+    header.write_skip(&mut writer)?;
+    writer.seek(SeekFrom::Start(end))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::{BitmapId, BlobId, MaterialParameterFlags, Pak, PakBuf},
+        std::io::Cursor,
+    };
+
+    fn encoded(value: impl serde::Serialize) -> Vec<u8> {
+        bincode::serde::encode_to_vec(value, bincode::config::legacy()).unwrap()
+    }
+
+    #[test]
+    fn record_fields_are_forward_compatible() {
+        let mut record = encoded(7u32);
+        record.extend(encoded(true));
+
+        // Fields appended by a newer writer are ignored by older readers
+        let mut fields = RecordReader::new(&record);
+        assert_eq!(fields.read::<u32>().unwrap(), 7);
+
+        // Fields appended after this record was written are defaulted by newer readers
+        let mut fields = RecordReader::new(&record);
+        assert_eq!(fields.read::<u32>().unwrap(), 7);
+        assert!(fields.read::<bool>().unwrap());
+        assert_eq!(fields.read_or_default::<u32>().unwrap(), 0);
+        assert!(fields.read::<u32>().is_err());
+    }
+
+    #[test]
+    fn material_records_ignore_new_parameters() {
+        let mut record = encoded(false);
+        record.extend(encoded(BitmapId(1)));
+        record.extend(encoded(Some(BitmapId(2))));
+        record.extend(encoded(None::<BitmapId>));
+        record.extend(encoded(Some(BitmapId(3))));
+        record.extend(encoded(MaterialParameterFlags::ROUGH));
+
+        // A parameter a newer version of this crate might append, such as an occlusion map
+        record.extend(encoded(Some(BitmapId(4))));
+
+        assert_eq!(
+            read_material(&record).unwrap(),
+            MaterialInfo {
+                alpha_test: false,
+                color: BitmapId(1),
+                emissive: Some(BitmapId(2)),
+                normal: None,
+                params: Some(BitmapId(3)),
+                params_used: MaterialParameterFlags::ROUGH,
+            }
+        );
+    }
+
+    #[test]
+    fn reads_version_1_paks() {
+        let blob = encoded(b"version one".to_vec());
+        let skip = (MAGIC_V1.len() + 4 + 1 + blob.len()) as u32;
+        let ids = BTreeMap::from([("blob".to_owned(), Id::from(BlobId(0)))]);
+        let no_refs = Vec::<()>::new();
+
+        // Version 1 indexes are bincode-encoded structs with 32-bit ranges
+        let data = (
+            (
+                ids,
+                Vec::<MaterialInfo>::new(),
+                &no_refs,
+                &no_refs,
+                &no_refs,
+            ),
+            (1u64, 1u32, skip - blob.len() as u32..skip),
+            (&no_refs, &no_refs),
+        );
+
+        let mut pak = encoded(MAGIC_V1);
+        pak.extend(encoded(skip));
+        pak.extend(encoded(None::<Compression>));
+        pak.extend(blob);
+        pak.extend(encoded(&data));
+        pak.extend([0; PAK_HASH_LEN]);
+
+        let pak: &'static [u8] = Box::leak(pak.into_boxed_slice());
+        let mut pak = PakBuf::from_stream(Cursor::new(pak)).unwrap();
+
+        assert_eq!(pak.read_blob("blob").unwrap(), b"version one");
+    }
+
+    #[test]
+    fn newer_versions_are_unsupported() {
+        let mut pak = encoded(MAGIC);
+        pak.extend(encoded(FORMAT_VERSION + 1));
+        pak.extend([0; 64]);
+        let pak: &'static [u8] = Box::leak(pak.into_boxed_slice());
+
+        assert!(matches!(
+            PakBuf::from_stream(Cursor::new(pak)),
+            Err(PakError::UnsupportedVersion { version }) if version == FORMAT_VERSION + 1
+        ));
+    }
+}
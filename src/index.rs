@@ -4,6 +4,7 @@ use {
     std::mem::size_of,
 };
 
+/// Triangle list indices, stored as little-endian bytes of the narrowest type which fits them.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub struct IndexBuffer {
     buf: Vec<u8>,
@@ -32,14 +33,14 @@ impl IndexBuffer {
         } else if max_vertex <= u16::MAX as _ {
             let mut buf = Vec::with_capacity(indices.len() << 1);
             for &idx in indices {
-                buf.extend_from_slice(&(idx as u16).to_ne_bytes());
+                buf.extend_from_slice(&(idx as u16).to_le_bytes());
             }
 
             (buf, IndexType::U16)
         } else {
             let mut buf = Vec::with_capacity(indices.len() << 2);
             for &idx in indices {
-                buf.extend_from_slice(&idx.to_ne_bytes());
+                buf.extend_from_slice(&idx.to_le_bytes());
             }
 
             (buf, IndexType::U32)
@@ -66,7 +67,7 @@ impl IndexBuffer {
                 for idx in 0..count {
                     let idx = idx << 1;
                     let data = &self.buf[idx..idx + 2];
-                    res.push(u16::from_ne_bytes([data[0], data[1]]));
+                    res.push(u16::from_le_bytes([data[0], data[1]]));
                 }
 
                 Some(res)
@@ -86,7 +87,7 @@ impl IndexBuffer {
                 for idx in 0..count {
                     let idx = idx << 1;
                     let data = &self.buf[idx..idx + 2];
-                    res.push(u16::from_ne_bytes([data[0], data[1]]) as _);
+                    res.push(u16::from_le_bytes([data[0], data[1]]) as _);
                 }
 
                 res
@@ -99,7 +100,7 @@ impl IndexBuffer {
                 for idx in 0..count {
                     let idx = idx << 2;
                     let data = &self.buf[idx..idx + 4];
-                    res.push(u32::from_ne_bytes([data[0], data[1], data[2], data[3]]));
+                    res.push(u32::from_le_bytes([data[0], data[1], data[2], data[3]]));
                 }
 
                 res
@@ -107,6 +108,11 @@ impl IndexBuffer {
         }
     }
 
+    /// Returns the little-endian bytes of each index, using the width of `index_type`.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
     pub fn index_count(&self) -> usize {
         match self.ty {
             IndexType::U8 => self.buf.len(),
@@ -262,6 +268,34 @@ mod test {
         }
     }
 
+    #[test]
+    fn index_buffer_bytes_are_little_endian() {
+        assert_eq!(
+            IndexBuffer::new(&[0, 1, 0x1234, 0, 1, 2]).unwrap().buf,
+            [0, 0, 1, 0, 0x34, 0x12, 0, 0, 1, 0, 2, 0]
+        );
+        assert_eq!(
+            IndexBuffer::new(&[0, 1, 0x12_3456, 0, 1, 2]).unwrap().buf[8..12],
+            [0x56, 0x34, 0x12, 0]
+        );
+
+        // Buffers baked on any host decode to the same indices
+        let index_buf = IndexBuffer {
+            buf: vec![0x01, 0x02, 0x03, 0x04, 0xff, 0x00],
+            ty: IndexType::U16,
+        };
+        assert_eq!(index_buf.as_u16().unwrap(), [0x0201, 0x0403, 0x00ff]);
+        assert_eq!(index_buf.as_u32(), [0x0201, 0x0403, 0x00ff]);
+
+        let index_buf = IndexBuffer {
+            buf: vec![
+                0x01, 0x02, 0x03, 0x04, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00,
+            ],
+            ty: IndexType::U32,
+        };
+        assert_eq!(index_buf.as_u32(), [0x0403_0201, 0xff, 0x1_0000]);
+    }
+
     #[test]
     fn deserialize_rejects_malformed_index_buffer() {
         let invalid = IndexBuffer {
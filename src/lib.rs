@@ -9,27 +9,59 @@ pub mod scene;
 pub mod buf;
 
 mod compression;
+mod error;
+mod format;
+
+#[cfg(feature = "async")]
+mod async_pak;
+#[cfg(feature = "mmap")]
+mod mapped;
+mod patch;
+mod report;
+mod set;
+mod shared;
 
 use {
     self::{
-        anim::Animation, bitmap::Bitmap, bitmap_font::BitmapFont, compression::Compression,
-        mesh::Mesh, scene::Scene,
+        anim::Animation, bitmap::Bitmap, bitmap_font::BitmapFont, format::Header, mesh::Mesh,
+        scene::Scene,
     },
+    bincode::error::DecodeError,
     bitflags::bitflags,
-    log::{trace, warn},
+    log::trace,
     paste::paste,
     serde::{Deserialize, Serialize, de::DeserializeOwned},
     std::{
         collections::BTreeMap,
-        fmt::{Debug, Formatter},
+        fmt::{Debug, Display, Formatter},
         fs::File,
         io::{BufReader, Cursor, Error, ErrorKind, Read, Seek, SeekFrom},
         mem::size_of,
         ops::Range,
         path::{Path, PathBuf},
+        sync::Arc,
     },
 };
 
+pub use self::{
+    compression::{BrotliParams, Compression},
+    error::PakError,
+    format::FORMAT_VERSION,
+    patch::PatchedPak,
+    report::{AssetDetails, AssetReport, PakReport, PrimitiveReport},
+    set::PakSet,
+    shared::SharedPak,
+};
+
+#[cfg(feature = "bake")]
+pub use self::patch::PatchPak;
+
+#[cfg(feature = "async")]
+pub use self::async_pak::{AsyncPak, AsyncPakBuf, AsyncStream};
+
+#[cfg(feature = "mmap")]
+pub use self::mapped::MappedPak;
+
 pub type Vec3 = [f32; 3];
 pub type Quat = [f32; 4];
 pub type Mat4 = [f32; 16];
@@ -82,6 +114,134 @@ fn read_hash_trailer(reader: &mut impl Read) -> Result<u64, Error> {
     }
 }
 
+/// Appends the hash trailer to the pak which was written to the given path.
+#[cfg(feature = "bake")]
+fn write_hash_trailer(path: &Path) -> Result<(), Error> {
+    use std::{fs::OpenOptions, io::Write};
+
+    let mut reader = File::open(path)?;
+    let len = reader.metadata()?.len();
+    let hash = pak_hash_stream(&mut reader, len)?;
+    let mut writer = OpenOptions::new().append(true).open(path)?;
+    bincode::serde::encode_into_std_write(hash, &mut writer, bincode::config::legacy())
+        .map_err(|_| Error::from(ErrorKind::InvalidData))?;
+    writer.flush()
+}
+
+/// Appends the hash trailer to the pak which was written to `stream` from `start` up to its
+/// current position, reading the pak back to hash it.
+#[cfg(feature = "bake")]
+fn write_stream_hash_trailer(
+    mut stream: impl Read + std::io::Write + Seek,
+    start: u64,
+) -> Result<(), Error> {
+    let end = stream.stream_position()?;
+    stream.seek(SeekFrom::Start(start))?;
+    let hash = pak_hash_stream(&mut stream, end - start)?;
+    bincode::serde::encode_into_std_write(hash, &mut stream, bincode::config::legacy())
+        .map_err(|_| Error::from(ErrorKind::InvalidData))?;
+    stream.flush()
+}
+
+/// Decodes one asset from its stored bytes, decompressing first if the pak is compressed.
+fn decode_data<T>(
+    compression: Option<Compression>,
+    dictionary: Option<&[u8]>,
+    data: &[u8],
+) -> Result<T, PakError>
+where
+    T: DeserializeOwned,
+{
+    // Optionally create a compression reader (or just use the one we have)
+    if let Some(compressed) = compression {
+        let mut reader = compressed
+            .new_reader(data, dictionary)
+            .map_err(PakError::Decompression)?;
+        let decoded = bincode::serde::decode_from_std_read(&mut reader, bincode::config::legacy())
+            .map_err(decompression_error)?;
+
+        let mut trailing = [0; 1];
+        match reader.read(&mut trailing) {
+            Ok(0) => Ok(decoded),
+            Ok(_) => Err(PakError::TrailingBytes),
+            Err(err) => Err(PakError::Decompression(err)),
+        }
+    } else {
+        let (decoded, consumed) =
+            bincode::serde::decode_from_slice(data, bincode::config::legacy())?;
+
+        if consumed == data.len() {
+            Ok(decoded)
+        } else {
+            Err(PakError::TrailingBytes)
+        }
+    }
+}
+
+/// Maps a failure to decode from a decompressing reader, where I/O errors come from the codec.
+fn decompression_error(err: DecodeError) -> PakError {
+    match err {
+        DecodeError::Io { inner, .. } => PakError::Decompression(inner),
+        err => PakError::Decode(err),
+    }
+}
+
+/// Maps a failure to read an asset byte range, where running out of data means a bad range.
+fn range_read_error(err: Error) -> PakError {
+    if err.kind() == ErrorKind::UnexpectedEof {
+        PakError::TruncatedRange
+    } else {
+        PakError::Io(err)
+    }
+}
+
+/// Reads the header and main data of a pak, leaving the hash trailer unread.
+fn read_header(mut stream: impl Read + Seek) -> Result<(Header, Data), PakError> {
+    let header = Header::read(&mut stream)?;
+
+    // Read the main data, excluding the hash trailer. The trailer is not validated here.
+    let stream_end = stream.seek(SeekFrom::End(0))?;
+    let data_len = header.data_len(stream_end)?;
+    stream.seek(SeekFrom::Start(header.skip))?;
+
+    let data = header.read_data((&mut stream).take(data_len))?;
+
+    trace!(
+        "Read header: v{} {} bytes ({} keys)",
+        header.version,
+        data_len,
+        data.ids.len()
+    );
+
+    Ok((header, data))
+}
+
+/// Location of one stored asset within a pak.
+#[derive(Clone, Copy, Debug)]
+struct Stored {
+    checksum: Option<u32>,
+    compression: Option<Compression>,
+    len: usize,
+    pos: u64,
+}
+
+impl Stored {
+    /// Checks the stored bytes against the recorded checksum, if there is one.
+    fn verify(&self, data: &[u8]) -> Result<(), PakError> {
+        match self.checksum {
+            Some(expected) => {
+                let actual = crc32fast::hash(data);
+                if actual == expected {
+                    Ok(())
+                } else {
+                    Err(PakError::ChecksumMismatch { expected, actual })
+                }
+            }
+            None => Ok(()),
+        }
+    }
+}
+
 #[derive(Debug, Default, Deserialize, Serialize)]
 struct Data {
     // These fields are handled by bincode serialization as-is
@@ -97,47 +257,91 @@ struct Data {
     scenes: Vec<DataRef<Scene>>,
 }
 
+impl Data {
+    /// Gets the ID stored under the given key, if one exists.
+    fn id(&self, key: &str) -> Option<Id> {
+        self.ids.get(key).copied()
+    }
+
+    /// Gets the reference to the stored bytes of the asset with the given ID.
+    fn data_ref<I>(&self, id: I) -> Result<&DataRef<I::Asset>, PakError>
+    where
+        I: StoredId,
+    {
+        trace!("Deserializing {} {}", I::KIND, id.index());
+
+        I::refs(self).get(id.index()).ok_or(PakError::InvalidId {
+            kind: I::KIND,
+            id: id.index(),
+        })
+    }
+
+    /// Finds the stored bytes of the asset with the given ID; `compression` is the pak
+    /// compression.
+    fn stored<I>(&self, id: I, compression: Option<Compression>) -> Result<Stored, PakError>
+    where
+        I: StoredId,
+    {
+        self.data_ref(id)?.stored(compression)
+    }
+}
+
 #[derive(Deserialize, PartialEq, Serialize)]
 enum DataRef<T> {
     Data(T),
-    Ref(Range<u32>),
+    Ref(Range<u64>),
+
+    /// A range of stored bytes along with their CRC-32 checksum.
+    Checked(Range<u64>, u32),
+
+    /// A range of stored bytes found in the base pak of a patch, along with their CRC-32 checksum.
+    Base(Range<u64>, u32),
+
+    /// A range of stored bytes along with their CRC-32 checksum and the compression they were
+    /// stored with, which is `None` for uncompressed bytes.
+    Compressed(Range<u64>, u32, Option<Compression>),
+
+    /// A range of stored bytes found in the base pak of a patch, along with their CRC-32 checksum
+    /// and the compression they were stored with.
+    BaseCompressed(Range<u64>, u32, Option<Compression>),
 }
 
 impl<T> DataRef<T> {
-    fn pos_len(&self) -> Result<(u64, usize), Error> {
-        match self {
-            Self::Ref(range) => {
-                let len = range
-                    .end
-                    .checked_sub(range.start)
-                    .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
-
-                Ok((range.start as _, len as _))
-            }
-            _ => {
-                warn!("Expected position and length but found data");
-
-                Err(Error::from(ErrorKind::InvalidInput))
-            }
+    /// Returns the stored bytes; `compression` is the pak compression, used by refs written
+    /// before the compression of each asset was recorded.
+    fn stored(&self, compression: Option<Compression>) -> Result<Stored, PakError> {
+        match self.stored_in(compression)? {
+            (stored, false) => Ok(stored),
+            (_, true) => Err(PakError::UnexpectedBaseData),
         }
     }
-}
 
-impl<T> DataRef<T>
-where
-    T: Serialize,
-{
-    #[cfg(feature = "bake")]
-    fn serialize(&self) -> Result<Vec<u8>, Error> {
-        let mut buf = vec![];
-        let data = match self {
-            Self::Data(t) => t,
-            Self::Ref(_) => return Err(Error::from(ErrorKind::InvalidData)),
+    /// Returns the stored bytes along with whether they are found in the base pak of a patch.
+    fn stored_in(&self, compression: Option<Compression>) -> Result<(Stored, bool), PakError> {
+        let (range, checksum, compression, base) = match self {
+            Self::Ref(range) => (range, None, compression, false),
+            Self::Checked(range, checksum) => (range, Some(*checksum), compression, false),
+            Self::Base(range, checksum) => (range, Some(*checksum), compression, true),
+            Self::Compressed(range, checksum, compression) => {
+                (range, Some(*checksum), *compression, false)
+            }
+            Self::BaseCompressed(range, checksum, compression) => {
+                (range, Some(*checksum), *compression, true)
+            }
+            Self::Data(_) => return Err(PakError::UnexpectedInlineData),
+        };
+        let len = range
+            .end
+            .checked_sub(range.start)
+            .ok_or(PakError::TruncatedRange)?;
+        let stored = Stored {
+            checksum,
+            compression,
+            len: usize::try_from(len).map_err(|_| PakError::TruncatedRange)?,
+            pos: range.start,
         };
-        bincode::serde::encode_into_std_write(data, &mut buf, bincode::config::legacy())
-            .map_err(|_| Error::from(ErrorKind::InvalidData))?;
 
-        Ok(buf)
+        Ok((stored, base))
     }
 }
 
@@ -145,7 +349,7 @@ impl<T> Debug for DataRef<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_str(match self {
             Self::Data(_) => "Data",
-            Self::Ref(_) => "DataRef",
+            _ => "DataRef",
         })
     }
 }
@@ -153,6 +357,25 @@ impl<T> Debug for DataRef<T> {
 macro_rules! id_enum {
     ($($variant:ident),*) => {
         paste::paste! {
+            /// The kinds of asset which may be stored in a `.pak` file.
+            #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+            #[serde(rename_all = "snake_case")]
+            pub enum AssetKind {
+                $(
+                    $variant,
+                )*
+            }
+
+            impl Display for AssetKind {
+                fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                    f.write_str(match self {
+                        $(
+                            Self::$variant => stringify!([<$variant:snake>]),
+                        )*
+                    })
+                }
+            }
+
             #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
             enum Id {
                 $(
@@ -161,6 +384,23 @@ macro_rules! id_enum {
             }
 
             impl Id {
+                fn kind(&self) -> AssetKind {
+                    match self {
+                        $(
+                            Self::$variant(_) => AssetKind::$variant,
+                        )*
+                    }
+                }
+
+                #[cfg(feature = "bake")]
+                fn index(&self) -> usize {
+                    match self {
+                        $(
+                            Self::$variant(id) => id.0,
+                        )*
+                    }
+                }
+
                 $(
                     fn [<as_ $variant:snake>](&self) -> Option<[<$variant Id>]> {
                         match self {
@@ -202,6 +442,141 @@ id_struct!(Material);
 id_struct!(Mesh);
 id_struct!(Scene);
 
+/// IDs of assets which are stored outside of the index and read on demand.
+trait StoredId: Copy {
+    type Asset: DeserializeOwned;
+
+    const KIND: AssetKind;
+
+    fn index(self) -> usize;
+
+    fn refs(data: &Data) -> &[DataRef<Self::Asset>];
+}
+
+macro_rules! stored_id {
+    ($variant: ident, $asset: ty, $refs: ident) => {
+        paste! {
+            impl StoredId for [<$variant Id>] {
+                type Asset = $asset;
+
+                const KIND: AssetKind = AssetKind::$variant;
+
+                fn index(self) -> usize {
+                    self.0
+                }
+
+                fn refs(data: &Data) -> &[DataRef<$asset>] {
+                    &data.$refs
+                }
+            }
+        }
+    };
+}
+
+stored_id!(Animation, Animation, anims);
+stored_id!(Bitmap, Bitmap, bitmaps);
+stored_id!(BitmapFont, BitmapFont, bitmap_fonts);
+stored_id!(Blob, Vec<u8>, blobs);
+stored_id!(Mesh, Mesh, meshes);
+stored_id!(Scene, Scene, scenes);
+
+/// Implements `Pak` for a reader with `compression`, `data` and `dictionary` fields; the reader
+/// supplies only a `read_stored` function which returns the stored bytes of one asset.
+macro_rules! impl_pak {
+    ($reader: ty) => {
+        impl $crate::Pak for $reader {
+            fn asset_kind(&self, key: impl AsRef<str>) -> Option<$crate::AssetKind> {
+                self.data.id(key.as_ref()).map(|id| id.kind())
+            }
+
+            fn animation_id(&self, key: impl AsRef<str>) -> Option<$crate::AnimationId> {
+                self.data.id(key.as_ref())?.as_animation()
+            }
+
+            fn bitmap_font_id(&self, key: impl AsRef<str>) -> Option<$crate::BitmapFontId> {
+                self.data.id(key.as_ref())?.as_bitmap_font()
+            }
+
+            fn bitmap_id(&self, key: impl AsRef<str>) -> Option<$crate::BitmapId> {
+                self.data.id(key.as_ref())?.as_bitmap()
+            }
+
+            fn blob_id(&self, key: impl AsRef<str>) -> Option<$crate::BlobId> {
+                self.data.id(key.as_ref())?.as_blob()
+            }
+
+            fn material_id(&self, key: impl AsRef<str>) -> Option<$crate::MaterialId> {
+                self.data.id(key.as_ref())?.as_material()
+            }
+
+            fn mesh_id(&self, key: impl AsRef<str>) -> Option<$crate::MeshId> {
+                self.data.id(key.as_ref())?.as_mesh()
+            }
+
+            fn scene_id(&self, key: impl AsRef<str>) -> Option<$crate::SceneId> {
+                self.data.id(key.as_ref())?.as_scene()
+            }
+
+            fn read_animation_id(
+                &mut self,
+                id: impl Into<$crate::AnimationId>,
+            ) -> Result<$crate::anim::Animation, $crate::PakError> {
+                impl_pak!(@read self, id)
+            }
+
+            fn read_bitmap_font_id(
+                &mut self,
+                id: impl Into<$crate::BitmapFontId>,
+            ) -> Result<$crate::bitmap_font::BitmapFont, $crate::PakError> {
+                impl_pak!(@read self, id)
+            }
+
+            fn read_bitmap_id(
+                &mut self,
+                id: impl Into<$crate::BitmapId>,
+            ) -> Result<$crate::bitmap::Bitmap, $crate::PakError> {
+                impl_pak!(@read self, id)
+            }
+
+            fn read_blob_id(
+                &mut self,
+                id: impl Into<$crate::BlobId>,
+            ) -> Result<Vec<u8>, $crate::PakError> {
+                impl_pak!(@read self, id)
+            }
+
+            fn read_material_id(
+                &self,
+                id: impl Into<$crate::MaterialId>,
+            ) -> Option<$crate::MaterialInfo> {
+                self.data.materials.get(id.into().0).copied()
+            }
+
+            fn read_mesh_id(
+                &mut self,
+                id: impl Into<$crate::MeshId>,
+            ) -> Result<$crate::mesh::Mesh, $crate::PakError> {
+                impl_pak!(@read self, id)
+            }
+
+            fn read_scene_id(
+                &mut self,
+                id: impl Into<$crate::SceneId>,
+            ) -> Result<$crate::scene::Scene, $crate::PakError> {
+                impl_pak!(@read self, id)
+            }
+        }
+    };
+    (@read $self: ident, $id: ident) => {{
+        let stored = $self.data.stored($id.into(), $self.compression)?;
+        let buf = $self.read_stored(stored)?;
+
+        $crate::decode_data(stored.compression, $self.dictionary.as_deref(), &buf)
+    }};
+}
+
+pub(crate) use impl_pak;
+
 /// Holds bitmap handles to match what was setup in the asset `.toml` file.
 #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct MaterialInfo {
@@ -237,6 +612,9 @@ bitflags! {
 pub trait Pak {
     // --- "Get by id" functions
 
+    /// Gets the kind of asset stored under the given key, if one exists.
+    fn asset_kind(&self, key: impl AsRef<str>) -> Option<AssetKind>;
+
     /// Gets the pak-unique `AnimationId` corresponding to the given key, if one exists.
     fn animation_id(&self, key: impl AsRef<str>) -> Option<AnimationId>;
 
@@ -261,25 +639,25 @@ pub trait Pak {
     // --- "Read" functions
 
     /// Gets the corresponding animation for the given ID.
-    fn read_animation_id(&mut self, id: impl Into<AnimationId>) -> Result<Animation, Error>;
+    fn read_animation_id(&mut self, id: impl Into<AnimationId>) -> Result<Animation, PakError>;
 
     /// Reads the corresponding bitmap for the given ID.
-    fn read_bitmap_font_id(&mut self, id: impl Into<BitmapFontId>) -> Result<BitmapFont, Error>;
+    fn read_bitmap_font_id(&mut self, id: impl Into<BitmapFontId>) -> Result<BitmapFont, PakError>;
 
     /// Reads the corresponding bitmap for the given ID.
-    fn read_bitmap_id(&mut self, id: impl Into<BitmapId>) -> Result<Bitmap, Error>;
+    fn read_bitmap_id(&mut self, id: impl Into<BitmapId>) -> Result<Bitmap, PakError>;
 
     /// Gets the corresponding blob for the given ID.
-    fn read_blob_id(&mut self, id: impl Into<BlobId>) -> Result<Vec<u8>, Error>;
+    fn read_blob_id(&mut self, id: impl Into<BlobId>) -> Result<Vec<u8>, PakError>;
 
     /// Gets the material for the given handle, if one exists.
     fn read_material_id(&self, id: impl Into<MaterialId>) -> Option<MaterialInfo>;
 
     /// Gets the corresponding mesh for the given ID.
-    fn read_mesh_id(&mut self, id: impl Into<MeshId>) -> Result<Mesh, Error>;
+    fn read_mesh_id(&mut self, id: impl Into<MeshId>) -> Result<Mesh, PakError>;
 
     /// Gets the corresponding scene for the given ID.
-    fn read_scene_id(&mut self, id: impl Into<SceneId>) -> Result<Scene, Error>;
+    fn read_scene_id(&mut self, id: impl Into<SceneId>) -> Result<Scene, PakError>;
 
     // --- Convenience functions
 
@@ -294,63 +672,99 @@ pub trait Pak {
         }
     }
 
-    fn read_animation(&mut self, key: impl AsRef<str>) -> Result<Animation, Error> {
-        trace!("Reading animation {}", key.as_ref());
+    fn read_animation(&mut self, key: impl AsRef<str>) -> Result<Animation, PakError> {
+        let key = key.as_ref();
+
+        trace!("Reading animation {key}");
 
         if let Some(h) = self.animation_id(key) {
             self.read_animation_id(h)
         } else {
-            Err(Error::from(ErrorKind::InvalidInput))
+            Err(PakError::missing_key(
+                key,
+                AssetKind::Animation,
+                self.asset_kind(key),
+            ))
         }
     }
 
-    fn read_bitmap_font(&mut self, key: impl AsRef<str>) -> Result<BitmapFont, Error> {
-        trace!("Reading bitmap font {}", key.as_ref());
+    fn read_bitmap_font(&mut self, key: impl AsRef<str>) -> Result<BitmapFont, PakError> {
+        let key = key.as_ref();
+
+        trace!("Reading bitmap font {key}");
 
         if let Some(h) = self.bitmap_font_id(key) {
             self.read_bitmap_font_id(h)
         } else {
-            Err(Error::from(ErrorKind::InvalidInput))
+            Err(PakError::missing_key(
+                key,
+                AssetKind::BitmapFont,
+                self.asset_kind(key),
+            ))
         }
     }
 
-    fn read_bitmap(&mut self, key: impl AsRef<str>) -> Result<Bitmap, Error> {
-        trace!("Reading bitmap {}", key.as_ref());
+    fn read_bitmap(&mut self, key: impl AsRef<str>) -> Result<Bitmap, PakError> {
+        let key = key.as_ref();
+
+        trace!("Reading bitmap {key}");
 
         if let Some(h) = self.bitmap_id(key) {
             self.read_bitmap_id(h)
         } else {
-            Err(Error::from(ErrorKind::InvalidInput))
+            Err(PakError::missing_key(
+                key,
+                AssetKind::Bitmap,
+                self.asset_kind(key),
+            ))
         }
     }
 
-    fn read_blob(&mut self, key: impl AsRef<str>) -> Result<Vec<u8>, Error> {
-        trace!("Reading blob {}", key.as_ref());
+    fn read_blob(&mut self, key: impl AsRef<str>) -> Result<Vec<u8>, PakError> {
+        let key = key.as_ref();
+
+        trace!("Reading blob {key}");
 
         if let Some(h) = self.blob_id(key) {
             self.read_blob_id(h)
         } else {
-            Err(Error::from(ErrorKind::InvalidInput))
+            Err(PakError::missing_key(
+                key,
+                AssetKind::Blob,
+                self.asset_kind(key),
+            ))
         }
     }
 
-    fn read_mesh(&mut self, key: impl AsRef<str>) -> Result<Mesh, Error> {
-        trace!("Reading mesh {}", key.as_ref());
+    fn read_mesh(&mut self, key: impl AsRef<str>) -> Result<Mesh, PakError> {
+        let key = key.as_ref();
+
+        trace!("Reading mesh {key}");
 
         if let Some(h) = self.mesh_id(key) {
             self.read_mesh_id(h)
         } else {
-            Err(Error::from(ErrorKind::InvalidInput))
+            Err(PakError::missing_key(
+                key,
+                AssetKind::Mesh,
+                self.asset_kind(key),
+            ))
         }
     }
 
-    fn read_scene(&mut self, key: impl AsRef<str>) -> Result<Scene, Error> {
-        trace!("Reading scene {}", key.as_ref());
+    fn read_scene(&mut self, key: impl AsRef<str>) -> Result<Scene, PakError> {
+        let key = key.as_ref();
+
+        trace!("Reading scene {key}");
 
         if let Some(h) = self.scene_id(key) {
             self.read_scene_id(h)
         } else {
-            Err(Error::from(ErrorKind::InvalidInput))
+            Err(PakError::missing_key(
+                key,
+                AssetKind::Scene,
+                self.asset_kind(key),
+            ))
         }
     }
 }
@@ -360,7 +774,9 @@ pub trait Pak {
 pub struct PakBuf {
     compression: Option<Compression>,
     data: Data,
+    dictionary: Option<Vec<u8>>,
     reader: Box<dyn Stream>,
+    verify: bool,
 }
 
 impl PakBuf {
@@ -380,10 +796,16 @@ impl PakBuf {
         self.data.blobs.len()
     }
 
-    fn deserialize<T>(&mut self, pos: u64, len: usize) -> Result<T, Error>
-    where
-        T: DeserializeOwned,
-    {
+    /// Returns the compression used by the index of this pak, which is also the compression of
+    /// stored assets unless their content files chose another.
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+
+    /// Reads the stored bytes of one asset, checking them if verification is enabled.
+    fn read_stored(&mut self, stored: Stored) -> Result<Vec<u8>, PakError> {
+        let Stored { len, pos, .. } = stored;
+
         trace!("Read data: {len} bytes ({pos}..{})", pos + len as u64);
 
         // Create a zero-filled buffer
@@ -391,107 +813,35 @@ impl PakBuf {
 
         // Read the data into our buffer
         self.reader.seek(SeekFrom::Start(pos))?;
-        self.reader.read_exact(&mut buf)?;
-        let data = buf.as_slice();
-
-        // Optionally create a compression reader (or just use the one we have)
-        if let Some(compressed) = self.compression {
-            let mut reader = compressed.new_reader(data);
-            let decoded =
-                bincode::serde::decode_from_std_read(&mut reader, bincode::config::legacy())
-                    .map_err(|err| {
-                        warn!("Unable to deserialize: {}", err);
-
-                        Error::from(ErrorKind::InvalidData)
-                    })?;
-
-            let mut trailing = [0; 1];
-            match reader.read(&mut trailing) {
-                Ok(0) => Ok(decoded),
-                Ok(_) => {
-                    warn!("Trailing bytes after deserialized data");
-
-                    Err(Error::from(ErrorKind::InvalidData))
-                }
-                Err(err) => {
-                    warn!("Unable to verify deserialized data end: {}", err);
-
-                    Err(Error::from(ErrorKind::InvalidData))
-                }
-            }
-        } else {
-            let (decoded, consumed) =
-                bincode::serde::decode_from_slice(data, bincode::config::legacy()).map_err(
-                    |err| {
-                        warn!("Unable to deserialize: {}", err);
-
-                        Error::from(ErrorKind::InvalidData)
-                    },
-                )?;
-
-            if consumed == data.len() {
-                Ok(decoded)
-            } else {
-                warn!("Trailing bytes after deserialized data");
-
-                Err(Error::from(ErrorKind::InvalidData))
-            }
-        }
-    }
-
-    pub fn from_stream(mut stream: impl Stream + 'static) -> Result<Self, Error> {
-        fn decode<T>(stream: &mut impl Read, msg: &str) -> Result<T, Error>
-        where
-            T: DeserializeOwned,
-        {
-            bincode::serde::decode_from_std_read(stream, bincode::config::legacy()).map_err(|_| {
-                warn!("{}", msg);
-                Error::from(ErrorKind::InvalidData)
-            })
-        }
+        self.reader.read_exact(&mut buf).map_err(range_read_error)?;
 
-        let magic_bytes: [u8; 20] = decode(&mut stream, "Unable to read magic bytes")?;
-        if &magic_bytes != b"ATTACKGOAT-PAK-V1.0 " {
-            warn!("Unsupported magic bytes");
-
-            return Err(Error::from(ErrorKind::InvalidData));
+        if self.verify {
+            stored.verify(&buf)?;
         }
 
-        // Read the number of bytes we must 'skip' in order to read the main data
-        let skip: u32 = decode(&mut stream, "Unable to read skip length")?;
+        Ok(buf)
+    }
 
-        let compression: Option<Compression> =
-            decode(&mut stream, "Unable to read compression data")?;
+    /// Converts this pak into one which may be read from many threads at once.
+    pub fn into_shared(self) -> SharedPak {
+        SharedPak::from(self)
+    }
 
-        // Read the main data, excluding the hash trailer. The trailer is not validated here.
-        let stream_end = stream.seek(SeekFrom::End(0))?;
-        let header_end = stream_end
-            .checked_sub(PAK_HASH_LEN as u64)
-            .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
-        let header_len = header_end
-            .checked_sub(skip as u64)
-            .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
-        stream.seek(SeekFrom::Start(skip as _))?;
-
-        let data: Data = if let Some(compressed) = compression {
-            let mut header = (&mut stream).take(header_len);
-            let mut compressed = compressed.new_reader(&mut header);
-            decode(&mut compressed, "Unable to read header")?
-        } else {
-            let mut header = (&mut stream).take(header_len);
-            decode(&mut header, "Unable to read header")?
-        };
+    /// Decodes a `Pak` from bytes held in memory, such as those written by
+    /// `PakBuf::bake_to_writer`.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, PakError> {
+        Self::from_stream(Cursor::new(Arc::<[u8]>::from(data)))
+    }
 
-        trace!(
-            "Read header: {} bytes ({} keys)",
-            header_len,
-            data.ids.len()
-        );
+    pub fn from_stream(mut stream: impl Stream + 'static) -> Result<Self, PakError> {
+        let (header, data) = read_header(&mut stream)?;
 
         Ok(Self {
-            compression,
+            compression: header.compression,
             data,
+            dictionary: header.dictionary,
             reader: Box::new(stream),
+            verify: false,
         })
     }
 
@@ -499,12 +849,23 @@ impl PakBuf {
         self.data.ids.keys().map(|key| key.as_str())
     }
 
-    pub fn validate_hash(&self) -> Result<bool, Error> {
+    /// Reads the hash trailer of this pak without validating it.
+    fn trailer_hash(&self) -> Result<u64, PakError> {
         let mut reader = self.reader.open()?;
+        reader.seek(SeekFrom::End(-(PAK_HASH_LEN as i64)))?;
+
+        Ok(read_hash_trailer(&mut reader)?)
+    }
+
+    pub fn validate_hash(&self) -> Result<bool, PakError> {
+        Self::validate_stream_hash(self.reader.open()?)
+    }
+
+    fn validate_stream_hash(mut reader: Box<dyn Stream>) -> Result<bool, PakError> {
         let stream_end = reader.seek(SeekFrom::End(0))?;
         let payload_len = stream_end
             .checked_sub(PAK_HASH_LEN as u64)
-            .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+            .ok_or(PakError::TruncatedRange)?;
 
         reader.seek(SeekFrom::Start(0))?;
         let actual = pak_hash_stream(&mut reader, payload_len)?;
@@ -521,7 +882,7 @@ impl PakBuf {
     }
 
     /// Opens the given path and decodes a `Pak`.
-    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, PakError> {
         let path = path.as_ref().to_path_buf();
         let file = File::open(&path)?;
         let buf = BufReader::new(file);
@@ -532,154 +893,42 @@ impl PakBuf {
     pub fn scene_count(&self) -> usize {
         self.data.scenes.len()
     }
-}
-
-impl Pak for PakBuf {
-    /// Gets the pak-unique `AnimationId` corresponding to the given key, if one exists.
-    fn animation_id(&self, key: impl AsRef<str>) -> Option<AnimationId> {
-        self.data
-            .ids
-            .get(key.as_ref())
-            .and_then(|id| id.as_animation())
-    }
-
-    /// Gets the pak-unique `BitmapFontId` corresponding to the given key, if one exists.
-    fn bitmap_font_id(&self, key: impl AsRef<str>) -> Option<BitmapFontId> {
-        self.data
-            .ids
-            .get(key.as_ref())
-            .and_then(|id| id.as_bitmap_font())
-    }
-
-    /// Gets the pak-unique `BitmapId` corresponding to the given key, if one exists.
-    fn bitmap_id(&self, key: impl AsRef<str>) -> Option<BitmapId> {
-        self.data
-            .ids
-            .get(key.as_ref())
-            .and_then(|id| id.as_bitmap())
-    }
-
-    /// Gets the pak-unique `BlobId` corresponding to the given key, if one exists.
-    fn blob_id(&self, key: impl AsRef<str>) -> Option<BlobId> {
-        self.data.ids.get(key.as_ref()).and_then(|id| id.as_blob())
-    }
-
-    /// Gets the pak-unique `MaterialId` corresponding to the given key, if one exists.
-    fn material_id(&self, key: impl AsRef<str>) -> Option<MaterialId> {
-        self.data
-            .ids
-            .get(key.as_ref())
-            .and_then(|id| id.as_material())
-    }
 
-    /// Gets the pak-unique `MeshId` corresponding to the given key, if one exists.
-    fn mesh_id(&self, key: impl AsRef<str>) -> Option<MeshId> {
-        self.data.ids.get(key.as_ref()).and_then(|id| id.as_mesh())
-    }
-
-    /// Gets the pak-unique `SceneId` corresponding to the given key, if one exists.
-    fn scene_id(&self, key: impl AsRef<str>) -> Option<SceneId> {
-        self.data.ids.get(key.as_ref()).and_then(|id| id.as_scene())
-    }
-
-    /// Gets the corresponding animation for the given ID.
-    fn read_animation_id(&mut self, id: impl Into<AnimationId>) -> Result<Animation, Error> {
-        let id = id.into();
-
-        trace!("Deserializing animation {}", id.0);
-
-        let (pos, len) = self
-            .data
-            .anims
-            .get(id.0)
-            .ok_or_else(|| Error::from(ErrorKind::InvalidInput))?
-            .pos_len()?;
-        self.deserialize(pos, len)
-    }
-
-    /// Reads the corresponding bitmap for the given ID.
-    fn read_bitmap_font_id(&mut self, id: impl Into<BitmapFontId>) -> Result<BitmapFont, Error> {
-        let id = id.into();
-
-        trace!("Deserializing bitmap font {}", id.0);
-
-        let (pos, len) = self
-            .data
-            .bitmap_fonts
-            .get(id.0)
-            .ok_or_else(|| Error::from(ErrorKind::InvalidInput))?
-            .pos_len()?;
-        self.deserialize(pos, len)
-    }
-
-    /// Reads the corresponding bitmap for the given ID.
-    fn read_bitmap_id(&mut self, id: impl Into<BitmapId>) -> Result<Bitmap, Error> {
-        let id = id.into();
-
-        trace!("Deserializing bitmap {}", id.0);
-
-        let (pos, len) = self
-            .data
-            .bitmaps
-            .get(id.0)
-            .ok_or_else(|| Error::from(ErrorKind::InvalidInput))?
-            .pos_len()?;
-        self.deserialize(pos, len)
-    }
-
-    /// Gets the corresponding blob for the given ID.
-    fn read_blob_id(&mut self, id: impl Into<BlobId>) -> Result<Vec<u8>, Error> {
-        let id = id.into();
-
-        trace!("Deserializing blob {}", id.0);
-
-        let (pos, len) = self
-            .data
-            .blobs
-            .get(id.0)
-            .ok_or_else(|| Error::from(ErrorKind::InvalidInput))?
-            .pos_len()?;
-        self.deserialize(pos, len)
-    }
-
-    /// Gets the material for the given ID.
-    fn read_material_id(&self, id: impl Into<MaterialId>) -> Option<MaterialInfo> {
-        let id = id.into();
-
-        self.data.materials.get(id.0).copied()
-    }
-
-    /// Gets the corresponding mesh for the given ID.
-    fn read_mesh_id(&mut self, id: impl Into<MeshId>) -> Result<Mesh, Error> {
-        let id = id.into();
+    /// Returns the number of bytes stored for the asset under the given key, after compression.
+    ///
+    /// Materials are stored within the index and return `None`, as do keys which do not exist.
+    pub fn stored_len(&self, key: impl AsRef<str>) -> Option<usize> {
+        fn len<T>(refs: &[DataRef<T>], idx: usize) -> Option<usize> {
+            refs.get(idx)?
+                .stored_in(None)
+                .ok()
+                .map(|(stored, _)| stored.len)
+        }
 
-        trace!("Deserializing mesh {}", id.0);
+        let data = &self.data;
 
-        let (pos, len) = self
-            .data
-            .meshes
-            .get(id.0)
-            .ok_or_else(|| Error::from(ErrorKind::InvalidInput))?
-            .pos_len()?;
-        self.deserialize(pos, len)
+        match *data.ids.get(key.as_ref())? {
+            Id::Animation(id) => len(&data.anims, id.0),
+            Id::Bitmap(id) => len(&data.bitmaps, id.0),
+            Id::BitmapFont(id) => len(&data.bitmap_fonts, id.0),
+            Id::Blob(id) => len(&data.blobs, id.0),
+            Id::Material(_) => None,
+            Id::Mesh(id) => len(&data.meshes, id.0),
+            Id::Scene(id) => len(&data.scenes, id.0),
+        }
     }
 
-    /// Gets the corresponding animation for the given ID.
-    fn read_scene_id(&mut self, id: impl Into<SceneId>) -> Result<Scene, Error> {
-        let id = id.into();
-
-        trace!("Deserializing scene {}", id.0);
-
-        let (pos, len) = self
-            .data
-            .scenes
-            .get(id.0)
-            .ok_or_else(|| Error::from(ErrorKind::InvalidInput))?
-            .pos_len()?;
-        self.deserialize(pos, len)
+    /// Sets whether each asset is checked against its stored checksum when read.
+    ///
+    /// Paks written before checksums were stored are read without verification.
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
     }
 }
 
+impl_pak!(PakBuf);
+
 #[derive(Debug)]
 struct PakFile {
     buf: BufReader<File>,
@@ -726,6 +975,12 @@ impl Stream for Cursor<&'static [u8]> {
     }
 }
 
+impl Stream for Cursor<Arc<[u8]>> {
+    fn open(&self) -> Result<Box<dyn Stream>, Error> {
+        Ok(Box::new(Cursor::new(Arc::clone(self.get_ref()))))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -734,7 +989,9 @@ mod test {
         PakBuf {
             compression: None,
             data: Data::default(),
+            dictionary: None,
             reader: Box::new(Cursor::new(&[] as &'static [u8])),
+            verify: false,
         }
     }
 
@@ -811,7 +1068,7 @@ mod test {
         let encoded: &'static [u8] = Box::leak(encoded.into_boxed_slice());
 
         let mut pak = empty_pak();
-        pak.data.blobs.push(DataRef::Ref(0..encoded.len() as u32));
+        pak.data.blobs.push(DataRef::Ref(0..encoded.len() as u64));
         pak.reader = Box::new(Cursor::new(encoded));
 
         assert_eq!(
@@ -821,4 +1078,90 @@ mod test {
             ErrorKind::InvalidData,
         );
     }
+
+    #[test]
+    fn read_errors_are_typed() {
+        assert!(matches!(
+            PakBuf::from_stream(Cursor::new(b"NOT-AN-ATTACKGOAT-PAK-FILE" as &'static [u8])),
+            Err(PakError::BadMagic)
+        ));
+        assert!(matches!(
+            PakBuf::from_stream(Cursor::new(
+                b"ATTACKGOAT-PAK\0\0\0\0\0\0\xff\xff\0\0\0\0" as &'static [u8]
+            )),
+            Err(PakError::UnsupportedVersion { version: 0xffff })
+        ));
+        assert!(matches!(
+            empty_pak().read_blob("missing"),
+            Err(PakError::KeyNotFound { key }) if key == "missing"
+        ));
+        assert!(matches!(
+            empty_pak().read_mesh_id(MeshId(3)),
+            Err(PakError::InvalidId {
+                kind: AssetKind::Mesh,
+                id: 3
+            })
+        ));
+
+        let mut pak = empty_pak();
+        pak.data.blobs.push(DataRef::Ref(0..4));
+        assert!(matches!(
+            pak.read_blob_id(BlobId(0)),
+            Err(PakError::TruncatedRange)
+        ));
+    }
+
+    #[test]
+    fn verify_checks_stored_checksums() {
+        let mut encoded = Vec::new();
+        bincode::serde::encode_into_std_write(
+            b"blob".to_vec(),
+            &mut encoded,
+            bincode::config::legacy(),
+        )
+        .unwrap();
+        let checksum = crc32fast::hash(&encoded);
+        let encoded: &'static [u8] = Box::leak(encoded.into_boxed_slice());
+        let len = encoded.len() as u64;
+
+        let mut pak = empty_pak().with_verify(true);
+        pak.data.blobs.push(DataRef::Checked(0..len, checksum));
+        pak.data.blobs.push(DataRef::Checked(0..len, !checksum));
+        pak.data.blobs.push(DataRef::Ref(0..len));
+        pak.reader = Box::new(Cursor::new(encoded));
+
+        assert_eq!(pak.read_blob_id(BlobId(0)).unwrap(), b"blob");
+        assert!(matches!(
+            pak.read_blob_id(BlobId(1)),
+            Err(PakError::ChecksumMismatch { expected, actual })
+                if expected == !checksum && actual == checksum
+        ));
+        assert_eq!(pak.read_blob_id(BlobId(2)).unwrap(), b"blob");
+
+        let mut pak = pak.with_verify(false);
+        assert_eq!(pak.read_blob_id(BlobId(1)).unwrap(), b"blob");
+    }
+
+    #[test]
+    fn key_of_other_asset_kind_is_reported() {
+        let mut pak = empty_pak();
+        pak.data
+            .ids
+            .insert("shared".to_owned(), Id::Bitmap(BitmapId(0)));
+
+        assert_eq!(pak.asset_kind("shared"), Some(AssetKind::Bitmap));
+        assert_eq!(pak.asset_kind("missing"), None);
+        assert!(matches!(
+            pak.read_mesh("shared"),
+            Err(PakError::WrongAssetType {
+                key,
+                expected: AssetKind::Mesh,
+                found: AssetKind::Bitmap,
+            }) if key == "shared"
+        ));
+        assert!(matches!(
+            pak.read_mesh("missing"),
+            Err(PakError::KeyNotFound { key }) if key == "missing"
+        ));
+    }
 }
@@ -0,0 +1,164 @@
+use {
+    super::{
+        BitmapId, BlobId, Data, PakError, Stored,
+        bitmap::{Bitmap, BitmapView},
+        compression::Compression,
+        decode_data, impl_pak, read_header,
+    },
+    log::trace,
+    memmap2::Mmap,
+    serde::Deserialize,
+    std::{
+        borrow::Cow,
+        fmt::{Debug, Formatter},
+        fs::File,
+        io::Cursor,
+        path::Path,
+    },
+};
+
+/// A memory-mapped `.pak` file which reads assets directly from the mapped bytes.
+///
+/// Uncompressed blobs and bitmap pixels may be borrowed from the mapping without copying. Meshes,
+/// along with every other kind of asset, are always decoded into owned values.
+pub struct MappedPak {
+    compression: Option<Compression>,
+    data: Data,
+    dictionary: Option<Vec<u8>>,
+    map: Mmap,
+    verify: bool,
+}
+
+impl MappedPak {
+    pub fn animation_count(&self) -> usize {
+        self.data.anims.len()
+    }
+
+    pub fn bitmap_count(&self) -> usize {
+        self.data.bitmaps.len()
+    }
+
+    pub fn bitmap_font_count(&self) -> usize {
+        self.data.bitmap_fonts.len()
+    }
+
+    pub fn blob_count(&self) -> usize {
+        self.data.blobs.len()
+    }
+
+    /// Returns the stored bytes of one asset, checking them if verification is enabled.
+    fn read_stored(&self, stored: Stored) -> Result<&[u8], PakError> {
+        let Stored { len, pos, .. } = stored;
+
+        trace!("Map data: {len} bytes ({pos}..{})", pos + len as u64);
+
+        let data = usize::try_from(pos)
+            .ok()
+            .and_then(|pos| self.map.get(pos..pos.checked_add(len)?))
+            .ok_or(PakError::TruncatedRange)?;
+
+        if self.verify {
+            stored.verify(data)?;
+        }
+
+        Ok(data)
+    }
+
+    /// Decodes a value which borrows from the mapping; only valid for uncompressed assets.
+    fn deserialize_borrowed<'a, T>(&'a self, data: &'a [u8]) -> Result<T, PakError>
+    where
+        T: Deserialize<'a>,
+    {
+        let (decoded, consumed) =
+            bincode::serde::borrow_decode_from_slice(data, bincode::config::legacy())?;
+
+        if consumed == data.len() {
+            Ok(decoded)
+        } else {
+            Err(PakError::TrailingBytes)
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.data.ids.keys().map(|key| key.as_str())
+    }
+
+    pub fn mesh_count(&self) -> usize {
+        self.data.meshes.len()
+    }
+
+    pub fn material_count(&self) -> usize {
+        self.data.materials.len()
+    }
+
+    /// Maps the given path and decodes the `Pak` header.
+    ///
+    /// The file must not be modified or truncated while it is mapped.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, PakError> {
+        let file = File::open(path)?;
+
+        // SAFETY: Pak files are read-only assets; callers must not modify the file while mapped
+        let map = unsafe { Mmap::map(&file)? };
+        let (header, data) = read_header(Cursor::new(&map[..]))?;
+
+        Ok(Self {
+            compression: header.compression,
+            data,
+            dictionary: header.dictionary,
+            map,
+            verify: false,
+        })
+    }
+
+    /// Reads the corresponding bitmap for the given ID, borrowing the pixels when the bitmap is
+    /// not compressed.
+    pub fn read_bitmap_view_id(&self, id: impl Into<BitmapId>) -> Result<BitmapView<'_>, PakError> {
+        let stored = self.data.stored(id.into(), self.compression)?;
+        let data = self.read_stored(stored)?;
+
+        if stored.compression.is_some() {
+            decode_data::<Bitmap>(stored.compression, self.dictionary.as_deref(), data)
+                .map(BitmapView::from)
+        } else {
+            self.deserialize_borrowed(data)
+        }
+    }
+
+    /// Reads the corresponding blob for the given ID, borrowing the bytes when the blob is not
+    /// compressed.
+    pub fn read_blob_bytes_id(&self, id: impl Into<BlobId>) -> Result<Cow<'_, [u8]>, PakError> {
+        let stored = self.data.stored(id.into(), self.compression)?;
+        let data = self.read_stored(stored)?;
+
+        if stored.compression.is_some() {
+            decode_data::<Vec<u8>>(stored.compression, self.dictionary.as_deref(), data)
+                .map(Cow::Owned)
+        } else {
+            self.deserialize_borrowed::<&[u8]>(data).map(Cow::Borrowed)
+        }
+    }
+
+    pub fn scene_count(&self) -> usize {
+        self.data.scenes.len()
+    }
+
+    /// Sets whether each asset is checked against its stored checksum when read.
+    ///
+    /// Paks written before checksums were stored are read without verification.
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+}
+
+impl Debug for MappedPak {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MappedPak")
+            .field("compression", &self.compression)
+            .field("data", &self.data)
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
+
+impl_pak!(MappedPak);
@@ -147,6 +147,7 @@ impl Primitive {
         buf_len / stride
     }
 
+    /// Returns the interleaved vertices described by `vertex_type`, stored little-endian.
     pub fn vertex_data(&self) -> &[u8] {
         &self.vertex_buf
     }
@@ -0,0 +1,495 @@
+use {
+    super::{
+        AnimationId, AssetKind, BitmapFontId, BitmapId, BlobId, MaterialId, MaterialInfo, MeshId,
+        Pak, PakBuf, PakError, SceneId, Stored, StoredId, anim::Animation, bitmap::Bitmap,
+        bitmap_font::BitmapFont, decode_data, format::Header, mesh::Mesh, scene::Scene,
+    },
+    log::trace,
+    std::path::Path,
+};
+
+#[cfg(feature = "bake")]
+use {
+    super::{
+        Data, DataRef,
+        format::{self, FORMAT_VERSION},
+        write_hash_trailer,
+    },
+    std::{
+        collections::HashMap,
+        fs::File,
+        io::{BufWriter, Cursor, Error, Seek, Write},
+    },
+};
+
+/// The changes between two bakes of a pak, which may be shipped in place of the newer pak.
+///
+/// Assets whose stored bytes are found in the older pak refer to the older pak instead of being
+/// stored again, so a patch holds only the changed payloads and the updated index. Patches are read
+/// by layering them over the older pak using `PatchedPak`.
+#[cfg(feature = "bake")]
+#[derive(Debug)]
+pub struct PatchPak {
+    buf: Vec<u8>,
+    changed: Vec<String>,
+    data: Data,
+    header: Header,
+    removed: Vec<String>,
+    skip_position: u64,
+}
+
+#[cfg(feature = "bake")]
+impl PatchPak {
+    /// Returns the keys of assets whose stored bytes are included in this patch.
+    ///
+    /// Materials are stored within the index and so are never listed.
+    pub fn changed_keys(&self) -> impl Iterator<Item = &str> {
+        self.changed.iter().map(|key| key.as_str())
+    }
+
+    /// Returns the keys of the older pak which are not found in the newer pak.
+    pub fn removed_keys(&self) -> impl Iterator<Item = &str> {
+        self.removed.iter().map(|key| key.as_str())
+    }
+
+    /// Writes this patch to the given path.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        {
+            let mut writer = BufWriter::new(File::create(path)?);
+            writer.write_all(&self.buf)?;
+
+            let mut header = self.header.clone();
+            format::write_index(&mut header, self.skip_position, &self.data, &mut writer)?;
+            writer.flush()?;
+        }
+
+        write_hash_trailer(path)
+    }
+}
+
+#[cfg(feature = "bake")]
+impl PakBuf {
+    /// Compares two bakes of a pak and returns a patch which layers over `old` to read as `new`.
+    ///
+    /// Each asset of `new` is first compared with the asset stored under the same key in `old`,
+    /// followed by any other asset of the same kind with a matching length and checksum, so assets
+    /// which were renamed or duplicated are not stored again either.
+    pub fn diff(old: &mut Self, new: &mut Self) -> Result<PatchPak, PakError> {
+        let header = Header {
+            base: Some(old.trailer_hash()?),
+            compression: new.compression,
+            dictionary: new.dictionary.clone(),
+            skip: 0,
+            version: FORMAT_VERSION,
+        };
+        let mut buf = Cursor::new(vec![]);
+        let skip_position = header.write(&mut buf)?;
+
+        let same_keys = new
+            .data
+            .ids
+            .iter()
+            .filter_map(|(key, id)| {
+                old.data
+                    .ids
+                    .get(key)
+                    .filter(|old_id| old_id.kind() == id.kind())
+                    .map(|old_id| ((id.kind(), id.index()), old_id.index()))
+            })
+            .collect::<HashMap<_, _>>();
+        let mut diff = Diff {
+            buf: &mut buf,
+            new,
+            old,
+            same_keys,
+        };
+
+        let data = Data {
+            anims: diff.refs(AssetKind::Animation, |data| &data.anims)?,
+            bitmap_fonts: diff.refs(AssetKind::BitmapFont, |data| &data.bitmap_fonts)?,
+            bitmaps: diff.refs(AssetKind::Bitmap, |data| &data.bitmaps)?,
+            blobs: diff.refs(AssetKind::Blob, |data| &data.blobs)?,
+            meshes: diff.refs(AssetKind::Mesh, |data| &data.meshes)?,
+            scenes: diff.refs(AssetKind::Scene, |data| &data.scenes)?,
+            ids: new.data.ids.clone(),
+            materials: new.data.materials.clone(),
+        };
+
+        let changed = data
+            .ids
+            .iter()
+            .filter(|(_, id)| {
+                let idx = id.index();
+
+                match id.kind() {
+                    AssetKind::Animation => is_patched(&data.anims[idx]),
+                    AssetKind::Bitmap => is_patched(&data.bitmaps[idx]),
+                    AssetKind::BitmapFont => is_patched(&data.bitmap_fonts[idx]),
+                    AssetKind::Blob => is_patched(&data.blobs[idx]),
+                    AssetKind::Material => false,
+                    AssetKind::Mesh => is_patched(&data.meshes[idx]),
+                    AssetKind::Scene => is_patched(&data.scenes[idx]),
+                }
+            })
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        let removed = old
+            .data
+            .ids
+            .keys()
+            .filter(|key| !data.ids.contains_key(*key))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        trace!(
+            "Diffed pak: {} changed keys, {} removed keys, {} bytes",
+            changed.len(),
+            removed.len(),
+            buf.get_ref().len()
+        );
+
+        Ok(PatchPak {
+            buf: buf.into_inner(),
+            changed,
+            data,
+            header,
+            removed,
+            skip_position,
+        })
+    }
+}
+
+/// The state of one call to `PakBuf::diff`.
+#[cfg(feature = "bake")]
+struct Diff<'a> {
+    buf: &'a mut Cursor<Vec<u8>>,
+    new: &'a mut PakBuf,
+    old: &'a mut PakBuf,
+    same_keys: HashMap<(AssetKind, usize), usize>,
+}
+
+#[cfg(feature = "bake")]
+impl Diff<'_> {
+    /// Compares the stored bytes of one kind of asset, appending those not found in the older pak.
+    fn refs<T>(
+        &mut self,
+        kind: AssetKind,
+        refs: fn(&Data) -> &Vec<DataRef<T>>,
+    ) -> Result<Vec<DataRef<T>>, PakError> {
+        let old_stored = stored_refs(self.old, refs)?;
+        let new_stored = stored_refs(self.new, refs)?;
+
+        // Index the stored bytes of the older pak by length and checksum
+        let mut checksums = HashMap::<_, Vec<_>>::new();
+        for (idx, stored) in old_stored.iter().enumerate() {
+            let checksum = match stored.checksum {
+                Some(checksum) => checksum,
+                None => crc32fast::hash(&self.old.read_stored(*stored)?),
+            };
+            checksums
+                .entry((stored.len, checksum))
+                .or_default()
+                .push(idx);
+        }
+
+        let mut res = vec![];
+
+        for (idx, stored) in new_stored.into_iter().enumerate() {
+            let data = self.new.read_stored(stored)?;
+            let checksum = crc32fast::hash(&data);
+            let candidates = self
+                .same_keys
+                .get(&(kind, idx))
+                .into_iter()
+                .chain(checksums.get(&(data.len(), checksum)).into_iter().flatten());
+
+            // Stored bytes may only be shared when both paks compress them the same way
+            let same_dictionary = self.old.dictionary == self.new.dictionary;
+            let mut base = None;
+            for old_idx in candidates {
+                let old_stored = old_stored[*old_idx];
+                if old_stored.compression == stored.compression
+                    && (same_dictionary
+                        || !stored
+                            .compression
+                            .is_some_and(|compression| compression.uses_dictionary()))
+                    && old_stored.len == data.len()
+                    && self.old.read_stored(old_stored)? == data
+                {
+                    base = Some(old_stored);
+                    break;
+                }
+            }
+
+            res.push(if let Some(base) = base {
+                trace!("{kind} {idx} found in base ({} bytes)", base.len);
+
+                DataRef::BaseCompressed(
+                    base.pos..base.pos + base.len as u64,
+                    checksum,
+                    stored.compression,
+                )
+            } else {
+                let start = self.buf.position();
+                self.buf.write_all(&data)?;
+
+                trace!("{kind} {idx} changed ({} bytes)", data.len());
+
+                DataRef::Compressed(start..self.buf.position(), checksum, stored.compression)
+            });
+        }
+
+        Ok(res)
+    }
+}
+
+#[cfg(feature = "bake")]
+fn is_patched<T>(data: &DataRef<T>) -> bool {
+    matches!(data, DataRef::Checked(..) | DataRef::Compressed(..))
+}
+
+#[cfg(feature = "bake")]
+fn stored_refs<T>(
+    pak: &PakBuf,
+    refs: fn(&Data) -> &Vec<DataRef<T>>,
+) -> Result<Vec<Stored>, PakError> {
+    refs(&pak.data)
+        .iter()
+        .map(|data| data.stored(pak.compression))
+        .collect()
+}
+
+/// A patch pak layered over the base pak it was made against, which reads as the newer pak.
+///
+/// Assets changed by the patch are read from the patch and every other asset is read from the
+/// base pak, so only the patch needs to be downloaded when a pak is updated.
+#[derive(Debug)]
+pub struct PatchedPak {
+    base: PakBuf,
+    patch: PakBuf,
+}
+
+impl PatchedPak {
+    /// Layers `patch` over `base`, checking that the patch was made against the given base.
+    ///
+    /// Returns `PakError::MissingPatchBase` if `patch` is an ordinary pak instead of a patch.
+    pub fn new(base: PakBuf, patch: PakBuf) -> Result<Self, PakError> {
+        let header = Header::read(&mut patch.reader.open()?)?;
+        let expected = header.base.ok_or(PakError::MissingPatchBase)?;
+        let actual = base.trailer_hash()?;
+        if actual != expected {
+            return Err(PakError::BaseMismatch { expected, actual });
+        }
+
+        trace!("Layering patch over base {expected:016x}");
+
+        Ok(Self { base, patch })
+    }
+
+    pub fn animation_count(&self) -> usize {
+        self.patch.animation_count()
+    }
+
+    pub fn bitmap_count(&self) -> usize {
+        self.patch.bitmap_count()
+    }
+
+    pub fn bitmap_font_count(&self) -> usize {
+        self.patch.bitmap_font_count()
+    }
+
+    pub fn blob_count(&self) -> usize {
+        self.patch.blob_count()
+    }
+
+    #[cfg(feature = "bake")]
+    fn copy_refs<T>(
+        &mut self,
+        refs: fn(&Data) -> &Vec<DataRef<T>>,
+        mut writer: impl Seek + Write,
+    ) -> Result<Vec<DataRef<T>>, PakError> {
+        let stored = refs(&self.patch.data)
+            .iter()
+            .map(|data| data.stored_in(self.patch.compression))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut res = vec![];
+
+        for (stored, base) in stored {
+            let data = self.read_stored(stored, base)?;
+            let start = writer.stream_position()?;
+            writer.write_all(&data)?;
+
+            res.push(DataRef::Compressed(
+                start..writer.stream_position()?,
+                crc32fast::hash(&data),
+                stored.compression,
+            ));
+        }
+
+        Ok(res)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.patch.keys()
+    }
+
+    pub fn material_count(&self) -> usize {
+        self.patch.material_count()
+    }
+
+    pub fn mesh_count(&self) -> usize {
+        self.patch.mesh_count()
+    }
+
+    /// Opens the given base and patch paths and layers the patch over the base.
+    pub fn open(base: impl AsRef<Path>, patch: impl AsRef<Path>) -> Result<Self, PakError> {
+        Self::new(PakBuf::open(base)?, PakBuf::open(patch)?)
+    }
+
+    fn read<I>(&mut self, id: I) -> Result<I::Asset, PakError>
+    where
+        I: StoredId,
+    {
+        let (stored, base) = self
+            .patch
+            .data
+            .data_ref(id)?
+            .stored_in(self.patch.compression)?;
+        let buf = self.read_stored(stored, base)?;
+
+        decode_data(stored.compression, self.patch.dictionary.as_deref(), &buf)
+    }
+
+    fn read_stored(&mut self, stored: Stored, base: bool) -> Result<Vec<u8>, PakError> {
+        if base {
+            self.base.read_stored(stored)
+        } else {
+            self.patch.read_stored(stored)
+        }
+    }
+
+    pub fn scene_count(&self) -> usize {
+        self.patch.scene_count()
+    }
+
+    /// Sets whether each asset is checked against its stored checksum when read.
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.base.verify = verify;
+        self.patch.verify = verify;
+        self
+    }
+
+    /// Applies the patch by writing a standalone pak which no longer requires the base pak.
+    ///
+    /// Applying a patch to the pak it was made against writes the same bytes as the newer pak.
+    #[cfg(feature = "bake")]
+    pub fn write(&mut self, path: impl AsRef<Path>) -> Result<(), PakError> {
+        let path = path.as_ref();
+        {
+            let mut writer = BufWriter::new(File::create(path)?);
+            let mut header = Header {
+                base: None,
+                compression: self.patch.compression,
+                dictionary: self.patch.dictionary.clone(),
+                skip: 0,
+                version: FORMAT_VERSION,
+            };
+            let skip_position = header.write(&mut writer)?;
+
+            // Stored bytes are written in the same order used when baking
+            let data = Data {
+                anims: self.copy_refs(|data| &data.anims, &mut writer)?,
+                bitmaps: self.copy_refs(|data| &data.bitmaps, &mut writer)?,
+                blobs: self.copy_refs(|data| &data.blobs, &mut writer)?,
+                bitmap_fonts: self.copy_refs(|data| &data.bitmap_fonts, &mut writer)?,
+                meshes: self.copy_refs(|data| &data.meshes, &mut writer)?,
+                scenes: self.copy_refs(|data| &data.scenes, &mut writer)?,
+                ids: self.patch.data.ids.clone(),
+                materials: self.patch.data.materials.clone(),
+            };
+
+            format::write_index(&mut header, skip_position, &data, &mut writer)?;
+            writer.flush()?;
+        }
+
+        Ok(write_hash_trailer(path)?)
+    }
+}
+
+impl Pak for PatchedPak {
+    /// Gets the kind of asset stored under the given key, if one exists.
+    fn asset_kind(&self, key: impl AsRef<str>) -> Option<AssetKind> {
+        self.patch.asset_kind(key)
+    }
+
+    /// Gets the pak-unique `AnimationId` corresponding to the given key, if one exists.
+    fn animation_id(&self, key: impl AsRef<str>) -> Option<AnimationId> {
+        self.patch.animation_id(key)
+    }
+
+    /// Gets the pak-unique `BitmapFontId` corresponding to the given key, if one exists.
+    fn bitmap_font_id(&self, key: impl AsRef<str>) -> Option<BitmapFontId> {
+        self.patch.bitmap_font_id(key)
+    }
+
+    /// Gets the pak-unique `BitmapId` corresponding to the given key, if one exists.
+    fn bitmap_id(&self, key: impl AsRef<str>) -> Option<BitmapId> {
+        self.patch.bitmap_id(key)
+    }
+
+    /// Gets the pak-unique `BlobId` corresponding to the given key, if one exists.
+    fn blob_id(&self, key: impl AsRef<str>) -> Option<BlobId> {
+        self.patch.blob_id(key)
+    }
+
+    /// Gets the pak-unique `MaterialId` corresponding to the given key, if one exists.
+    fn material_id(&self, key: impl AsRef<str>) -> Option<MaterialId> {
+        self.patch.material_id(key)
+    }
+
+    /// Gets the pak-unique `MeshId` corresponding to the given key, if one exists.
+    fn mesh_id(&self, key: impl AsRef<str>) -> Option<MeshId> {
+        self.patch.mesh_id(key)
+    }
+
+    /// Gets the pak-unique `SceneId` corresponding to the given key, if one exists.
+    fn scene_id(&self, key: impl AsRef<str>) -> Option<SceneId> {
+        self.patch.scene_id(key)
+    }
+
+    /// Gets the corresponding animation for the given ID.
+    fn read_animation_id(&mut self, id: impl Into<AnimationId>) -> Result<Animation, PakError> {
+        self.read(id.into())
+    }
+
+    /// Reads the corresponding bitmap font for the given ID.
+    fn read_bitmap_font_id(&mut self, id: impl Into<BitmapFontId>) -> Result<BitmapFont, PakError> {
+        self.read(id.into())
+    }
+
+    /// Reads the corresponding bitmap for the given ID.
+    fn read_bitmap_id(&mut self, id: impl Into<BitmapId>) -> Result<Bitmap, PakError> {
+        self.read(id.into())
+    }
+
+    /// Gets the corresponding blob for the given ID.
+    fn read_blob_id(&mut self, id: impl Into<BlobId>) -> Result<Vec<u8>, PakError> {
+        self.read(id.into())
+    }
+
+    /// Gets the material for the given ID.
+    fn read_material_id(&self, id: impl Into<MaterialId>) -> Option<MaterialInfo> {
+        self.patch.read_material_id(id)
+    }
+
+    /// Gets the corresponding mesh for the given ID.
+    fn read_mesh_id(&mut self, id: impl Into<MeshId>) -> Result<Mesh, PakError> {
+        self.read(id.into())
+    }
+
+    /// Gets the corresponding scene for the given ID.
+    fn read_scene_id(&mut self, id: impl Into<SceneId>) -> Result<Scene, PakError> {
+        self.read(id.into())
+    }
+}
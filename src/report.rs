@@ -0,0 +1,360 @@
+use {
+    super::{
+        AssetKind, Compression, Data, DataRef, Id, PakBuf, PakError,
+        anim::Animation,
+        bitmap::{Bitmap, BitmapColor, BitmapFormat},
+        bitmap_font::BitmapFont,
+        decode_data,
+        index::IndexType,
+        mesh::Mesh,
+        scene::Scene,
+    },
+    serde::{Serialize, de::DeserializeOwned},
+    std::{
+        fmt::{Display, Formatter},
+        io::Read,
+        ops::Range,
+    },
+};
+
+/// A description of every keyed asset of a pak, used to track asset budgets.
+///
+/// `Display` prints one asset per line; the report may also be serialized, for instance as JSON.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PakReport {
+    /// Keyed assets, in key order.
+    pub assets: Vec<AssetReport>,
+
+    /// The compression used by the index of the pak.
+    pub compression: Option<Compression>,
+}
+
+impl Display for PakReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.compression {
+            Some(compression) => writeln!(f, "compression: {compression:?}")?,
+            None => writeln!(f, "compression: none")?,
+        }
+
+        for asset in &self.assets {
+            write!(f, "{}\t{}", asset.key, asset.kind)?;
+
+            match &asset.range {
+                Some(range) if asset.base => write!(f, "\tbase {}..{}", range.start, range.end)?,
+                Some(range) => write!(f, "\t{}..{}", range.start, range.end)?,
+                None => f.write_str("\t-")?,
+            }
+
+            match (asset.stored_len, asset.len, asset.ratio()) {
+                (Some(stored_len), Some(len), Some(ratio)) => {
+                    write!(f, "\t{stored_len}/{len} bytes ({:.1}%)", ratio * 100.0)?
+                }
+                (Some(stored_len), ..) => write!(f, "\t{stored_len} bytes")?,
+                _ => f.write_str("\t-")?,
+            }
+
+            // Only compression which differs from the pak compression is shown
+            if asset.kind != AssetKind::Material && asset.compression != self.compression {
+                match asset.compression {
+                    Some(compression) => write!(f, " {compression:?}")?,
+                    None => f.write_str(" uncompressed")?,
+                }
+            }
+
+            if let Some(details) = &asset.details {
+                write!(f, "\t{details}")?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A description of one keyed asset of a pak.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AssetReport {
+    /// `true` if the asset is stored in the base pak of a patch, in which case `range` refers to
+    /// the base pak and `len` and `details` are not known.
+    pub base: bool,
+
+    /// The compression the asset was stored with.
+    pub compression: Option<Compression>,
+
+    /// Details which depend on the kind of asset; blobs and materials have none.
+    pub details: Option<AssetDetails>,
+
+    pub key: String,
+    pub kind: AssetKind,
+
+    /// The number of bytes of the asset after decompression.
+    pub len: Option<usize>,
+
+    /// The byte range of the stored asset; materials are stored within the index and have none.
+    pub range: Option<Range<u64>>,
+
+    /// The number of bytes of the asset as stored, after compression.
+    pub stored_len: Option<usize>,
+}
+
+impl AssetReport {
+    /// Returns the stored length as a fraction of the decompressed length.
+    pub fn ratio(&self) -> Option<f64> {
+        match (self.stored_len, self.len) {
+            (Some(stored_len), Some(len)) if len > 0 => Some(stored_len as f64 / len as f64),
+            _ => None,
+        }
+    }
+}
+
+/// Details of one asset which depend on its kind.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetDetails {
+    Animation {
+        channels: usize,
+    },
+    Bitmap {
+        color: BitmapColor,
+        format: BitmapFormat,
+        height: u32,
+        mip_levels: u32,
+        width: u32,
+    },
+    BitmapFont {
+        pages: usize,
+    },
+    Mesh {
+        primitives: Vec<PrimitiveReport>,
+        skinned: bool,
+    },
+    Scene {
+        geometries: usize,
+        refs: usize,
+    },
+}
+
+impl Display for AssetDetails {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Animation { channels } => write!(f, "{channels} channels"),
+            Self::Bitmap {
+                color,
+                format,
+                height,
+                mip_levels,
+                width,
+            } => write!(
+                f,
+                "{width}x{height} {format:?} {color:?}, {mip_levels} mip levels"
+            ),
+            Self::BitmapFont { pages } => write!(f, "{pages} pages"),
+            Self::Mesh {
+                primitives,
+                skinned,
+            } => {
+                write!(f, "{} primitives", primitives.len())?;
+
+                if *skinned {
+                    f.write_str(" (skinned)")?;
+                }
+
+                for primitive in primitives {
+                    write!(
+                        f,
+                        "; {} vertices, {:?} triangles",
+                        primitive.vertex_count, primitive.lod_triangles
+                    )?;
+
+                    if let Some(index_type) = primitive.index_type {
+                        write!(f, " ({index_type:?} indices)")?;
+                    }
+                }
+
+                Ok(())
+            }
+            Self::Scene { geometries, refs } => write!(f, "{geometries} geometries, {refs} refs"),
+        }
+    }
+}
+
+/// A description of one primitive of a mesh.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PrimitiveReport {
+    /// The index type of the first level of detail, if there is one.
+    pub index_type: Option<IndexType>,
+
+    /// The number of triangles of each level of detail.
+    pub lod_triangles: Vec<usize>,
+
+    pub material: u8,
+    pub vertex_count: usize,
+}
+
+impl PakBuf {
+    /// Describes the stored range, size and contents of every keyed asset.
+    ///
+    /// Each stored asset is read and decompressed in order to find its size and details.
+    pub fn report(&mut self) -> Result<PakReport, PakError> {
+        let ids = self
+            .data
+            .ids
+            .iter()
+            .map(|(key, id)| (key.clone(), *id))
+            .collect::<Vec<_>>();
+        let mut assets = Vec::with_capacity(ids.len());
+
+        for (key, id) in ids {
+            let asset = match id {
+                Id::Animation(id) => self.report_asset(
+                    key,
+                    id.0,
+                    |data| &data.anims,
+                    |anim: Animation| {
+                        Some(AssetDetails::Animation {
+                            channels: anim.channels().len(),
+                        })
+                    },
+                )?,
+                Id::Bitmap(id) => self.report_asset(
+                    key,
+                    id.0,
+                    |data| &data.bitmaps,
+                    |bitmap: Bitmap| {
+                        Some(AssetDetails::Bitmap {
+                            color: bitmap.color(),
+                            format: bitmap.format(),
+                            height: bitmap.height(),
+                            mip_levels: bitmap.mip_levels(),
+                            width: bitmap.width(),
+                        })
+                    },
+                )?,
+                Id::BitmapFont(id) => self.report_asset(
+                    key,
+                    id.0,
+                    |data| &data.bitmap_fonts,
+                    |font: BitmapFont| {
+                        Some(AssetDetails::BitmapFont {
+                            pages: font.pages().len(),
+                        })
+                    },
+                )?,
+                Id::Blob(id) => {
+                    self.report_asset(key, id.0, |data| &data.blobs, |_: Vec<u8>| None)?
+                }
+                Id::Material(_) => AssetReport {
+                    base: false,
+                    compression: None,
+                    details: None,
+                    key,
+                    kind: AssetKind::Material,
+                    len: None,
+                    range: None,
+                    stored_len: None,
+                },
+                Id::Mesh(id) => self.report_asset(
+                    key,
+                    id.0,
+                    |data| &data.meshes,
+                    |mesh: Mesh| {
+                        Some(AssetDetails::Mesh {
+                            primitives: mesh
+                                .primitives()
+                                .iter()
+                                .map(|primitive| PrimitiveReport {
+                                    index_type: primitive
+                                        .lods()
+                                        .first()
+                                        .map(|lod| lod.index_type()),
+                                    lod_triangles: primitive
+                                        .lods()
+                                        .iter()
+                                        .map(|lod| lod.index_count() / 3)
+                                        .collect(),
+                                    material: primitive.material(),
+                                    vertex_count: primitive.vertex_count(),
+                                })
+                                .collect(),
+                            skinned: mesh.skin().is_some(),
+                        })
+                    },
+                )?,
+                Id::Scene(id) => self.report_asset(
+                    key,
+                    id.0,
+                    |data| &data.scenes,
+                    |scene: Scene| {
+                        Some(AssetDetails::Scene {
+                            geometries: scene.geometries().len(),
+                            refs: scene.refs().len(),
+                        })
+                    },
+                )?,
+            };
+
+            assets.push(asset);
+        }
+
+        Ok(PakReport {
+            assets,
+            compression: self.compression,
+        })
+    }
+
+    fn report_asset<T>(
+        &mut self,
+        key: String,
+        idx: usize,
+        refs: fn(&Data) -> &Vec<DataRef<T>>,
+        details: impl FnOnce(T) -> Option<AssetDetails>,
+    ) -> Result<AssetReport, PakError>
+    where
+        T: DeserializeOwned,
+    {
+        let kind = self.data.ids[&key].kind();
+        let data_ref = refs(&self.data)
+            .get(idx)
+            .ok_or(PakError::InvalidId { kind, id: idx })?;
+        let (stored, base) = data_ref.stored_in(self.compression)?;
+        let range = stored.pos..stored.pos + stored.len as u64;
+
+        if base {
+            return Ok(AssetReport {
+                base,
+                compression: stored.compression,
+                details: None,
+                key,
+                kind,
+                len: None,
+                range: Some(range),
+                stored_len: Some(stored.len),
+            });
+        }
+
+        let buf = self.read_stored(stored)?;
+        let buf = match stored.compression {
+            Some(compression) => {
+                let mut decompressed = vec![];
+                compression
+                    .new_reader(buf.as_slice(), self.dictionary.as_deref())
+                    .and_then(|mut reader| reader.read_to_end(&mut decompressed))
+                    .map_err(PakError::Decompression)?;
+                decompressed
+            }
+            None => buf,
+        };
+
+        Ok(AssetReport {
+            base,
+            compression: stored.compression,
+            details: details(decode_data(None, None, &buf)?),
+            key,
+            kind,
+            len: Some(buf.len()),
+            range: Some(range),
+            stored_len: Some(stored.len),
+        })
+    }
+}
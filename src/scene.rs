@@ -245,6 +245,14 @@ impl GeometryRef<'_> {
         }
     }
 
+    /// Returns an `Iterator` of the keys of all data, in sorted order.
+    pub fn data_keys(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.geometry()
+            .data
+            .iter()
+            .map(move |(key, _)| self.scene.str(*key))
+    }
+
     /// Returns `true` if the geometry contains the given tag.
     pub fn has_tag(&self, tag: &str) -> bool {
         self.geometry()
@@ -284,9 +292,21 @@ impl GeometryRef<'_> {
             .map(move |idx| self.scene.str(*idx))
     }
 
+    /// Returns packed XYZ position values as little-endian `f32` bytes.
     pub fn vertex_data(&self) -> &[u8] {
         &self.geometry().vertex_buf
     }
+
+    /// Returns an `Iterator` of XYZ positions decoded from `vertex_data`.
+    pub fn vertices(&self) -> impl ExactSizeIterator<Item = Vec3> + '_ {
+        self.vertex_data().chunks_exact(12).map(|vertex| {
+            [
+                f32::from_le_bytes(vertex[0..4].try_into().unwrap()),
+                f32::from_le_bytes(vertex[4..8].try_into().unwrap()),
+                f32::from_le_bytes(vertex[8..12].try_into().unwrap()),
+            ]
+        })
+    }
 }
 
 /// An `Iterator` of [`Geometry`] items.
@@ -433,6 +453,19 @@ impl Scene {
         }
     }
 
+    /// Shifts the material and mesh IDs of every reference by the given amounts.
+    pub(crate) fn offset_ids(&mut self, materials: usize, meshes: usize) {
+        for reference in &mut self.references {
+            for material in &mut reference.materials {
+                material.0 += materials;
+            }
+
+            if let Some(mesh) = &mut reference.mesh {
+                mesh.0 += meshes;
+            }
+        }
+    }
+
     /// Gets an iterator of the `Reference` items stored in this `Scene`.
     pub fn refs(&self) -> impl ExactSizeIterator<Item = ReferenceRef<'_>> {
         ReferenceIter {
@@ -473,6 +506,14 @@ impl ReferenceRef<'_> {
         }
     }
 
+    /// Returns an `Iterator` of the keys of all data, in sorted order.
+    pub fn data_keys(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.reference()
+            .data
+            .iter()
+            .map(move |(key, _)| self.scene.str(*key))
+    }
+
     /// Returns `true` if the ref contains the given tag.
     pub fn has_tag(&self, tag: &str) -> bool {
         self.reference()
@@ -630,4 +671,28 @@ mod test {
         assert!(geometry.has_tag("z"));
         assert!(geometry.has_tag("a"));
     }
+
+    #[test]
+    fn geometry_vertices_are_little_endian() {
+        let scene = Scene::new(
+            [GeometryData {
+                data: vec![],
+                id: None,
+                indices: vec![0, 1, 2],
+                vertices: vec![
+                    0x00, 0x00, 0x80, 0x3f, // 1.0
+                    0x00, 0x00, 0x00, 0xc0, // -2.0
+                    0x00, 0x00, 0x00, 0x3f, // 0.5
+                ],
+                rotation: [0.0, 0.0, 0.0, 1.0],
+                tags: vec![],
+                translation: [0.0, 0.0, 0.0],
+            }],
+            [],
+        )
+        .unwrap();
+        let geometry = scene.geometries().next().unwrap();
+
+        assert_eq!(geometry.vertices().collect::<Vec<_>>(), [[1.0, -2.0, 0.5]]);
+    }
 }
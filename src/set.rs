@@ -0,0 +1,270 @@
+use {
+    super::{
+        AnimationId, AssetKind, BitmapFontId, BitmapId, BlobId, Id, MaterialId, MaterialInfo,
+        MeshId, Pak, PakBuf, PakError, SceneId, anim::Animation, bitmap::Bitmap,
+        bitmap_font::BitmapFont, mesh::Mesh, scene::Scene,
+    },
+    log::trace,
+    std::collections::BTreeMap,
+};
+
+/// An ordered list of mounted paks which are read as if they were one pak.
+///
+/// Keys of later mounts shadow the same keys of earlier mounts, which allows DLC, mod and patch
+/// paks to replace assets of a base pak. The IDs of each mounted pak are namespaced by placing
+/// them after the IDs of every earlier mount, so IDs returned by a `PakSet` (including those found
+/// inside materials, meshes and scenes) are only valid for that `PakSet`.
+#[derive(Debug, Default)]
+pub struct PakSet {
+    ids: BTreeMap<String, Id>,
+    paks: Vec<PakBuf>,
+}
+
+impl PakSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn animation_count(&self) -> usize {
+        self.count(AssetKind::Animation)
+    }
+
+    pub fn bitmap_count(&self) -> usize {
+        self.count(AssetKind::Bitmap)
+    }
+
+    pub fn bitmap_font_count(&self) -> usize {
+        self.count(AssetKind::BitmapFont)
+    }
+
+    pub fn blob_count(&self) -> usize {
+        self.count(AssetKind::Blob)
+    }
+
+    fn count(&self, kind: AssetKind) -> usize {
+        self.paks.iter().map(|pak| Self::pak_count(pak, kind)).sum()
+    }
+
+    /// Returns the first set-wide ID of the given kind which belongs to the mount at `idx`.
+    fn first_id(&self, idx: usize, kind: AssetKind) -> usize {
+        self.paks[..idx]
+            .iter()
+            .map(|pak| Self::pak_count(pak, kind))
+            .sum()
+    }
+
+    /// Returns the set-wide equivalent of an ID read from the mount at `idx`.
+    fn global_id(&self, idx: usize, id: Id) -> Id {
+        let first_id = |kind| self.first_id(idx, kind);
+
+        match id {
+            Id::Animation(id) => AnimationId(id.0 + first_id(AssetKind::Animation)).into(),
+            Id::Bitmap(id) => BitmapId(id.0 + first_id(AssetKind::Bitmap)).into(),
+            Id::BitmapFont(id) => BitmapFontId(id.0 + first_id(AssetKind::BitmapFont)).into(),
+            Id::Blob(id) => BlobId(id.0 + first_id(AssetKind::Blob)).into(),
+            Id::Material(id) => MaterialId(id.0 + first_id(AssetKind::Material)).into(),
+            Id::Mesh(id) => MeshId(id.0 + first_id(AssetKind::Mesh)).into(),
+            Id::Scene(id) => SceneId(id.0 + first_id(AssetKind::Scene)).into(),
+        }
+    }
+
+    /// Returns an iterator of the merged keys of every mounted pak.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.ids.keys().map(|key| key.as_str())
+    }
+
+    /// Returns the index of the mount which holds the given set-wide ID and the ID within it.
+    fn locate(&self, kind: AssetKind, id: usize) -> Result<(usize, usize), PakError> {
+        let mut first_id = 0;
+
+        for (idx, pak) in self.paks.iter().enumerate() {
+            let count = Self::pak_count(pak, kind);
+            if id < first_id + count {
+                return Ok((idx, id - first_id));
+            }
+
+            first_id += count;
+        }
+
+        Err(PakError::InvalidId { kind, id })
+    }
+
+    pub fn material_count(&self) -> usize {
+        self.count(AssetKind::Material)
+    }
+
+    pub fn mesh_count(&self) -> usize {
+        self.count(AssetKind::Mesh)
+    }
+
+    /// Mounts a pak after all previously mounted paks, shadowing any keys they share.
+    pub fn mount(&mut self, pak: PakBuf) -> &mut Self {
+        let idx = self.paks.len();
+        self.paks.push(pak);
+
+        let ids = self.paks[idx]
+            .data
+            .ids
+            .iter()
+            .map(|(key, id)| (key.clone(), self.global_id(idx, *id)))
+            .collect::<Vec<_>>();
+
+        trace!("Mounting pak {idx} ({} keys)", ids.len());
+
+        self.ids.extend(ids);
+
+        self
+    }
+
+    /// Returns the number of mounted paks.
+    pub fn mount_count(&self) -> usize {
+        self.paks.len()
+    }
+
+    fn pak_count(pak: &PakBuf, kind: AssetKind) -> usize {
+        match kind {
+            AssetKind::Animation => pak.data.anims.len(),
+            AssetKind::Bitmap => pak.data.bitmaps.len(),
+            AssetKind::BitmapFont => pak.data.bitmap_fonts.len(),
+            AssetKind::Blob => pak.data.blobs.len(),
+            AssetKind::Material => pak.data.materials.len(),
+            AssetKind::Mesh => pak.data.meshes.len(),
+            AssetKind::Scene => pak.data.scenes.len(),
+        }
+    }
+
+    pub fn scene_count(&self) -> usize {
+        self.count(AssetKind::Scene)
+    }
+}
+
+impl FromIterator<PakBuf> for PakSet {
+    fn from_iter<I: IntoIterator<Item = PakBuf>>(iter: I) -> Self {
+        let mut res = Self::default();
+
+        for pak in iter {
+            res.mount(pak);
+        }
+
+        res
+    }
+}
+
+impl Pak for PakSet {
+    /// Gets the kind of asset stored under the given key, if one exists.
+    fn asset_kind(&self, key: impl AsRef<str>) -> Option<AssetKind> {
+        self.ids.get(key.as_ref()).map(Id::kind)
+    }
+
+    /// Gets the set-wide `AnimationId` corresponding to the given key, if one exists.
+    fn animation_id(&self, key: impl AsRef<str>) -> Option<AnimationId> {
+        self.ids.get(key.as_ref()).and_then(|id| id.as_animation())
+    }
+
+    /// Gets the set-wide `BitmapFontId` corresponding to the given key, if one exists.
+    fn bitmap_font_id(&self, key: impl AsRef<str>) -> Option<BitmapFontId> {
+        self.ids
+            .get(key.as_ref())
+            .and_then(|id| id.as_bitmap_font())
+    }
+
+    /// Gets the set-wide `BitmapId` corresponding to the given key, if one exists.
+    fn bitmap_id(&self, key: impl AsRef<str>) -> Option<BitmapId> {
+        self.ids.get(key.as_ref()).and_then(|id| id.as_bitmap())
+    }
+
+    /// Gets the set-wide `BlobId` corresponding to the given key, if one exists.
+    fn blob_id(&self, key: impl AsRef<str>) -> Option<BlobId> {
+        self.ids.get(key.as_ref()).and_then(|id| id.as_blob())
+    }
+
+    /// Gets the set-wide `MaterialId` corresponding to the given key, if one exists.
+    fn material_id(&self, key: impl AsRef<str>) -> Option<MaterialId> {
+        self.ids.get(key.as_ref()).and_then(|id| id.as_material())
+    }
+
+    /// Gets the set-wide `MeshId` corresponding to the given key, if one exists.
+    fn mesh_id(&self, key: impl AsRef<str>) -> Option<MeshId> {
+        self.ids.get(key.as_ref()).and_then(|id| id.as_mesh())
+    }
+
+    /// Gets the set-wide `SceneId` corresponding to the given key, if one exists.
+    fn scene_id(&self, key: impl AsRef<str>) -> Option<SceneId> {
+        self.ids.get(key.as_ref()).and_then(|id| id.as_scene())
+    }
+
+    /// Gets the corresponding animation for the given ID.
+    fn read_animation_id(&mut self, id: impl Into<AnimationId>) -> Result<Animation, PakError> {
+        let (idx, id) = self.locate(AssetKind::Animation, id.into().0)?;
+
+        self.paks[idx].read_animation_id(AnimationId(id))
+    }
+
+    /// Reads the corresponding bitmap font for the given ID.
+    fn read_bitmap_font_id(&mut self, id: impl Into<BitmapFontId>) -> Result<BitmapFont, PakError> {
+        let (idx, id) = self.locate(AssetKind::BitmapFont, id.into().0)?;
+
+        self.paks[idx].read_bitmap_font_id(BitmapFontId(id))
+    }
+
+    /// Reads the corresponding bitmap for the given ID.
+    fn read_bitmap_id(&mut self, id: impl Into<BitmapId>) -> Result<Bitmap, PakError> {
+        let (idx, id) = self.locate(AssetKind::Bitmap, id.into().0)?;
+
+        self.paks[idx].read_bitmap_id(BitmapId(id))
+    }
+
+    /// Gets the corresponding blob for the given ID.
+    fn read_blob_id(&mut self, id: impl Into<BlobId>) -> Result<Vec<u8>, PakError> {
+        let (idx, id) = self.locate(AssetKind::Blob, id.into().0)?;
+
+        self.paks[idx].read_blob_id(BlobId(id))
+    }
+
+    /// Gets the material for the given handle, if one exists.
+    fn read_material_id(&self, id: impl Into<MaterialId>) -> Option<MaterialInfo> {
+        let (idx, id) = self.locate(AssetKind::Material, id.into().0).ok()?;
+        let mut material = self.paks[idx].read_material_id(MaterialId(id))?;
+
+        // Bitmaps referenced by the material are namespaced the same way as the material
+        let first_bitmap = self.first_id(idx, AssetKind::Bitmap);
+        material.color.0 += first_bitmap;
+        for bitmap in [
+            &mut material.emissive,
+            &mut material.normal,
+            &mut material.params,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            bitmap.0 += first_bitmap;
+        }
+
+        Some(material)
+    }
+
+    /// Gets the corresponding mesh for the given ID.
+    fn read_mesh_id(&mut self, id: impl Into<MeshId>) -> Result<Mesh, PakError> {
+        let (idx, id) = self.locate(AssetKind::Mesh, id.into().0)?;
+        let mut mesh = self.paks[idx].read_mesh_id(MeshId(id))?;
+
+        if let Some(data) = mesh.data() {
+            mesh.set_data(BlobId(data.0 + self.first_id(idx, AssetKind::Blob)));
+        }
+
+        Ok(mesh)
+    }
+
+    /// Gets the corresponding scene for the given ID.
+    fn read_scene_id(&mut self, id: impl Into<SceneId>) -> Result<Scene, PakError> {
+        let (idx, id) = self.locate(AssetKind::Scene, id.into().0)?;
+        let mut scene = self.paks[idx].read_scene_id(SceneId(id))?;
+
+        scene.offset_ids(
+            self.first_id(idx, AssetKind::Material),
+            self.first_id(idx, AssetKind::Mesh),
+        );
+
+        Ok(scene)
+    }
+}
@@ -0,0 +1,153 @@
+use {
+    super::{
+        Data, PakBuf, PakError, Stored, Stream, compression::Compression, impl_pak,
+        range_read_error,
+    },
+    log::trace,
+    std::{
+        fmt::{Debug, Formatter},
+        io::{Error, Read, Seek, SeekFrom},
+        path::Path,
+        sync::{Mutex, MutexGuard},
+    },
+};
+
+/// A thread-safe `.pak` reader which supports concurrent reads through a shared reference.
+///
+/// Each read borrows an idle reader from a pool, or opens a new one using `Stream::open`, so
+/// many threads may read from the same `SharedPak` at once. `Pak` is implemented for both
+/// `SharedPak` and `&SharedPak`.
+pub struct SharedPak {
+    compression: Option<Compression>,
+    data: Data,
+    dictionary: Option<Vec<u8>>,
+    readers: Mutex<Vec<Box<dyn Stream>>>,
+
+    /// The stream the pak was opened with, which is only used to open readers and is locked
+    /// separately so that opening a reader does not block reads using idle readers.
+    source: Mutex<Box<dyn Stream>>,
+
+    verify: bool,
+}
+
+impl SharedPak {
+    pub fn animation_count(&self) -> usize {
+        self.data.anims.len()
+    }
+
+    pub fn bitmap_count(&self) -> usize {
+        self.data.bitmaps.len()
+    }
+
+    pub fn bitmap_font_count(&self) -> usize {
+        self.data.bitmap_fonts.len()
+    }
+
+    pub fn blob_count(&self) -> usize {
+        self.data.blobs.len()
+    }
+
+    /// Reads the stored bytes of one asset, checking them if verification is enabled.
+    fn read_stored(&self, stored: Stored) -> Result<Vec<u8>, PakError> {
+        let Stored { len, pos, .. } = stored;
+
+        trace!("Read data: {len} bytes ({pos}..{})", pos + len as u64);
+
+        let mut reader = self.take_reader()?;
+
+        // Create a zero-filled buffer
+        let mut buf = vec![0; len];
+
+        // Read the data into our buffer; readers which fail are dropped instead of being reused
+        reader.seek(SeekFrom::Start(pos))?;
+        reader.read_exact(&mut buf).map_err(range_read_error)?;
+
+        lock(&self.readers).push(reader);
+
+        if self.verify {
+            stored.verify(&buf)?;
+        }
+
+        Ok(buf)
+    }
+
+    pub fn from_stream(stream: impl Stream + 'static) -> Result<Self, PakError> {
+        PakBuf::from_stream(stream).map(Self::from)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.data.ids.keys().map(|key| key.as_str())
+    }
+
+    pub fn mesh_count(&self) -> usize {
+        self.data.meshes.len()
+    }
+
+    pub fn material_count(&self) -> usize {
+        self.data.materials.len()
+    }
+
+    /// Opens the given path and decodes a `Pak`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, PakError> {
+        PakBuf::open(path).map(Self::from)
+    }
+
+    pub fn scene_count(&self) -> usize {
+        self.data.scenes.len()
+    }
+
+    /// Sets whether each asset is checked against its stored checksum when read.
+    ///
+    /// Paks written before checksums were stored are read without verification.
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Takes an idle reader from the pool, or opens a new one once the pool is unlocked.
+    fn take_reader(&self) -> Result<Box<dyn Stream>, Error> {
+        let reader = lock(&self.readers).pop();
+        match reader {
+            Some(reader) => Ok(reader),
+            None => lock(&self.source).open(),
+        }
+    }
+
+    pub fn validate_hash(&self) -> Result<bool, PakError> {
+        let reader = lock(&self.source).open()?;
+
+        PakBuf::validate_stream_hash(reader)
+    }
+}
+
+impl Debug for SharedPak {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedPak")
+            .field("compression", &self.compression)
+            .field("data", &self.data)
+            .finish_non_exhaustive()
+    }
+}
+
+impl From<PakBuf> for SharedPak {
+    fn from(pak: PakBuf) -> Self {
+        Self {
+            compression: pak.compression,
+            data: pak.data,
+            dictionary: pak.dictionary,
+            readers: Mutex::new(vec![]),
+            source: Mutex::new(pak.reader),
+            verify: pak.verify,
+        }
+    }
+}
+
+impl_pak!(&SharedPak);
+impl_pak!(SharedPak);
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // Neither the pool nor the source holds invariants which a panicking reader could break
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
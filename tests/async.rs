@@ -0,0 +1,184 @@
+#[cfg(all(feature = "async", feature = "bake"))]
+use {
+    pak::{
+        AnimationId, AssetKind, AsyncPak, AsyncPakBuf, BitmapId, BlobId, Compression, MeshId, Pak,
+        PakBuf, PakError, SceneId,
+    },
+    std::{fs, io::Error, path::PathBuf, sync::LazyLock},
+    tokio::runtime::Runtime,
+};
+
+#[cfg(all(feature = "async", feature = "bake"))]
+static CARGO_MANIFEST_DIR: LazyLock<PathBuf> =
+    LazyLock::new(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+#[cfg(all(feature = "async", feature = "bake"))]
+static TESTS_DATA_DIR: LazyLock<PathBuf> = LazyLock::new(|| CARGO_MANIFEST_DIR.join("tests/data"));
+
+#[cfg(all(feature = "async", feature = "bake"))]
+fn assert_async_matches_sync(name: &str, content: &str) -> Result<(), Error> {
+    let generated_dir =
+        std::env::temp_dir().join(format!("pak-async-{name}-{}", std::process::id()));
+    fs::create_dir_all(&generated_dir)?;
+
+    let pak_src = generated_dir.join("pak.toml");
+    let pak_dst = generated_dir.join("async.pak");
+    fs::write(
+        &pak_src,
+        format!(
+            "{content}[[content.group]]\nassets = ['/blob/payload.toml', '/scene/scene.toml']\n"
+        ),
+    )?;
+
+    PakBuf::bake_with_dir(&pak_src, &pak_dst, &*TESTS_DATA_DIR).unwrap();
+
+    let mut pak = PakBuf::open(&pak_dst)?;
+    Runtime::new()?.block_on(async {
+        let mut async_pak = AsyncPakBuf::open(&pak_dst).await?;
+
+        assert_eq!(async_pak.keys().count(), pak.keys().count());
+        assert_eq!(async_pak.blob_count(), pak.blob_count());
+        assert_eq!(async_pak.bitmap_count(), pak.bitmap_count());
+        assert_eq!(async_pak.mesh_count(), pak.mesh_count());
+        assert_eq!(async_pak.scene_count(), pak.scene_count());
+
+        for key in pak.keys() {
+            assert_eq!(async_pak.blob_id(key), pak.blob_id(key));
+            assert_eq!(async_pak.bitmap_id(key), pak.bitmap_id(key));
+            assert_eq!(async_pak.material_id(key), pak.material_id(key));
+            assert_eq!(async_pak.mesh_id(key), pak.mesh_id(key));
+            assert_eq!(async_pak.scene_id(key), pak.scene_id(key));
+        }
+
+        for idx in 0..pak.animation_count() {
+            assert_eq!(
+                async_pak.read_animation_id(AnimationId(idx)).await?,
+                pak.read_animation_id(AnimationId(idx))?
+            );
+        }
+
+        for idx in 0..pak.bitmap_count() {
+            assert_eq!(
+                async_pak.read_bitmap_id(BitmapId(idx)).await?,
+                pak.read_bitmap_id(BitmapId(idx))?
+            );
+        }
+
+        for idx in 0..pak.blob_count() {
+            assert_eq!(
+                async_pak.read_blob_id(BlobId(idx)).await?,
+                pak.read_blob_id(BlobId(idx))?
+            );
+        }
+
+        for idx in 0..pak.mesh_count() {
+            assert_eq!(
+                format!("{:?}", async_pak.read_mesh_id(MeshId(idx)).await?),
+                format!("{:?}", pak.read_mesh_id(MeshId(idx))?)
+            );
+        }
+
+        for idx in 0..pak.scene_count() {
+            assert_eq!(
+                format!("{:?}", async_pak.read_scene_id(SceneId(idx)).await?),
+                format!("{:?}", pak.read_scene_id(SceneId(idx))?)
+            );
+        }
+
+        assert_eq!(
+            async_pak.read_blob("blob/payload").await?,
+            b"blob payload\n".to_vec()
+        );
+        assert_eq!(
+            async_pak.read_blob("missing").await.unwrap_err().kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+        assert!(matches!(
+            async_pak.read_bitmap_font("blob/payload").await,
+            Err(PakError::WrongAssetType {
+                expected: AssetKind::BitmapFont,
+                found: AssetKind::Blob,
+                ..
+            })
+        ));
+
+        Ok::<_, Error>(())
+    })?;
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}
+
+#[cfg(all(feature = "async", feature = "bake"))]
+#[test]
+fn async_reads_match_sync_reads() -> Result<(), Error> {
+    assert_async_matches_sync("uncompressed", "")
+}
+
+#[cfg(all(feature = "async", feature = "bake"))]
+#[test]
+fn async_reads_match_sync_reads_with_compression() -> Result<(), Error> {
+    assert_async_matches_sync("snap", "[content]\ncompression = 'snap'\n\n")?;
+    assert_async_matches_sync("brotli", "[content]\ncompression = 'brotli'\n\n")?;
+    assert_async_matches_sync("zstd", "[content]\ncompression = 'zstd'\n\n")?;
+    assert_async_matches_sync("lz4", "[content]\ncompression = 'lz4'\n\n")
+}
+
+#[cfg(all(feature = "async", feature = "bake"))]
+#[test]
+fn async_reads_headers_with_dictionary() -> Result<(), Error> {
+    let generated_dir =
+        std::env::temp_dir().join(format!("pak-async-dictionary-{}", std::process::id()));
+    let asset_dir = generated_dir.join("assets");
+    fs::create_dir_all(&asset_dir)?;
+
+    for idx in 0..64 {
+        fs::write(
+            asset_dir.join(format!("{idx:02}.txt")),
+            format!(
+                "name = enemy_{idx}\nhealth = {}\nloot = potion_{}\n",
+                idx * 10,
+                idx % 3
+            ),
+        )?;
+    }
+
+    let pak_src = generated_dir.join("pak.toml");
+    let pak_dst = generated_dir.join("async.pak");
+    fs::write(
+        &pak_src,
+        "[content]\ncompression = 'zstd'\ndictionary = true\n\n[[content.group]]\nassets = \
+         ['*.txt']\n",
+    )?;
+
+    PakBuf::bake_with_dir(&pak_src, &pak_dst, &asset_dir).unwrap();
+
+    // The trained dictionary makes the header longer than the first read of the stream
+    let mut pak = PakBuf::open(&pak_dst)?;
+    assert!(matches!(
+        pak.compression(),
+        Some(Compression::Zstd {
+            dictionary: true,
+            ..
+        })
+    ));
+
+    Runtime::new()?.block_on(async {
+        let mut async_pak = AsyncPakBuf::open(&pak_dst).await?;
+
+        assert_eq!(async_pak.blob_count(), 64);
+
+        for idx in 0..pak.blob_count() {
+            assert_eq!(
+                async_pak.read_blob_id(BlobId(idx)).await?,
+                pak.read_blob_id(BlobId(idx))?
+            );
+        }
+
+        Ok::<_, Error>(())
+    })?;
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}
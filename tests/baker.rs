@@ -0,0 +1,106 @@
+#[cfg(feature = "bake")]
+use {
+    pak::{Compression, Pak, PakBuf, buf::Baker},
+    std::{
+        fs,
+        sync::{Arc, Mutex},
+    },
+};
+
+#[cfg(feature = "bake")]
+#[test]
+fn baker_settings_apply_to_one_bake() -> anyhow::Result<()> {
+    let generated_dir = std::env::temp_dir().join(format!("pak-baker-{}", std::process::id()));
+    let asset_dir = generated_dir.join("assets");
+    fs::create_dir_all(&asset_dir)?;
+
+    fs::write(asset_dir.join("a.bin"), "first payload\n".repeat(64))?;
+    fs::write(asset_dir.join("b.bin"), "second payload\n".repeat(64))?;
+
+    // The content file lives outside of the asset root
+    let src = generated_dir.join("pak.toml");
+    fs::write(
+        &src,
+        "[content]\ncompression = 'brotli'\n\n[[content.group]]\nassets = ['*.bin']\n",
+    )?;
+
+    let progress = Arc::new(Mutex::new(vec![]));
+    let baker = {
+        let progress = Arc::clone(&progress);
+        Baker::new()
+            .with_asset_dir(&asset_dir)
+            .with_cargo_watches(false)
+            .with_threads(1)
+            .with_progress(move |event| {
+                progress.lock().unwrap().push((
+                    event.path.to_path_buf(),
+                    event.completed,
+                    event.total,
+                ))
+            })
+    };
+
+    let dst = generated_dir.join("default.pak");
+    baker.bake(&src, &dst)?;
+
+    assert_eq!(
+        *progress.lock().unwrap(),
+        [
+            (asset_dir.join("a.bin"), 1, 2),
+            (asset_dir.join("b.bin"), 2, 2)
+        ]
+    );
+
+    let pak = PakBuf::open(&dst)?;
+    assert!(matches!(pak.compression(), Some(Compression::Brotli(_))));
+
+    // Overriding the compression does not change the settings of the first baker
+    let dst = generated_dir.join("uncompressed.pak");
+    baker.clone().with_compression(None).bake(&src, &dst)?;
+
+    let mut pak = PakBuf::open(&dst)?;
+    assert_eq!(pak.compression(), None);
+    assert!(
+        pak.report()?
+            .assets
+            .iter()
+            .all(|asset| asset.compression.is_none())
+    );
+    assert_eq!(
+        pak.read_blob("a.bin")?,
+        "first payload\n".repeat(64).as_bytes()
+    );
+
+    let dst = generated_dir.join("again.pak");
+    baker.bake(&src, &dst)?;
+
+    let pak = PakBuf::open(&dst)?;
+    assert!(matches!(pak.compression(), Some(Compression::Brotli(_))));
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}
+
+#[cfg(feature = "bake")]
+#[test]
+fn baker_defaults_to_content_directory() -> anyhow::Result<()> {
+    let generated_dir =
+        std::env::temp_dir().join(format!("pak-baker-default-{}", std::process::id()));
+    fs::create_dir_all(&generated_dir)?;
+
+    fs::write(generated_dir.join("data.bin"), "payload")?;
+
+    let src = generated_dir.join("pak.toml");
+    let dst = generated_dir.join("default.pak");
+    fs::write(&src, "[content]\n\n[[content.group]]\nassets = ['*.bin']\n")?;
+
+    Baker::default().bake(&src, &dst)?;
+
+    let mut pak = PakBuf::open(&dst)?;
+    assert_eq!(pak.read_blob("data.bin")?, b"payload");
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}
@@ -1,6 +1,6 @@
 #[cfg(feature = "bake")]
 use {
-    pak::{BlobId, Pak, PakBuf},
+    pak::{AssetKind, BlobId, Pak, PakBuf, PakError, SharedPak},
     std::{
         fs,
         io::{Error, Read, Seek, SeekFrom, Write},
@@ -31,7 +31,7 @@ fn bake_blob_asset_toml() -> Result<(), Error> {
     assert!(source_files.contains(&data_dir.join("payload.name.bin.toml")));
     assert!(source_files.contains(&data_dir.join("payload.name.bin")));
 
-    let mut pak = PakBuf::open(&pak_dst)?;
+    let mut pak = PakBuf::open(&pak_dst)?.with_verify(true);
     assert!(pak.validate_hash()?);
     assert_eq!(pak.blob_count(), 2);
     assert_eq!(pak.read_blob("payload")?, b"blob payload\n".to_vec());
@@ -39,6 +39,15 @@ fn bake_blob_asset_toml() -> Result<(), Error> {
         pak.read_blob("payload.name.bin")?,
         b"dotted blob payload\n".to_vec()
     );
+    assert_eq!(pak.asset_kind("payload"), Some(AssetKind::Blob));
+    assert!(matches!(
+        pak.read_bitmap("payload"),
+        Err(PakError::WrongAssetType {
+            expected: AssetKind::Bitmap,
+            found: AssetKind::Blob,
+            ..
+        })
+    ));
 
     fs::remove_file(pak_dst)?;
 
@@ -66,7 +75,7 @@ fn bake_with_dir_resolves_project_rooted_asset_globs() -> Result<(), Error> {
     assert!(source_files.contains(&TESTS_DATA_DIR.join("blob/payload.toml")));
     assert!(source_files.contains(&TESTS_DATA_DIR.join("blob/payload.bin")));
 
-    let mut pak = PakBuf::open(&pak_dst)?;
+    let mut pak = PakBuf::open(&pak_dst)?.with_verify(true);
     assert!(pak.validate_hash()?);
     assert_eq!(pak.blob_count(), 1);
     assert_eq!(pak.read_blob("blob/payload")?, b"blob payload\n".to_vec());
@@ -136,7 +145,7 @@ fn repeated_bakes_assign_stable_blob_ids() -> Result<(), Error> {
         let pak = PakBuf::open(&pak_dst)?;
         for idx in 0..32 {
             assert_eq!(
-                pak.blob_id(&format!("asset_{idx:02}.bin")),
+                pak.blob_id(format!("asset_{idx:02}.bin")),
                 Some(BlobId(idx))
             );
         }
@@ -152,3 +161,49 @@ fn repeated_bakes_assign_stable_blob_ids() -> Result<(), Error> {
     fs::remove_dir_all(generated_dir)?;
     Ok(())
 }
+
+#[cfg(feature = "bake")]
+#[test]
+fn shared_pak_reads_concurrently() -> Result<(), Error> {
+    let generated_dir = std::env::temp_dir().join(format!("pak-shared-{}", std::process::id()));
+    fs::create_dir_all(&generated_dir)?;
+    for idx in 0..8 {
+        fs::write(
+            generated_dir.join(format!("asset_{idx}.bin")),
+            vec![idx as u8; idx * 1024 + 1],
+        )?;
+    }
+    let pak_src = generated_dir.join("pak.toml");
+    let pak_dst = generated_dir.join("blob.pak");
+    fs::write(
+        &pak_src,
+        "[content]\ncompression = 'snap'\n\n[[content.group]]\nassets = ['*.bin']\n",
+    )?;
+
+    PakBuf::bake(&pak_src, &pak_dst).unwrap();
+
+    let pak = SharedPak::open(&pak_dst)?;
+    assert!(pak.validate_hash()?);
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                let mut pak = &pak;
+                for _ in 0..16 {
+                    for idx in 0..8 {
+                        assert_eq!(
+                            pak.read_blob(format!("asset_{idx}.bin")).unwrap(),
+                            vec![idx as u8; idx * 1024 + 1]
+                        );
+                    }
+                }
+            });
+        }
+    });
+
+    let mut pak = PakBuf::open(&pak_dst)?.into_shared();
+    assert_eq!(pak.blob_count(), 8);
+    assert_eq!(pak.read_blob_id(BlobId(0))?, vec![0]);
+
+    fs::remove_dir_all(generated_dir)?;
+    Ok(())
+}
@@ -0,0 +1,100 @@
+#[cfg(feature = "bake")]
+use {
+    pak::{Pak, PakBuf},
+    std::{
+        fs,
+        io::Error,
+        path::{Path, PathBuf},
+        sync::LazyLock,
+    },
+};
+
+#[cfg(feature = "bake")]
+static CARGO_MANIFEST_DIR: LazyLock<PathBuf> =
+    LazyLock::new(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+#[cfg(feature = "bake")]
+static TESTS_DATA_DIR: LazyLock<PathBuf> = LazyLock::new(|| CARGO_MANIFEST_DIR.join("tests/data"));
+
+#[cfg(feature = "bake")]
+fn cache_entries(cache_dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut res = fs::read_dir(cache_dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    res.sort();
+
+    Ok(res)
+}
+
+#[cfg(feature = "bake")]
+fn new_entry(before: &[PathBuf], after: &[PathBuf]) -> PathBuf {
+    let mut added = after.iter().filter(|entry| !before.contains(entry));
+    let res = added.next().expect("bake should add a cache entry").clone();
+    assert!(added.next().is_none());
+
+    res
+}
+
+#[cfg(feature = "bake")]
+#[test]
+fn unchanged_assets_are_read_from_cache() -> Result<(), Error> {
+    let generated_dir = std::env::temp_dir().join(format!("pak-cache-{}", std::process::id()));
+    let asset_dir = generated_dir.join("assets");
+    let cache_dir = generated_dir.join("cache");
+    fs::create_dir_all(&asset_dir)?;
+    fs::copy(
+        TESTS_DATA_DIR.join("scene/cube.glb"),
+        asset_dir.join("cube.glb"),
+    )?;
+    fs::copy(
+        TESTS_DATA_DIR.join("scene/material_01.png"),
+        asset_dir.join("bitmap.png"),
+    )?;
+
+    let pak_src = generated_dir.join("pak.toml");
+    let pak_dst = generated_dir.join("cache.pak");
+    let bake = || PakBuf::bake_with_cache(&pak_src, &pak_dst, &asset_dir, &cache_dir).unwrap();
+
+    fs::write(&pak_src, "[[content.group]]\nassets = ['/cube.glb']\n")?;
+    bake();
+    let mesh_entries = cache_entries(&cache_dir)?;
+    assert_eq!(mesh_entries.len(), 1);
+
+    fs::write(
+        &pak_src,
+        "[[content.group]]\nassets = ['/bitmap.png', '/cube.glb']\n",
+    )?;
+    bake();
+    let entries = cache_entries(&cache_dir)?;
+    let original_entry = new_entry(&mesh_entries, &entries);
+    let original_pak = fs::read(&pak_dst)?;
+    let original_bitmap = PakBuf::open(&pak_dst)?.read_bitmap("bitmap.png")?;
+
+    // Baking again produces the same pak without adding entries
+    bake();
+    assert_eq!(fs::read(&pak_dst)?, original_pak);
+    assert_eq!(cache_entries(&cache_dir)?, entries);
+
+    // Changing a source adds an entry for the new contents
+    fs::copy(
+        TESTS_DATA_DIR.join("scene/material_02.png"),
+        asset_dir.join("bitmap.png"),
+    )?;
+    bake();
+    let changed_entry = new_entry(&entries, &cache_entries(&cache_dir)?);
+    assert_ne!(
+        PakBuf::open(&pak_dst)?.read_bitmap("bitmap.png")?,
+        original_bitmap
+    );
+
+    // Cached values are used instead of baking the source again
+    fs::copy(original_entry, changed_entry)?;
+    bake();
+    assert_eq!(
+        PakBuf::open(&pak_dst)?.read_bitmap("bitmap.png")?,
+        original_bitmap
+    );
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}
@@ -0,0 +1,97 @@
+#[cfg(feature = "cli")]
+use std::{
+    fs,
+    io::Error,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::LazyLock,
+};
+
+#[cfg(feature = "cli")]
+static CARGO_MANIFEST_DIR: LazyLock<PathBuf> =
+    LazyLock::new(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+#[cfg(feature = "cli")]
+static TESTS_DATA_DIR: LazyLock<PathBuf> = LazyLock::new(|| CARGO_MANIFEST_DIR.join("tests/data"));
+
+#[cfg(feature = "cli")]
+fn pak(args: &[&Path]) -> Result<String, Error> {
+    let output = Command::new(env!("CARGO_BIN_EXE_pak"))
+        .args(args)
+        .output()?;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    Ok(String::from_utf8(output.stdout).unwrap())
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn bakes_and_inspects_paks() -> Result<(), Error> {
+    let generated_dir = std::env::temp_dir().join(format!("pak-cli-{}", std::process::id()));
+    fs::create_dir_all(&generated_dir)?;
+    fs::copy(
+        TESTS_DATA_DIR.join("scene/material_01.png"),
+        generated_dir.join("bitmap.png"),
+    )?;
+    fs::write(
+        generated_dir.join("payload.toml"),
+        "[blob]\nsrc = 'payload.bin'\n",
+    )?;
+    fs::write(generated_dir.join("payload.bin"), "cli payload\n")?;
+
+    let src = generated_dir.join("pak.toml");
+    let dst = generated_dir.join("cli.pak");
+    fs::write(
+        &src,
+        "[[content.group]]\nassets = ['/bitmap.png', '/payload.toml']\n",
+    )?;
+
+    pak(&[Path::new("bake"), &src, &dst])?;
+    assert!(pak(&[Path::new("verify"), &dst])?.contains("is valid"));
+
+    let info = pak(&[Path::new("info"), &dst])?;
+    assert!(info.contains("bitmaps: 1"));
+    assert!(info.contains("blobs: 1"));
+
+    let report = pak(&[Path::new("inspect"), &dst])?;
+    assert!(report.contains("bitmap.png\tbitmap\t"));
+
+    let json = pak(&[Path::new("inspect"), &dst, Path::new("--json")])?;
+    assert!(json.contains("\"kind\": \"blob\""));
+
+    let list = pak(&[Path::new("list"), &dst])?;
+    let mut lines = list.lines();
+    assert!(lines.next().unwrap().starts_with("bitmap.png\tbitmap\t"));
+    assert!(lines.next().unwrap().starts_with("payload\tblob\t"));
+    assert!(lines.next().is_none());
+
+    assert_eq!(
+        pak(&[Path::new("extract"), &dst, Path::new("payload")])?,
+        "cli payload\n"
+    );
+
+    let png = generated_dir.join("extracted.png");
+    pak(&[
+        Path::new("extract"),
+        &dst,
+        Path::new("bitmap.png"),
+        Path::new("--output"),
+        &png,
+    ])?;
+    assert!(fs::read(&png)?.starts_with(b"\x89PNG"));
+
+    let unpacked = generated_dir.join("unpacked");
+    pak(&[Path::new("unpack"), &dst, &unpacked])?;
+    assert_eq!(fs::read(unpacked.join("payload"))?, b"cli payload\n");
+    assert!(fs::read(unpacked.join("bitmap.png"))?.starts_with(b"\x89PNG"));
+
+    let sources = pak(&[Path::new("sources"), &src])?;
+    assert!(sources.lines().any(|line| line.ends_with("payload.bin")));
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}
@@ -0,0 +1,256 @@
+#[cfg(feature = "bake")]
+use {
+    pak::{Compression, Pak, PakBuf},
+    std::{fs, path::PathBuf},
+};
+
+#[cfg(feature = "bake")]
+fn bake_payloads(name: &str, content: &str) -> anyhow::Result<PathBuf> {
+    let generated_dir =
+        std::env::temp_dir().join(format!("pak-compression-{name}-{}", std::process::id()));
+    let asset_dir = generated_dir.join("assets");
+    fs::create_dir_all(asset_dir.join("raw"))?;
+
+    fs::write(
+        asset_dir.join("default.bin"),
+        "default payload\n".repeat(64),
+    )?;
+    fs::write(
+        asset_dir.join("raw/stored.bin"),
+        "stored payload\n".repeat(64),
+    )?;
+    fs::write(asset_dir.join("fast.bin"), "fast payload\n".repeat(64))?;
+    fs::write(
+        asset_dir.join("fast.toml"),
+        "[blob]\nsrc = 'fast.bin'\ncompression = 'snap'\n",
+    )?;
+
+    let src = generated_dir.join("pak.toml");
+    let dst = generated_dir.join("compression.pak");
+    fs::write(&src, content)?;
+    PakBuf::bake_with_dir(&src, &dst, &asset_dir)?;
+
+    Ok(dst)
+}
+
+#[cfg(feature = "bake")]
+fn compression_of(pak: &mut PakBuf, key: &str) -> Option<Compression> {
+    let report = pak.report().unwrap();
+
+    report
+        .assets
+        .into_iter()
+        .find(|asset| asset.key == key)
+        .unwrap()
+        .compression
+}
+
+#[cfg(feature = "bake")]
+#[test]
+fn groups_and_assets_choose_compression() -> anyhow::Result<()> {
+    let dst = bake_payloads(
+        "mixed",
+        "[content]\ncompression = 'brotli'\n\n[[content.group]]\nassets = ['/default.bin', \
+         '/fast.toml']\n\n[[content.group]]\nassets = ['/raw/*.bin']\ncompression = 'none'\n",
+    )?;
+    let mut pak = PakBuf::open(&dst)?;
+
+    assert!(matches!(pak.compression(), Some(Compression::Brotli(_))));
+    assert!(matches!(
+        compression_of(&mut pak, "default.bin"),
+        Some(Compression::Brotli(_))
+    ));
+    assert_eq!(compression_of(&mut pak, "raw/stored.bin"), None);
+    assert_eq!(compression_of(&mut pak, "fast"), Some(Compression::Snap));
+
+    // Stored assets are kept as-is, after the serialized length of the blob
+    assert_eq!(pak.stored_len("raw/stored.bin"), Some(15 * 64 + 8));
+
+    assert_eq!(
+        pak.read_blob("default.bin")?,
+        "default payload\n".repeat(64).as_bytes()
+    );
+    assert_eq!(
+        pak.read_blob("raw/stored.bin")?,
+        "stored payload\n".repeat(64).as_bytes()
+    );
+    assert_eq!(
+        pak.read_blob("fast")?,
+        "fast payload\n".repeat(64).as_bytes()
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "bake")]
+#[test]
+fn older_format_versions_require_one_compression() {
+    let err = bake_payloads(
+        "format-version",
+        "[content]\ncompression = 'brotli'\nformat-version = 3\n\n[[content.group]]\nassets = \
+         ['/fast.toml']\n",
+    )
+    .unwrap_err();
+
+    assert!(
+        format!("{err:#}").contains("per-asset compression requires format version 4"),
+        "{err:#}"
+    );
+}
+
+#[cfg(feature = "bake")]
+#[test]
+fn older_format_versions_accept_matching_compression() -> anyhow::Result<()> {
+    let dst = bake_payloads(
+        "matching",
+        "[content]\ncompression = 'snap'\nformat-version = 3\n\n[[content.group]]\nassets = \
+         ['/default.bin', '/fast.toml']\n",
+    )?;
+    let mut pak = PakBuf::open(&dst)?;
+
+    assert_eq!(
+        pak.read_blob("fast")?,
+        "fast payload\n".repeat(64).as_bytes()
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "bake")]
+fn bake_small_blobs(name: &str, content: &str) -> anyhow::Result<PakBuf> {
+    let generated_dir =
+        std::env::temp_dir().join(format!("pak-compression-{name}-{}", std::process::id()));
+    let asset_dir = generated_dir.join("assets");
+    fs::create_dir_all(&asset_dir)?;
+
+    for idx in 0..64 {
+        fs::write(
+            asset_dir.join(format!("{idx:02}.json")),
+            format!(
+                "{{\"name\": \"enemy_{idx}\", \"health\": {}, \"speed\": {}, \"loot\":                  [\"coin\", \"potion_{}\"], \"spawn\": {{\"x\": {}, \"y\": {}}}}}\n",
+                idx * 10,
+                idx % 7,
+                idx % 3,
+                idx * 31 % 97,
+                idx * 17 % 89
+            ),
+        )?;
+    }
+
+    let src = generated_dir.join("pak.toml");
+    let dst = generated_dir.join("compression.pak");
+    fs::write(&src, content)?;
+    PakBuf::bake_with_dir(&src, &dst, &asset_dir)?;
+
+    Ok(PakBuf::open(&dst)?)
+}
+
+#[cfg(feature = "bake")]
+#[test]
+fn dictionary_compresses_small_assets() -> anyhow::Result<()> {
+    let groups = "\n[[content.group]]\nassets = ['*.json']\n";
+    let mut pak = bake_small_blobs(
+        "dictionary",
+        &format!("[content]\ncompression = 'zstd'\ndictionary = true\n{groups}"),
+    )?;
+    let plain = bake_small_blobs(
+        "no-dictionary",
+        &format!("[content]\ncompression = 'zstd'\n{groups}"),
+    )?;
+
+    assert!(matches!(
+        pak.compression(),
+        Some(Compression::Zstd {
+            dictionary: true,
+            ..
+        })
+    ));
+    assert!(
+        pak.read_blob("00.json")?
+            .starts_with(b"{\"name\": \"enemy_0\"")
+    );
+    assert!(
+        pak.read_blob("63.json")?
+            .starts_with(b"{\"name\": \"enemy_63\"")
+    );
+
+    let stored_len = |pak: &PakBuf| {
+        pak.keys()
+            .map(|key| pak.stored_len(key).unwrap())
+            .sum::<usize>()
+    };
+    assert!(stored_len(&pak) < stored_len(&plain));
+
+    Ok(())
+}
+
+#[cfg(feature = "bake")]
+#[test]
+fn dictionary_is_skipped_without_enough_samples() -> anyhow::Result<()> {
+    let dst = bake_payloads(
+        "dictionary-fallback",
+        "[content]\ncompression = 'zstd'\ndictionary = true\n\n[[content.group]]\nassets = \
+         ['/default.bin']\n",
+    )?;
+    let mut pak = PakBuf::open(&dst)?;
+
+    assert!(matches!(
+        pak.compression(),
+        Some(Compression::Zstd {
+            dictionary: false,
+            ..
+        })
+    ));
+    assert_eq!(
+        pak.read_blob("default.bin")?,
+        "default payload\n".repeat(64).as_bytes()
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "bake")]
+#[test]
+fn nested_assets_choose_compression() -> anyhow::Result<()> {
+    let generated_dir =
+        std::env::temp_dir().join(format!("pak-compression-nested-{}", std::process::id()));
+    fs::create_dir_all(&generated_dir)?;
+
+    fs::copy(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/scene/cube.glb"),
+        generated_dir.join("cube.glb"),
+    )?;
+    fs::write(
+        generated_dir.join("fast.toml"),
+        "[mesh]\nsrc = 'cube.glb'\nlod = false\ncompression = 'snap'\n",
+    )?;
+    fs::write(
+        generated_dir.join("plain.toml"),
+        "[mesh]\nsrc = 'cube.glb'\nlod = false\n",
+    )?;
+    fs::write(
+        generated_dir.join("scene.toml"),
+        "[scene]\n\n[[scene.ref]]\nmesh = 'fast.toml'\n\n[[scene.ref]]\nmesh = 'plain.toml'\n",
+    )?;
+
+    let src = generated_dir.join("pak.toml");
+    let dst = generated_dir.join("compression.pak");
+    fs::write(
+        &src,
+        "[content]\ncompression = 'snap'\n\n[[content.group]]\nassets = ['scene.toml']\n\
+         compression = 'brotli'\n",
+    )?;
+    PakBuf::bake(&src, &dst)?;
+    let mut pak = PakBuf::open(&dst)?;
+
+    // Meshes referred to by a scene use the compression of the scene unless they choose their own
+    assert_eq!(compression_of(&mut pak, "fast"), Some(Compression::Snap));
+    assert!(matches!(
+        compression_of(&mut pak, "plain"),
+        Some(Compression::Brotli(_))
+    ));
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+#[cfg(feature = "bake")]
+use {
+    pak::{
+        PakBuf,
+        buf::{BakeFailures, Baker},
+    },
+    std::fs,
+};
+
+#[cfg(feature = "bake")]
+#[test]
+fn keep_going_reports_every_failed_asset() -> anyhow::Result<()> {
+    let generated_dir = std::env::temp_dir().join(format!("pak-keep-going-{}", std::process::id()));
+    let asset_dir = generated_dir.join("assets");
+    fs::create_dir_all(&asset_dir)?;
+
+    fs::write(asset_dir.join("broken_01.png"), "not a png")?;
+    fs::write(asset_dir.join("broken_02.png"), "not a png either")?;
+    fs::write(asset_dir.join("data.bin"), "baked anyway")?;
+    fs::write(
+        asset_dir.join("missing.toml"),
+        "[blob]\nsrc = 'missing.bin'\n",
+    )?;
+
+    let src = generated_dir.join("pak.toml");
+    let dst = generated_dir.join("keep-going.pak");
+    fs::write(
+        &src,
+        "[content]\n\n[[content.group]]\nassets = ['*.png', '*.bin', '*.toml']\n",
+    )?;
+
+    // By default the first failure stops the bake
+    let err = PakBuf::bake_with_dir(&src, &dst, &asset_dir).unwrap_err();
+    assert!(err.downcast_ref::<BakeFailures>().is_none());
+    assert!(format!("{err:#}").contains("broken_01.png"), "{err:#}");
+
+    let err = Baker::new()
+        .with_asset_dir(&asset_dir)
+        .with_keep_going(true)
+        .bake(&src, &dst)
+        .unwrap_err();
+    let failures = err.downcast_ref::<BakeFailures>().unwrap();
+
+    assert_eq!(
+        failures
+            .failures
+            .iter()
+            .map(|failure| failure.path.clone())
+            .collect::<Vec<_>>(),
+        [
+            asset_dir.join("broken_01.png"),
+            asset_dir.join("broken_02.png"),
+            asset_dir.join("missing.toml"),
+        ]
+    );
+    assert!(
+        format!("{err}").starts_with("3 assets failed to bake"),
+        "{err}"
+    );
+    assert!(
+        format!("{err}").contains("Unable to open blob file"),
+        "{err}"
+    );
+    assert!(!dst.exists());
+
+    // The deprecated options are forwarded to `Baker`
+    #[allow(deprecated)]
+    let err = PakBuf::bake_with_options(
+        &src,
+        &dst,
+        &asset_dir,
+        pak::buf::BakeOptions { keep_going: true },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BakeFailures>().unwrap().failures.len(),
+        3
+    );
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}
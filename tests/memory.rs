@@ -0,0 +1,38 @@
+#[cfg(feature = "bake")]
+use {
+    pak::{Pak, PakBuf},
+    std::{fs, io::Cursor, path::PathBuf, sync::LazyLock},
+};
+
+#[cfg(feature = "bake")]
+static CARGO_MANIFEST_DIR: LazyLock<PathBuf> =
+    LazyLock::new(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+#[cfg(feature = "bake")]
+static TESTS_DATA_DIR: LazyLock<PathBuf> = LazyLock::new(|| CARGO_MANIFEST_DIR.join("tests/data"));
+
+#[cfg(feature = "bake")]
+#[test]
+fn bake_to_memory_matches_bake_to_file() -> anyhow::Result<()> {
+    let src = TESTS_DATA_DIR.join("scene/pak.toml");
+
+    let mut buf = Cursor::new(vec![]);
+    PakBuf::bake_to_writer(&src, &mut buf)?;
+    let buf = buf.into_inner();
+
+    let dst = std::env::temp_dir().join(format!("pak-memory-{}.pak", std::process::id()));
+    PakBuf::bake(&src, &dst)?;
+    assert!(fs::read(&dst)? == buf);
+    fs::remove_file(dst)?;
+
+    let mut pak = PakBuf::from_bytes(buf)?;
+    assert!(pak.validate_hash()?);
+
+    let scene = pak.read_scene("scene")?;
+    assert!(
+        scene
+            .refs()
+            .any(|r| r.id() == Some("mesh-with-one-material"))
+    );
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+#[cfg(all(feature = "bake", feature = "mmap"))]
+use {
+    pak::{BitmapId, BlobId, MappedPak, Pak, PakBuf},
+    std::{borrow::Cow, fs, io::Error, path::PathBuf, sync::LazyLock},
+};
+
+#[cfg(all(feature = "bake", feature = "mmap"))]
+static CARGO_MANIFEST_DIR: LazyLock<PathBuf> =
+    LazyLock::new(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+#[cfg(all(feature = "bake", feature = "mmap"))]
+static TESTS_DATA_DIR: LazyLock<PathBuf> = LazyLock::new(|| CARGO_MANIFEST_DIR.join("tests/data"));
+
+#[cfg(all(feature = "bake", feature = "mmap"))]
+fn bake_mapped(name: &str, content: &str) -> Result<(PathBuf, PathBuf), Error> {
+    let generated_dir =
+        std::env::temp_dir().join(format!("pak-mmap-{name}-{}", std::process::id()));
+    fs::create_dir_all(&generated_dir)?;
+
+    let pak_src = generated_dir.join("pak.toml");
+    let pak_dst = generated_dir.join("mapped.pak");
+    fs::write(
+        &pak_src,
+        format!(
+            "{content}[[content.group]]\nassets = ['/blob/payload.toml', '/scene/material_01.png']\n"
+        ),
+    )?;
+
+    PakBuf::bake_with_dir(&pak_src, &pak_dst, &*TESTS_DATA_DIR).unwrap();
+
+    Ok((generated_dir, pak_dst))
+}
+
+#[cfg(all(feature = "bake", feature = "mmap"))]
+#[test]
+fn mapped_uncompressed_pak_borrows_assets() -> Result<(), Error> {
+    let (generated_dir, pak_dst) = bake_mapped("uncompressed", "")?;
+
+    let mut buf = PakBuf::open(&pak_dst)?;
+    let mut mapped = MappedPak::open(&pak_dst)?;
+    assert_eq!(mapped.blob_count(), 1);
+    assert_eq!(mapped.bitmap_count(), 1);
+    assert_eq!(mapped.blob_id("blob/payload"), buf.blob_id("blob/payload"));
+
+    let blob = mapped.read_blob_bytes_id(BlobId(0))?;
+    assert!(matches!(blob, Cow::Borrowed(_)));
+    assert_eq!(&*blob, b"blob payload\n");
+
+    let bitmap = buf.read_bitmap_id(BitmapId(0))?;
+    let view = mapped.read_bitmap_view_id(BitmapId(0))?;
+    assert!(view.is_borrowed());
+    assert_eq!(view.to_bitmap(), bitmap);
+
+    drop(view);
+    drop(blob);
+    assert_eq!(mapped.read_bitmap_id(BitmapId(0))?, bitmap);
+    assert_eq!(
+        mapped.read_blob("blob/payload")?,
+        buf.read_blob("blob/payload")?
+    );
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}
+
+#[cfg(all(feature = "bake", feature = "mmap"))]
+#[test]
+fn mapped_compressed_pak_decodes_assets() -> Result<(), Error> {
+    let (generated_dir, pak_dst) =
+        bake_mapped("compressed", "[content]\ncompression = 'snap'\n\n")?;
+
+    let mut buf = PakBuf::open(&pak_dst)?;
+    let mapped = MappedPak::open(&pak_dst)?;
+
+    // The blob does not become smaller when compressed, so it is stored uncompressed
+    let blob = mapped.read_blob_bytes_id(BlobId(0))?;
+    assert!(matches!(blob, Cow::Borrowed(_)));
+    assert_eq!(&*blob, b"blob payload\n");
+
+    let view = mapped.read_bitmap_view_id(BitmapId(0))?;
+    assert!(!view.is_borrowed());
+    assert_eq!(view.to_bitmap(), buf.read_bitmap_id(BitmapId(0))?);
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}
@@ -0,0 +1,137 @@
+#[cfg(feature = "bake")]
+use {
+    pak::buf::{BakeEvent, Baker},
+    std::{
+        fs,
+        path::PathBuf,
+        sync::{Arc, LazyLock, Mutex},
+    },
+};
+
+#[cfg(feature = "bake")]
+static CARGO_MANIFEST_DIR: LazyLock<PathBuf> =
+    LazyLock::new(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+#[cfg(feature = "bake")]
+static TESTS_DATA_DIR: LazyLock<PathBuf> = LazyLock::new(|| CARGO_MANIFEST_DIR.join("tests/data"));
+
+#[cfg(feature = "bake")]
+#[derive(Debug, PartialEq)]
+enum Event {
+    Started(PathBuf),
+    Finished(PathBuf),
+    CacheHit(PathBuf),
+    Warning(String),
+    Error(PathBuf),
+}
+
+#[cfg(feature = "bake")]
+fn observed_baker(events: &Arc<Mutex<Vec<Event>>>) -> Baker {
+    let events = Arc::clone(events);
+
+    Baker::new().with_observer(move |event: BakeEvent<'_>| {
+        let event = match event {
+            BakeEvent::AssetStarted { path } => Event::Started(path.to_path_buf()),
+            BakeEvent::AssetFinished { path, len, .. } => {
+                assert!(len > 0);
+
+                Event::Finished(path.to_path_buf())
+            }
+            BakeEvent::CacheHit { path } => Event::CacheHit(path.to_path_buf()),
+            BakeEvent::Warning { message } => Event::Warning(message.to_owned()),
+            BakeEvent::Error { path, .. } => Event::Error(path.to_path_buf()),
+            _ => unreachable!(),
+        };
+
+        events.lock().unwrap().push(event);
+    })
+}
+
+#[cfg(feature = "bake")]
+#[test]
+fn observer_receives_bake_events() -> anyhow::Result<()> {
+    let generated_dir = std::env::temp_dir().join(format!("pak-observer-{}", std::process::id()));
+    let asset_dir = generated_dir.join("assets");
+    fs::create_dir_all(&asset_dir)?;
+
+    fs::write(asset_dir.join("data.bin"), "payload")?;
+    fs::copy(
+        TESTS_DATA_DIR.join("scene/material_01.png"),
+        asset_dir.join("image.png"),
+    )?;
+
+    // A single asset is not enough to train a dictionary
+    let src = generated_dir.join("pak.toml");
+    fs::write(
+        &src,
+        "[content]\ncompression = 'zstd'\ndictionary = true\n\n[[content.group]]\nassets = \
+         ['*.bin', '*.png']\n",
+    )?;
+
+    let events = Arc::new(Mutex::new(vec![]));
+    let baker = observed_baker(&events)
+        .with_asset_dir(&asset_dir)
+        .with_cache_dir(generated_dir.join("cache"));
+
+    baker.bake(&src, generated_dir.join("first.pak"))?;
+
+    {
+        let events = events.lock().unwrap();
+        assert_eq!(
+            events[..4],
+            [
+                Event::Started(asset_dir.join("data.bin")),
+                Event::Finished(asset_dir.join("data.bin")),
+                Event::Started(asset_dir.join("image.png")),
+                Event::Finished(asset_dir.join("image.png")),
+            ]
+        );
+        assert!(
+            matches!(&events[4], Event::Warning(message) if message.contains("dictionary")),
+            "{events:?}"
+        );
+    }
+
+    events.lock().unwrap().clear();
+    baker.bake(&src, generated_dir.join("second.pak"))?;
+
+    assert!(
+        events
+            .lock()
+            .unwrap()
+            .contains(&Event::CacheHit(asset_dir.join("image.png")))
+    );
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}
+
+#[cfg(feature = "bake")]
+#[test]
+fn observer_receives_bake_errors() -> anyhow::Result<()> {
+    let generated_dir =
+        std::env::temp_dir().join(format!("pak-observer-error-{}", std::process::id()));
+    fs::create_dir_all(&generated_dir)?;
+
+    fs::write(generated_dir.join("broken.png"), "not a png")?;
+
+    let src = generated_dir.join("pak.toml");
+    fs::write(&src, "[content]\n\n[[content.group]]\nassets = ['*.png']\n")?;
+
+    let events = Arc::new(Mutex::new(vec![]));
+    observed_baker(&events)
+        .bake(&src, generated_dir.join("broken.pak"))
+        .unwrap_err();
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            Event::Started(generated_dir.join("broken.png")),
+            Event::Error(generated_dir.join("broken.png")),
+        ]
+    );
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}
@@ -0,0 +1,108 @@
+#[cfg(feature = "bake")]
+use {
+    pak::{Pak, PakBuf, PakError, PatchedPak},
+    std::{fs, io::Error, path::PathBuf, sync::LazyLock},
+};
+
+#[cfg(feature = "bake")]
+static CARGO_MANIFEST_DIR: LazyLock<PathBuf> =
+    LazyLock::new(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+#[cfg(feature = "bake")]
+static TESTS_DATA_DIR: LazyLock<PathBuf> = LazyLock::new(|| CARGO_MANIFEST_DIR.join("tests/data"));
+
+#[cfg(feature = "bake")]
+#[test]
+fn patches_store_only_changed_assets() -> Result<(), Error> {
+    let generated_dir = std::env::temp_dir().join(format!("pak-patch-{}", std::process::id()));
+    let asset_dir = generated_dir.join("assets");
+    fs::create_dir_all(&asset_dir)?;
+    fs::copy(
+        TESTS_DATA_DIR.join("scene/material_01.png"),
+        asset_dir.join("bitmap.png"),
+    )?;
+
+    for blob in ["kept", "changed", "removed", "added"] {
+        fs::write(
+            asset_dir.join(format!("{blob}.toml")),
+            format!("[blob]\nsrc = '{blob}.bin'\n"),
+        )?;
+    }
+
+    let bake = |assets: &str, name: &str| {
+        let src = generated_dir.join(format!("{name}.toml"));
+        let dst = generated_dir.join(format!("{name}.pak"));
+        fs::write(&src, format!("[[content.group]]\nassets = [{assets}]\n")).unwrap();
+        PakBuf::bake_with_dir(&src, &dst, &asset_dir).unwrap();
+
+        dst
+    };
+
+    fs::write(asset_dir.join("kept.bin"), "kept payload\n")?;
+    fs::write(asset_dir.join("changed.bin"), "old payload\n")?;
+    fs::write(asset_dir.join("removed.bin"), "removed payload\n")?;
+    let old_dst = bake(
+        "'/bitmap.png', '/changed.toml', '/kept.toml', '/removed.toml'",
+        "old",
+    );
+
+    fs::write(asset_dir.join("changed.bin"), "new payload\n")?;
+    fs::write(asset_dir.join("added.bin"), "removed payload\n")?;
+    let new_dst = bake(
+        "'/added.toml', '/bitmap.png', '/changed.toml', '/kept.toml'",
+        "new",
+    );
+
+    let patch = PakBuf::diff(&mut PakBuf::open(&old_dst)?, &mut PakBuf::open(&new_dst)?)?;
+    assert_eq!(patch.changed_keys().collect::<Vec<_>>(), ["changed"]);
+    assert_eq!(patch.removed_keys().collect::<Vec<_>>(), ["removed"]);
+
+    let patch_dst = generated_dir.join("patch.pak");
+    patch.write(&patch_dst)?;
+    assert!(fs::metadata(&patch_dst)?.len() < fs::metadata(&new_dst)?.len() / 2);
+
+    // Unchanged assets are stored in the base pak only
+    assert!(matches!(
+        PakBuf::open(&patch_dst)?.read_bitmap("bitmap.png"),
+        Err(PakError::UnexpectedBaseData)
+    ));
+
+    let mut new = PakBuf::open(&new_dst)?;
+    let mut patched = PatchedPak::open(&old_dst, &patch_dst)?.with_verify(true);
+    assert_eq!(
+        patched.keys().collect::<Vec<_>>(),
+        new.keys().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        patched.read_bitmap("bitmap.png")?,
+        new.read_bitmap("bitmap.png")?
+    );
+
+    for (blob, payload) in [
+        ("added", "removed payload\n"),
+        ("changed", "new payload\n"),
+        ("kept", "kept payload\n"),
+    ] {
+        assert_eq!(patched.read_blob(blob)?, payload.as_bytes());
+    }
+
+    // Applying the patch reproduces the newer pak
+    let applied_dst = generated_dir.join("applied.pak");
+    patched.write(&applied_dst)?;
+    assert_eq!(fs::read(&applied_dst)?, fs::read(&new_dst)?);
+
+    // Patches may only be layered over the pak they were made against
+    assert!(matches!(
+        PatchedPak::open(&new_dst, &patch_dst),
+        Err(PakError::BaseMismatch { .. })
+    ));
+
+    // Ordinary paks record no base and are not patches
+    assert!(matches!(
+        PatchedPak::open(&old_dst, &new_dst),
+        Err(PakError::MissingPatchBase)
+    ));
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}
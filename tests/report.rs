@@ -0,0 +1,120 @@
+#[cfg(feature = "bake")]
+use {
+    pak::{AssetDetails, AssetKind, PakBuf, bitmap::BitmapFormat, index::IndexType},
+    std::{fs, path::PathBuf, sync::LazyLock},
+};
+
+#[cfg(feature = "bake")]
+static CARGO_MANIFEST_DIR: LazyLock<PathBuf> =
+    LazyLock::new(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+#[cfg(feature = "bake")]
+static TESTS_DATA_DIR: LazyLock<PathBuf> = LazyLock::new(|| CARGO_MANIFEST_DIR.join("tests/data"));
+
+#[cfg(feature = "bake")]
+#[test]
+fn reports_stored_assets() -> anyhow::Result<()> {
+    let generated_dir = std::env::temp_dir().join(format!("pak-report-{}", std::process::id()));
+    let asset_dir = generated_dir.join("assets");
+    fs::create_dir_all(&asset_dir)?;
+
+    for file in [
+        "cube.glb",
+        "material_01.png",
+        "material_01.toml",
+        "material_02.png",
+        "mesh_01.toml",
+        "scene.toml",
+    ] {
+        fs::copy(
+            TESTS_DATA_DIR.join("scene").join(file),
+            asset_dir.join(file),
+        )?;
+    }
+
+    fs::write(
+        asset_dir.join("payload.toml"),
+        "[blob]\nsrc = 'payload.bin'\n",
+    )?;
+    fs::write(asset_dir.join("payload.bin"), "report payload\n".repeat(64))?;
+
+    let src = generated_dir.join("pak.toml");
+    let dst = generated_dir.join("report.pak");
+    fs::write(
+        &src,
+        "[content]\ncompression = 'snap'\n\n[[content.group]]\nassets = ['/material_01.png', \
+         '/material_01.toml', '/mesh_01.toml', '/payload.toml', '/scene.toml']\n",
+    )?;
+    PakBuf::bake_with_dir(&src, &dst, &asset_dir)?;
+
+    let report = PakBuf::open(&dst)?.report()?;
+    let asset = |key| report.assets.iter().find(|asset| asset.key == key).unwrap();
+
+    assert_eq!(
+        report
+            .assets
+            .iter()
+            .map(|asset| asset.kind)
+            .collect::<Vec<_>>(),
+        [
+            AssetKind::Material,
+            AssetKind::Bitmap,
+            AssetKind::Mesh,
+            AssetKind::Blob,
+            AssetKind::Scene
+        ]
+    );
+
+    // Repeated data compresses well
+    let payload = asset("payload");
+    assert_eq!(payload.len, Some(15 * 64 + 8));
+    assert!(payload.ratio().unwrap() < 0.5);
+    assert_eq!(
+        payload.range.as_ref().map(|range| range.end - range.start),
+        payload.stored_len.map(|len| len as u64)
+    );
+    assert!(payload.details.is_none());
+
+    let material = asset("material_01");
+    assert!(material.range.is_none());
+    assert!(material.details.is_none());
+
+    match &asset("material_01.png").details {
+        Some(AssetDetails::Bitmap {
+            format, mip_levels, ..
+        }) => {
+            assert_eq!(*format, BitmapFormat::Rgb);
+            assert_eq!(*mip_levels, 1);
+        }
+        details => panic!("unexpected details {details:?}"),
+    }
+
+    match &asset("mesh_01").details {
+        Some(AssetDetails::Mesh { primitives, .. }) => {
+            assert_eq!(primitives.len(), 1);
+            assert_eq!(primitives[0].vertex_count, 24);
+            assert_eq!(primitives[0].index_type, Some(IndexType::U8));
+            assert_eq!(primitives[0].lod_triangles, [12]);
+        }
+        details => panic!("unexpected details {details:?}"),
+    }
+
+    assert!(matches!(
+        asset("scene").details,
+        Some(AssetDetails::Scene {
+            geometries: 1,
+            refs: 8
+        })
+    ));
+
+    let text = report.to_string();
+    assert!(text.starts_with("compression: Snap\n"));
+    assert!(text.lines().any(|line| line.starts_with("mesh_01\tmesh\t")));
+
+    let json = serde_json::to_value(&report)?;
+    assert_eq!(json["assets"][3]["kind"], "blob");
+    assert_eq!(json["assets"][3]["len"], 15 * 64 + 8);
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}
@@ -0,0 +1,76 @@
+#[cfg(feature = "bake")]
+use {
+    pak::{BitmapId, Pak, PakBuf},
+    std::{fs, path::PathBuf, sync::LazyLock},
+};
+
+#[cfg(feature = "bake")]
+static CARGO_MANIFEST_DIR: LazyLock<PathBuf> =
+    LazyLock::new(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+#[cfg(feature = "bake")]
+static TESTS_DATA_DIR: LazyLock<PathBuf> = LazyLock::new(|| CARGO_MANIFEST_DIR.join("tests/data"));
+
+#[cfg(feature = "bake")]
+#[test]
+fn identical_inputs_bake_identical_paks() -> anyhow::Result<()> {
+    let generated_dir =
+        std::env::temp_dir().join(format!("pak-reproducible-{}", std::process::id()));
+    let asset_dir = generated_dir.join("assets");
+    fs::create_dir_all(&asset_dir)?;
+
+    for file in [
+        "cube.glb",
+        "material_01.png",
+        "material_02.png",
+        "mesh_01.toml",
+        "scene.toml",
+    ] {
+        fs::copy(
+            TESTS_DATA_DIR.join("scene").join(file),
+            asset_dir.join(file),
+        )?;
+    }
+
+    // Each bitmap of this material is baked by its own task
+    let material = "[material]\ncolor = 'material_01.png'\nnormal = 'material_02.png'\nemissive = \
+                    [0.5, 0.25, 0.125]\nmetal = 0.25\nrough = 0.5\n";
+    fs::write(asset_dir.join("material_01.toml"), material)?;
+    fs::write(
+        asset_dir.join("material_03.toml"),
+        material.replace("0.125", "1.0"),
+    )?;
+
+    let src = generated_dir.join("pak.toml");
+    fs::write(
+        &src,
+        "[content]\ncompression = 'snap'\n\n[[content.group]]\nassets = ['*.toml']\nexclude = \
+         ['/mesh_01.toml']\n",
+    )?;
+
+    let bake = |name| -> anyhow::Result<Vec<u8>> {
+        let dst = generated_dir.join(name);
+        PakBuf::bake_with_dir(&src, &dst, &asset_dir)?;
+
+        Ok(fs::read(dst)?)
+    };
+    let first = bake("first.pak")?;
+
+    for idx in 0..8 {
+        assert!(
+            bake("second.pak")? == first,
+            "bake {idx} differs from the first bake"
+        );
+    }
+
+    // Bitmaps are numbered in the order the material lists them
+    let pak = PakBuf::open(generated_dir.join("first.pak"))?;
+    let material = pak.read_material("material_01").unwrap();
+    assert_eq!(material.color, BitmapId(0));
+    assert_eq!(material.normal, Some(BitmapId(1)));
+    assert_eq!(material.emissive, Some(BitmapId(2)));
+    assert_eq!(material.params, Some(BitmapId(3)));
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}
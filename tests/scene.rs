@@ -134,3 +134,38 @@ fn bake_with_generated_content_file() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[cfg(feature = "bake")]
+#[test]
+fn bake_reports_missing_scene_references() -> Result<(), Error> {
+    let generated_dir =
+        std::env::temp_dir().join(format!("pak-missing-refs-{}", std::process::id()));
+    fs::create_dir_all(&generated_dir)?;
+
+    let pak_src = generated_dir.join("pak.toml");
+    let pak_dst = generated_dir.join("scene.pak");
+    fs::write(
+        &pak_src,
+        "[content]\n\n[[content.group]]\nassets = ['scene.toml']\n",
+    )?;
+
+    let bake = |scene: &str| {
+        fs::write(generated_dir.join("scene.toml"), scene).unwrap();
+        let err = PakBuf::bake_with_dir(&pak_src, &pak_dst, &generated_dir).unwrap_err();
+
+        format!("{err:#}")
+    };
+
+    let err =
+        bake("[scene]\n\n[[scene.ref]]\nid = 'hero'\nmaterials = ['missing_material.toml']\n");
+    assert!(err.contains("scene ref `hero`"), "{err}");
+    assert!(err.contains("missing_material.toml"), "{err}");
+
+    let err = bake("[scene]\n\n[[scene.ref]]\nmesh = 'missing_mesh.toml'\n");
+    assert!(err.contains("scene ref 0"), "{err}");
+    assert!(err.contains("missing_mesh.toml"), "{err}");
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}
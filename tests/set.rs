@@ -0,0 +1,100 @@
+#[cfg(feature = "bake")]
+use {
+    pak::{BlobId, Pak, PakBuf, PakError, PakSet},
+    std::{fs, io::Error, path::PathBuf, sync::LazyLock},
+};
+
+#[cfg(feature = "bake")]
+static CARGO_MANIFEST_DIR: LazyLock<PathBuf> =
+    LazyLock::new(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+#[cfg(feature = "bake")]
+static TESTS_DATA_DIR: LazyLock<PathBuf> = LazyLock::new(|| CARGO_MANIFEST_DIR.join("tests/data"));
+
+#[cfg(feature = "bake")]
+#[test]
+fn later_mounts_shadow_earlier_keys() -> Result<(), Error> {
+    let generated_dir = std::env::temp_dir().join(format!("pak-set-{}", std::process::id()));
+    let mod_dir = generated_dir.join("mod");
+    fs::create_dir_all(mod_dir.join("blob"))?;
+
+    // The base pak holds a scene along with its meshes, materials and bitmaps
+    let base_src = generated_dir.join("base.toml");
+    let base_dst = generated_dir.join("base.pak");
+    fs::write(
+        &base_src,
+        "[[content.group]]\nassets = ['/blob/payload.toml', '/scene/scene.toml']\n",
+    )?;
+    PakBuf::bake_with_dir(&base_src, &base_dst, &*TESTS_DATA_DIR).unwrap();
+
+    // The mod pak replaces the base blob using the same key
+    let mod_src = generated_dir.join("mod.toml");
+    let mod_dst = generated_dir.join("mod.pak");
+    fs::write(
+        &mod_src,
+        "[[content.group]]\nassets = ['/blob/payload.toml']\n",
+    )?;
+    fs::write(
+        mod_dir.join("blob/payload.toml"),
+        "[blob]\nsrc = 'payload.bin'\n",
+    )?;
+    fs::write(mod_dir.join("blob/payload.bin"), "modded payload\n")?;
+    PakBuf::bake_with_dir(&mod_src, &mod_dst, &mod_dir).unwrap();
+
+    let mut base = PakBuf::open(&base_dst)?;
+    let mut paks = [&base_dst, &mod_dst, &base_dst]
+        .into_iter()
+        .map(PakBuf::open)
+        .collect::<Result<PakSet, _>>()?;
+
+    assert_eq!(paks.mount_count(), 3);
+    assert_eq!(
+        paks.keys().collect::<Vec<_>>(),
+        base.keys().collect::<Vec<_>>()
+    );
+    assert_eq!(paks.blob_count(), base.blob_count() * 2 + 1);
+    assert_eq!(paks.mesh_count(), base.mesh_count() * 2);
+
+    // Shadowed assets are still readable using their IDs
+    assert_eq!(paks.read_blob_id(BlobId(0))?, b"blob payload\n");
+    assert_eq!(
+        paks.read_blob_id(BlobId(base.blob_count()))?,
+        b"modded payload\n"
+    );
+    assert!(matches!(
+        paks.read_blob_id(BlobId(paks.blob_count())),
+        Err(PakError::InvalidId { .. })
+    ));
+
+    // Keys refer to the last mount and the IDs stored within assets are namespaced to match
+    assert_eq!(paks.read_blob("blob/payload")?, b"blob payload\n");
+    assert_eq!(
+        paks.blob_id("blob/payload"),
+        Some(BlobId(base.blob_count() + 1))
+    );
+
+    let scene = paks.read_scene("scene/scene")?;
+    let base_scene = base.read_scene("scene/scene")?;
+    for (reference, base_reference) in scene.refs().zip(base_scene.refs()) {
+        assert_eq!(
+            reference.mesh().map(|mesh| mesh.0),
+            base_reference.mesh().map(|mesh| mesh.0 + base.mesh_count())
+        );
+
+        for (material, base_material) in
+            reference.materials().iter().zip(base_reference.materials())
+        {
+            assert_eq!(material.0, base_material.0 + base.material_count());
+
+            let material = paks.read_material_id(*material).unwrap();
+            let base_material = base.read_material_id(*base_material).unwrap();
+            assert_eq!(
+                material.color.0,
+                base_material.color.0 + base.bitmap_count()
+            );
+        }
+    }
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+#[cfg(feature = "bake")]
+use {
+    pak::{Pak, PakBuf},
+    std::{fs, path::PathBuf, sync::LazyLock},
+};
+
+#[cfg(feature = "bake")]
+static CARGO_MANIFEST_DIR: LazyLock<PathBuf> =
+    LazyLock::new(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+#[cfg(feature = "bake")]
+static TESTS_DATA_DIR: LazyLock<PathBuf> = LazyLock::new(|| CARGO_MANIFEST_DIR.join("tests/data"));
+
+#[cfg(feature = "bake")]
+#[test]
+fn unpacks_assets_to_standard_files() -> anyhow::Result<()> {
+    let generated_dir = std::env::temp_dir().join(format!("pak-unpack-{}", std::process::id()));
+    let asset_dir = generated_dir.join("assets");
+    fs::create_dir_all(&asset_dir)?;
+
+    for file in [
+        "cube.glb",
+        "material_01.png",
+        "material_01.toml",
+        "material_02.png",
+        "mesh_01.toml",
+        "scene.toml",
+    ] {
+        fs::copy(
+            TESTS_DATA_DIR.join("scene").join(file),
+            asset_dir.join(file),
+        )?;
+    }
+
+    fs::write(
+        asset_dir.join("payload.toml"),
+        "[blob]\nsrc = 'payload.bin'\n",
+    )?;
+    fs::write(asset_dir.join("payload.bin"), "unpacked payload\n")?;
+
+    let src = generated_dir.join("pak.toml");
+    let dst = generated_dir.join("unpack.pak");
+    fs::write(
+        &src,
+        "[[content.group]]\nassets = ['/material_01.png', '/mesh_01.toml', '/payload.toml', \
+         '/scene.toml']\n",
+    )?;
+    PakBuf::bake_with_dir(&src, &dst, &asset_dir)?;
+
+    let unpacked_dir = generated_dir.join("unpacked");
+    let mut pak = PakBuf::open(&dst)?;
+    let mut paths = pak.unpack(&unpacked_dir)?;
+    paths.sort();
+
+    for path in &paths {
+        assert!(path.starts_with(&unpacked_dir));
+        assert!(path.exists());
+    }
+
+    assert_eq!(
+        fs::read(unpacked_dir.join("payload"))?,
+        b"unpacked payload\n"
+    );
+
+    // Bitmaps keep their format and extent
+    let bitmap = pak.read_bitmap("material_01.png")?;
+    let png = image::open(unpacked_dir.join("material_01.png"))?;
+    assert_eq!((png.width(), png.height()), bitmap.extent());
+
+    // Meshes keep their primitives and vertices
+    let mesh = pak.read_mesh("mesh_01")?;
+    let (gltf, buffers, _) = gltf::import(unpacked_dir.join("mesh_01.glb"))?;
+    let gltf_mesh = gltf.meshes().next().unwrap();
+    assert_eq!(gltf_mesh.primitives().len(), mesh.primitives().len());
+
+    for (gltf_primitive, primitive) in gltf_mesh.primitives().zip(mesh.primitives()) {
+        let reader = gltf_primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        assert_eq!(
+            reader.read_positions().unwrap().count(),
+            primitive.vertex_count()
+        );
+        assert_eq!(
+            reader
+                .read_indices()
+                .unwrap()
+                .into_u32()
+                .collect::<Vec<_>>(),
+            primitive.lods()[0].as_u32()
+        );
+    }
+
+    // Scenes refer to the unpacked meshes by path
+    let scene = pak.read_scene("scene")?;
+    let toml = fs::read_to_string(unpacked_dir.join("scene.toml"))?;
+    let table = toml.parse::<toml::Table>()?;
+    let refs = table["scene"]["ref"].as_array().unwrap();
+    assert_eq!(refs.len(), scene.refs().len());
+    assert_eq!(refs[0]["id"].as_str(), Some("mesh-with-one-material"));
+    assert_eq!(refs[0]["mesh"].as_str(), Some("/mesh_01.glb"));
+    assert_eq!(refs[0]["translation"][2].as_float(), Some(3.0));
+
+    let data = refs
+        .iter()
+        .find(|reference| reference["id"].as_str() == Some("just data"))
+        .unwrap();
+    assert_eq!(data["data"]["another-value"].as_str(), Some("foo"));
+    assert_eq!(data["data"]["bar"].as_array().unwrap().len(), 4);
+
+    fs::remove_dir_all(generated_dir)?;
+
+    Ok(())
+}